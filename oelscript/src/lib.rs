@@ -0,0 +1,117 @@
+// errors are reported where they happen, by `response!`, so `Err(())` only tells callers to stop
+#![allow(clippy::result_unit_err, clippy::unit_arg)]
+
+extern crate colored;
+extern crate unicode_width;
+
+#[path = "øl/mod.rs"]
+#[macro_use]
+pub mod øl;
+
+pub mod repl;
+
+use std::panic::{ self, AssertUnwindSafe };
+
+use self::øl::source::Source;
+use self::øl::parser::Parser;
+use self::øl::compiler::{ self, Compiler };
+use self::øl::types::Checker;
+use self::øl::vm::{ CodeGen, Vm, Globals, Limits, Value };
+
+pub use self::øl::error::{ Diagnostic, Severity };
+pub use self::øl::lexer::{ Token, TokenType, Pos };
+pub use self::øl::parser::{ Statement, StatementNode, Expression, ExpressionNode, Operator };
+
+// What one of the `*_str` functions produced, diagnostics are kept even when it succeeded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output<T> {
+  pub result:      Option<T>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<T> Output<T> {
+  pub fn is_ok(&self) -> bool {
+    self.result.is_some()
+  }
+}
+
+// Tokens of `code` without whitespace and comments, `name` is the file diagnostics point at
+pub fn tokenize_str(name: &str, code: &str) -> Output<Vec<Token>> {
+  let source = Source::from(name, code.to_string());
+
+  run(|| tokenize(&source))
+}
+
+pub fn parse_str(name: &str, code: &str) -> Output<Vec<Statement>> {
+  let source = Source::from(name, code.to_string());
+
+  run(|| Parser::new(tokenize(&source)?, &source).parse().ok())
+}
+
+// JavaScript compiled from `code`
+pub fn compile_str(name: &str, code: &str) -> Output<String> {
+  let source = Source::from(name, code.to_string());
+
+  run(|| {
+    let ast = Parser::new(tokenize(&source)?, &source).parse().ok()?;
+
+    match panic::catch_unwind(AssertUnwindSafe(|| Compiler { source: &source }.compile(&ast))) {
+      Ok(compiled) => Some(compiled),
+      Err(_)       => {
+        response!(øl::error::Response::Wrong("this can't be compiled to JavaScript yet"), source.file);
+
+        None
+      },
+    }
+  })
+}
+
+// TypeScript declarations of what `code` defines, typed as far as inference gets
+pub fn declarations_str(name: &str, code: &str) -> Output<String> {
+  let source = Source::from(name, code.to_string());
+
+  run(|| {
+    let ast = Parser::new(tokenize(&source)?, &source).parse().ok()?;
+
+    let mut checker = Checker::new(&source);
+
+    // type errors are for `check --types` to report
+    let _ = øl::error::capture(|| checker.check(&ast));
+
+    Some(compiler::declarations(&ast, &checker))
+  })
+}
+
+// Runs `code` against `globals` within `limits`, yielding the value of a trailing expression
+pub fn run_str(name: &str, code: &str, globals: &mut Globals, limits: Limits) -> Output<Value> {
+  let source = Source::from(name, code.to_string());
+
+  run(|| {
+    let ast       = Parser::new(tokenize(&source)?, &source).parse().ok()?;
+    let prototype = CodeGen::new(&source).compile(&ast).ok()?;
+
+    Vm::new(globals).with_limits(limits).run(prototype).ok()
+  })
+}
+
+
+
+fn tokenize(source: &Source) -> Option<Vec<Token>> {
+  let mut tokens = Vec::new();
+
+  for token in øl::lexer::Lexer::default(source.content.chars().collect(), source) {
+    tokens.push(token.ok()?)
+  }
+
+  Some(tokens)
+}
+
+// Collects what `f` reports instead of printing it
+fn run<T, F: FnOnce() -> Option<T>>(f: F) -> Output<T> {
+  let (result, diagnostics) = øl::error::capture(f);
+
+  Output {
+    result,
+    diagnostics,
+  }
+}
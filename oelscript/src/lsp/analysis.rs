@@ -0,0 +1,366 @@
+use std::mem;
+use std::panic::{ self, AssertUnwindSafe };
+
+use super::øl::error::{ self, Diagnostic, Severity };
+use super::øl::source::*;
+use super::øl::lexer::*;
+use super::øl::parser::*;
+use super::øl::compiler::*;
+use super::øl::prelude::{ self, Builtin };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+  Function,
+  Variable,
+  Constant,
+  Parameter,
+  Record,
+  Enum,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+  pub name:       String,
+  pub kind:       SymbolKind,
+  pub pos:        Pos, // the name at the definition
+  pub span:       Pos, // the whole definition
+  pub params:     Vec<String>, // or the fields of a record, or the variants of an enum
+  pub parent:     Option<usize>,
+  pub references: Vec<Pos>,
+}
+
+
+
+// Everything the server knows about one open document
+pub struct Analysis {
+  pub source:      Source,
+  pub tokens:      Vec<Token>,
+  pub diagnostics: Vec<Diagnostic>,
+  pub symbols:     Vec<Symbol>,
+  pub builtins:    Vec<(Pos, &'static Builtin)>, // identifiers calling into the prelude
+}
+
+impl Analysis {
+  pub fn new(path: &str, text: String) -> Self {
+    let source = Source::from(path, text);
+
+    let (tokens, mut diagnostics) = error::capture(|| {
+      let mut tokens = Vec::new();
+
+      for token in Lexer::lossless(source.content.chars().collect(), &source) {
+        match token {
+          Ok(token) => tokens.push(token),
+          Err(_)    => return None,
+        }
+      }
+
+      Some(tokens)
+    });
+
+    let tokens = match tokens {
+      Some(tokens) => tokens,
+      None         => return Analysis { source, tokens: Vec::new(), diagnostics, symbols: Vec::new(), builtins: Vec::new() },
+    };
+
+    let significant = tokens.iter().filter(|token| !token.token_type.is_trivia()).cloned().collect::<Vec<Token>>();
+
+    let (ast, parse_diagnostics) = error::capture(|| {
+      panic::catch_unwind(AssertUnwindSafe(|| Parser::new(significant, &source).parse()))
+    });
+
+    diagnostics.extend(parse_diagnostics);
+
+    let ast = match ast {
+      Ok(Ok(ast)) => ast,
+      Ok(Err(_))  => return Analysis { source, tokens, diagnostics, symbols: Vec::new(), builtins: Vec::new() },
+      Err(_)      => {
+        diagnostics.push(Self::crash("the parser crashed on this file", &source));
+
+        return Analysis { source, tokens, diagnostics, symbols: Vec::new(), builtins: Vec::new() }
+      },
+    };
+
+    let compiled = panic::catch_unwind(AssertUnwindSafe(|| Compiler { source: &source }.compile(&ast)));
+
+    if compiled.is_err() {
+      diagnostics.push(Self::crash("this file can't be compiled to JavaScript yet", &source))
+    }
+
+    let (symbols, builtins) = {
+      let mut resolver = Resolver::new(&tokens);
+
+      resolver.resolve_body(&ast, None);
+      (resolver.symbols, resolver.builtins)
+    };
+
+    Analysis {
+      source,
+      tokens,
+      diagnostics,
+      symbols,
+      builtins,
+    }
+  }
+
+  fn crash(message: &str, source: &Source) -> Diagnostic {
+    Diagnostic {
+      severity: Severity::Wrong,
+      message:  message.to_string(),
+      file:     source.file.0.clone(),
+      pos:      None,
+      labels:   Vec::new(),
+    }
+  }
+
+
+
+  // The symbol defined or referenced at `offset`
+  pub fn symbol_at(&self, offset: usize) -> Option<&Symbol> {
+    let covers = |pos: &Pos| pos.0 <= offset && offset <= pos.1;
+
+    self.symbols.iter().find(|symbol| covers(&symbol.pos) || symbol.references.iter().any(&covers))
+  }
+
+  pub fn builtin_at(&self, offset: usize) -> Option<(Pos, &'static Builtin)> {
+    self.builtins.iter().find(|&&(pos, _)| pos.0 <= offset && offset <= pos.1).cloned()
+  }
+
+  pub fn symbol_kind_at(&self, pos: &Pos) -> Option<SymbolKind> {
+    self.symbols.iter().find(|symbol| symbol.pos == *pos || symbol.references.contains(pos)).map(|symbol| symbol.kind)
+  }
+}
+
+
+
+// Collects definitions per scope and resolves identifiers against them, definitions are hoisted like JS `var`
+struct Resolver<'r> {
+  tokens:   &'r [Token],
+  symbols:  Vec<Symbol>,
+  builtins: Vec<(Pos, &'static Builtin)>,
+  scopes:   Vec<Vec<usize>>,
+  parent:   Option<usize>, // the function whose body is being resolved
+}
+
+impl<'r> Resolver<'r> {
+  fn new(tokens: &'r [Token]) -> Self {
+    Resolver {
+      tokens,
+      symbols:  Vec::new(),
+      builtins: Vec::new(),
+      scopes:   vec!(Vec::new()),
+      parent:   None,
+    }
+  }
+
+  // Declares what `body` defines before resolving it, so uses can come before definitions
+  fn resolve_body(&mut self, body: &'r [Statement], parent: Option<usize>) {
+    let outer = mem::replace(&mut self.parent, parent);
+
+    visit::definitions(body, &mut |defined| self.declare(defined));
+
+    // resolving never fails, unknown names may come from the prelude or elsewhere
+    let _ = self.body(body);
+
+    self.parent = outer;
+  }
+
+  fn declare(&mut self, defined: Defined) {
+    let parent = self.parent;
+
+    match defined {
+      Defined::Statement(name, statement) => match statement.node {
+        StatementNode::Function(ref function, ref params, ..) => {
+          let params = params.iter().map(|param| if param.rest { format!("...{}", param.name) } else { param.name.clone() }).collect();
+
+          self.define(name, SymbolKind::Function, function.pos, statement.pos, params, parent);
+        },
+
+        StatementNode::Record(_, ref fields) => {
+          let fields = fields.iter().map(|field| field.name.clone()).collect();
+          let pos    = self.name_pos(statement.pos.0 + "record".len(), name);
+
+          self.define(name, SymbolKind::Record, pos, statement.pos, fields, parent);
+        },
+
+        StatementNode::Enum(_, ref variants) => {
+          let variants = variants.iter().map(|variant| variant.name.clone()).collect();
+          let pos      = self.name_pos(statement.pos.0 + "enum".len(), name);
+
+          self.define(name, SymbolKind::Enum, pos, statement.pos, variants, parent);
+        },
+
+        ref node => {
+          let kind = if let StatementNode::Variable(.., true) = *node { SymbolKind::Constant } else { SymbolKind::Variable };
+          let pos  = self.name_pos(statement.pos.0, name);
+
+          self.define(name, kind, pos, statement.pos, Vec::new(), parent);
+        },
+      },
+
+      Defined::Loop(name, statement) => {
+        let pos = self.name_pos(statement.pos.0 + "for".len(), name);
+
+        self.define(name, SymbolKind::Variable, pos, pos, Vec::new(), parent);
+      },
+
+      // the fields an arm binds are variables of the scope it's in
+      Defined::Binding(name, pos) => {
+        self.define(name, SymbolKind::Variable, pos, pos, Vec::new(), parent);
+      },
+
+      Defined::Caught(name, catch) => {
+        let pos = self.name_pos(catch.pos.0 + "fang".len(), name);
+
+        self.define(name, SymbolKind::Variable, pos, catch.pos, Vec::new(), parent);
+      },
+    }
+  }
+
+  fn define(&mut self, name: &str, kind: SymbolKind, pos: Pos, span: Pos, params: Vec<String>, parent: Option<usize>) -> usize {
+    self.symbols.push(
+      Symbol {
+        name: name.to_string(),
+        kind,
+        pos,
+        span,
+        params,
+        parent,
+        references: Vec::new(),
+      }
+    );
+
+    let index = self.symbols.len() - 1;
+
+    self.scopes.last_mut().unwrap().push(index);
+
+    index
+  }
+
+  // Position of the first `name` identifier token from `start` on
+  fn name_pos(&self, start: usize, name: &str) -> Pos {
+    self.tokens.iter()
+      .find(|token| token.pos.0 >= start && token.token_type == TokenType::Identifier && token.lexeme == name)
+      .map_or(Pos(start, start), |token| token.pos)
+  }
+}
+
+impl<'r> Visit<'r> for Resolver<'r> {
+  fn statement(&mut self, statement: &'r Statement) -> Result<(), ()> {
+    let (name, params, body) = match statement.node {
+      StatementNode::Function(ref name, ref params, _, ref body, ..) => (name, params, body),
+      _                                                              => return walk_statement(self, statement),
+    };
+
+    let function = match name.node {
+      ExpressionNode::Identifier(_) => self.symbols.iter().position(|symbol| symbol.pos == name.pos),
+      _                             => {
+        self.expression(name)?;
+        None
+      },
+    };
+
+    self.scopes.push(Vec::new());
+
+    // a default sees the params before it
+    for param in params.iter() {
+      if let Some(ref default) = param.default {
+        self.expression(default)?
+      }
+
+      self.define(&param.name, SymbolKind::Parameter, param.pos, param.pos, Vec::new(), function);
+    }
+
+    self.resolve_body(body, function);
+
+    self.scopes.pop();
+
+    Ok(())
+  }
+
+  fn expression(&mut self, expression: &'r Expression) -> Result<(), ()> {
+    let name = match expression.node {
+      ExpressionNode::Identifier(ref name) => name,
+      _                                    => return walk_expression(self, expression),
+    };
+
+    let found = self.scopes.iter().rev()
+      .filter_map(|scope| scope.iter().rev().find(|index| self.symbols[**index].name == *name))
+      .next()
+      .cloned();
+
+    match found {
+      Some(index) => if self.symbols[index].pos != expression.pos {
+        self.symbols[index].references.push(expression.pos)
+      },
+
+      // anything the program doesn't define itself may still come from the prelude
+      None => if let Some(builtin) = prelude::builtin(name) {
+        self.builtins.push((expression.pos, builtin))
+      },
+    }
+
+    Ok(())
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CODE: &str = "øl x = 1\nøl f(x) =\n  øl x + y\nøl y = f(x)\n";
+
+  // Offset of the `nth` whole identifier `name`, counting from 0
+  fn at(name: &str, nth: usize) -> usize {
+    let source = Source::from("<test>", CODE.to_string());
+
+    let token = Lexer::default(CODE.chars().collect(), &source).filter_map(Result::ok).filter(|token| token.lexeme == name).nth(nth);
+
+    token.unwrap().pos.0
+  }
+
+  fn text(analysis: &Analysis, pos: &Pos) -> (usize, usize) {
+    analysis.source.location(pos.0)
+  }
+
+  #[test]
+  fn defines_what_is_referenced() {
+    let analysis = Analysis::new("<test>", CODE.to_string());
+
+    let outer = analysis.symbol_at(at("x", 3)).unwrap();
+
+    assert_eq!((outer.kind, text(&analysis, &outer.pos)), (SymbolKind::Variable, (1, 4)));
+
+    // the param shadows the outer `x` within the function
+    let param = analysis.symbol_at(at("x", 2)).unwrap();
+
+    assert_eq!((param.kind, text(&analysis, &param.pos)), (SymbolKind::Parameter, (2, 6)));
+
+    // `y` is hoisted, so the function sees the one defined after it
+    let y = analysis.symbol_at(at("y", 0)).unwrap();
+
+    assert_eq!((y.kind, text(&analysis, &y.pos)), (SymbolKind::Variable, (4, 4)))
+  }
+
+  #[test]
+  fn references_every_use() {
+    let analysis = Analysis::new("<test>", CODE.to_string());
+
+    let references = |offset| analysis.symbol_at(offset).unwrap().references.iter().map(|pos| text(&analysis, pos)).collect::<Vec<_>>();
+
+    assert_eq!(references(at("x", 0)), vec!((4, 10)));
+    assert_eq!(references(at("x", 1)), vec!((3, 6)));
+    assert_eq!(references(at("f", 0)), vec!((4, 8)));
+    assert_eq!(references(at("y", 1)), vec!((3, 10)))
+  }
+
+  #[test]
+  fn resolves_the_prelude_when_nothing_shadows_it() {
+    let analysis = Analysis::new("<test>", String::from("print(1)\nøl len(x) =\n  øl x\nlen(2)\n"));
+
+    assert_eq!(analysis.builtin_at(0).map(|(_, builtin)| builtin.name), Some("print"));
+    assert!(analysis.builtin_at(analysis.source.content.rfind("len").unwrap()).is_none());
+    assert_eq!(analysis.symbol_at(analysis.source.content.rfind("len").unwrap()).map(|symbol| symbol.kind), Some(SymbolKind::Function))
+  }
+}
@@ -0,0 +1,431 @@
+#[macro_use] extern crate serde_json;
+
+extern crate oelscript;
+
+mod protocol;
+mod analysis;
+
+use std::io::{ self, BufRead, Write };
+use std::process;
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use oelscript::øl;
+
+use self::øl::error::Severity;
+use self::øl::lexer::TokenType;
+use self::analysis::*;
+
+const TOKEN_TYPES:     &[&str] = &["keyword", "variable", "function", "parameter", "number", "string", "operator", "comment", "type"];
+const TOKEN_MODIFIERS: &[&str] = &["defaultLibrary"];
+
+struct Server {
+  documents: HashMap<String, Analysis>,
+  shutdown:  bool,
+}
+
+impl Server {
+  fn new() -> Self {
+    Server {
+      documents: HashMap::new(),
+      shutdown:  false,
+    }
+  }
+
+
+
+  // Answers a request or notification with the messages to send back
+  fn handle(&mut self, message: Value) -> Vec<Value> {
+    let method = message["method"].as_str().unwrap_or("").to_string();
+    let params = &message["params"];
+
+    let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+
+    let result = match method.as_str() {
+      "initialize" => json!({
+        "capabilities": {
+          "textDocumentSync":       1,
+          "definitionProvider":     true,
+          "referencesProvider":     true,
+          "hoverProvider":          true,
+          "documentSymbolProvider": true,
+          "semanticTokensProvider": {
+            "legend": { "tokenTypes": TOKEN_TYPES, "tokenModifiers": TOKEN_MODIFIERS },
+            "full":   true,
+          },
+        },
+        "serverInfo": { "name": "ølscript-lsp" },
+      }),
+
+      "shutdown" => {
+        self.shutdown = true;
+        Value::Null
+      },
+
+      "exit" => process::exit(if self.shutdown { 0 } else { 1 }),
+
+      "textDocument/didOpen" => {
+        let text = params["textDocument"]["text"].as_str().unwrap_or("").to_string();
+
+        return vec!(self.update(uri, text))
+      },
+
+      "textDocument/didChange" => {
+        let text = match params["contentChanges"].as_array().and_then(|changes| changes.last()) {
+          Some(change) => change["text"].as_str().unwrap_or("").to_string(),
+          None         => return Vec::new(),
+        };
+
+        return vec!(self.update(uri, text))
+      },
+
+      "textDocument/didClose" => {
+        self.documents.remove(&uri);
+
+        return vec!(Self::notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] })))
+      },
+
+      "textDocument/definition"          => self.with_symbol(&uri, params, |analysis, symbol| Self::location(analysis, &uri, &symbol.pos)),
+      "textDocument/references"          => self.references(&uri, params),
+      "textDocument/hover"               => self.hover(&uri, params),
+      "textDocument/documentSymbol"      => self.document_symbols(&uri),
+      "textDocument/semanticTokens/full" => self.semantic_tokens(&uri),
+
+      _ => if message["id"].is_null() {
+        return Vec::new()
+      } else {
+        return vec!(json!({
+          "jsonrpc": "2.0",
+          "id":      message["id"],
+          "error":   { "code": -32601, "message": format!("unsupported method `{}`", method) },
+        }))
+      },
+    };
+
+    if message["id"].is_null() {
+      Vec::new()
+    } else {
+      vec!(json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }))
+    }
+  }
+
+  fn update(&mut self, uri: String, text: String) -> Value {
+    let analysis = Analysis::new(&uri, text);
+
+    let diagnostics = analysis.diagnostics.iter().map(|diagnostic| {
+      let range = match diagnostic.pos {
+        Some(ref pos) => protocol::to_range(&analysis.source, pos),
+        None          => json!({ "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } }),
+      };
+
+      let related = diagnostic.labels.iter().map(|(pos, message)| json!({
+        "location": Self::location(&analysis, &uri, pos),
+        "message":  message,
+      })).collect::<Vec<Value>>();
+
+      let severity = match diagnostic.severity {
+        Severity::Wrong => 1,
+        Severity::Weird => 2,
+        Severity::Note  => 3,
+      };
+
+      json!({
+        "range":              range,
+        "severity":           severity,
+        "source":             "ølscript",
+        "message":            diagnostic.message,
+        "relatedInformation": related,
+      })
+    }).collect::<Vec<Value>>();
+
+    self.documents.insert(uri.clone(), analysis);
+
+    Self::notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": diagnostics }))
+  }
+
+
+
+  fn with_symbol<F: Fn(&Analysis, &Symbol) -> Value>(&self, uri: &str, params: &Value, f: F) -> Value {
+    let analysis = match self.documents.get(uri) {
+      Some(analysis) => analysis,
+      None           => return Value::Null,
+    };
+
+    let offset = protocol::to_offset(&analysis.source, &params["position"]);
+
+    match analysis.symbol_at(offset) {
+      Some(symbol) => f(analysis, symbol),
+      None         => Value::Null,
+    }
+  }
+
+  fn references(&self, uri: &str, params: &Value) -> Value {
+    let declaration = params["context"]["includeDeclaration"].as_bool().unwrap_or(true);
+
+    self.with_symbol(uri, params, |analysis, symbol| {
+      let mut locations = Vec::new();
+
+      if declaration {
+        locations.push(Self::location(analysis, uri, &symbol.pos))
+      }
+
+      for pos in symbol.references.iter() {
+        locations.push(Self::location(analysis, uri, pos))
+      }
+
+      Value::Array(locations)
+    })
+  }
+
+  fn hover(&self, uri: &str, params: &Value) -> Value {
+    let analysis = match self.documents.get(uri) {
+      Some(analysis) => analysis,
+      None           => return Value::Null,
+    };
+
+    let offset = protocol::to_offset(&analysis.source, &params["position"]);
+
+    if let Some((pos, builtin)) = analysis.builtin_at(offset) {
+      return json!({
+        "contents": { "kind": "markdown", "value": format!("```ølscript\n{}({})\n```\n{}", builtin.name, builtin.params.join(", "), builtin.doc) },
+        "range":    protocol::to_range(&analysis.source, &pos),
+      })
+    }
+
+    match analysis.symbol_at(offset) {
+      Some(symbol) => Self::hover_symbol(analysis, symbol),
+      None         => Value::Null,
+    }
+  }
+
+  fn hover_symbol(analysis: &Analysis, symbol: &Symbol) -> Value {
+    let signature = match symbol.kind {
+      SymbolKind::Function  => format!("øl {}({})", symbol.name, symbol.params.join(", ")),
+      SymbolKind::Variable  => format!("øl {}", symbol.name),
+      SymbolKind::Constant  => format!("iskold øl {}", symbol.name),
+      SymbolKind::Parameter => format!("(parameter) {}", symbol.name),
+      SymbolKind::Record    => format!("record {} = {{ {} }}", symbol.name, symbol.params.join(", ")),
+      SymbolKind::Enum      => format!("enum {} = {{ {} }}", symbol.name, symbol.params.join(", ")),
+    };
+
+    json!({
+      "contents": { "kind": "markdown", "value": format!("```ølscript\n{}\n```", signature) },
+      "range":    protocol::to_range(&analysis.source, &symbol.pos),
+    })
+  }
+
+  fn document_symbols(&self, uri: &str) -> Value {
+    match self.documents.get(uri) {
+      Some(analysis) => Value::Array(Self::symbols_of(analysis, None)),
+      None           => Value::Null,
+    }
+  }
+
+  fn symbols_of(analysis: &Analysis, parent: Option<usize>) -> Vec<Value> {
+    analysis.symbols.iter().enumerate()
+      .filter(|&(_, symbol)| symbol.parent == parent && symbol.kind != SymbolKind::Parameter)
+      .map(|(index, symbol)| {
+        let kind = match symbol.kind {
+          SymbolKind::Function => 12,
+          SymbolKind::Constant => 14,
+          SymbolKind::Record   => 23,
+          SymbolKind::Enum     => 10,
+          _                    => 13,
+        };
+
+        let detail = match symbol.kind {
+          SymbolKind::Function                  => format!("({})", symbol.params.join(", ")),
+          SymbolKind::Record | SymbolKind::Enum => format!("{{ {} }}", symbol.params.join(", ")),
+          _                                     => String::new(),
+        };
+
+        json!({
+          "name":           symbol.name,
+          "detail":         detail,
+          "kind":           kind,
+          "range":          protocol::to_range(&analysis.source, &symbol.span),
+          "selectionRange": protocol::to_range(&analysis.source, &symbol.pos),
+          "children":       Self::symbols_of(analysis, Some(index)),
+        })
+      }).collect()
+  }
+
+  // Token types come from the lexer, identifiers are refined by what they resolve to
+  fn semantic_tokens(&self, uri: &str) -> Value {
+    let analysis = match self.documents.get(uri) {
+      Some(analysis) => analysis,
+      None           => return Value::Null,
+    };
+
+    let mut data: Vec<u64> = Vec::new();
+    let mut last = (0, 0);
+
+    for token in analysis.tokens.iter() {
+      let mut modifiers = 0;
+
+      let token_type = match token.token_type {
+        TokenType::Keyword | TokenType::Bool => 0,
+        TokenType::Identifier if token.lexeme == "nil" => 0,
+        TokenType::Int | TokenType::Float    => 4,
+        TokenType::Str                       => 5,
+        TokenType::Operator                  => 6,
+        TokenType::Comment                   => 7,
+        TokenType::Identifier                => match analysis.symbol_kind_at(&token.pos) {
+          Some(SymbolKind::Function)  => 2,
+          Some(SymbolKind::Parameter) => 3,
+          Some(SymbolKind::Record)    => 8,
+          Some(SymbolKind::Enum)      => 8,
+          Some(_)                     => 1,
+
+          None => if analysis.builtin_at(token.pos.0).is_some() {
+            modifiers = 1;
+            2
+          } else {
+            1
+          },
+        },
+        _ => continue,
+      };
+
+      let start = protocol::to_position(&analysis.source, token.pos.0);
+      let end   = protocol::to_position(&analysis.source, token.pos.1);
+
+      let line      = start["line"].as_u64().unwrap();
+      let character = start["character"].as_u64().unwrap();
+
+      // tokens spanning lines can't be expressed
+      if end["line"].as_u64().unwrap() != line {
+        continue
+      }
+
+      let delta_line = line - last.0;
+      let delta      = if delta_line == 0 { character - last.1 } else { character };
+
+      data.extend(&[delta_line, delta, end["character"].as_u64().unwrap() - character, token_type, modifiers]);
+
+      last = (line, character)
+    }
+
+    json!({ "data": data })
+  }
+
+
+
+  fn location(analysis: &Analysis, uri: &str, pos: &øl::lexer::Pos) -> Value {
+    json!({ "uri": uri, "range": protocol::to_range(&analysis.source, pos) })
+  }
+
+  fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+  }
+}
+
+
+
+fn main() {
+  let stdin  = io::stdin();
+  let stdout = io::stdout();
+
+  if serve(&mut stdin.lock(), &mut stdout.lock()).is_err() {
+    process::exit(1)
+  }
+}
+
+// Answers messages until the input is closed, ones that can't be read are answered with a parse error
+fn serve<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> io::Result<()> {
+  let mut server = Server::new();
+
+  while let Some(message) = protocol::read_message(input) {
+    let responses = match message {
+      Ok(message) => server.handle(message),
+
+      // without an id to answer to, the error goes out with a null one
+      Err(reason) => vec!(json!({
+        "jsonrpc": "2.0",
+        "id":      Value::Null,
+        "error":   { "code": -32700, "message": reason },
+      })),
+    };
+
+    for response in responses {
+      protocol::write_message(output, &response)?
+    }
+  }
+
+  Ok(())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn open(server: &mut Server, text: &str) {
+    server.handle(json!({
+      "jsonrpc": "2.0",
+      "method":  "textDocument/didOpen",
+      "params":  { "textDocument": { "uri": "file:///test.øl", "text": text } },
+    }));
+  }
+
+  #[test]
+  fn encodes_semantic_tokens_relative_to_the_last() {
+    let mut server = Server::new();
+
+    open(&mut server, "øl x = \"ø\"\nprint(x)\n");
+
+    // line, start, length, type and modifiers, the first two from the token before
+    assert_eq!(server.semantic_tokens("file:///test.øl"), json!({ "data": [
+      0, 0, 2, 0, 0,
+      0, 3, 1, 1, 0,
+      0, 4, 3, 5, 0,
+      1, 0, 5, 2, 1,
+      0, 6, 1, 1, 0,
+    ] }))
+  }
+
+  #[test]
+  fn answers_definitions_and_references() {
+    let mut server = Server::new();
+
+    open(&mut server, "øl f(x) =\n  øl x\nf(1)\n");
+
+    let request = |method: &str| json!({
+      "jsonrpc": "2.0",
+      "id":      1,
+      "method":  method,
+      "params":  {
+        "textDocument": { "uri": "file:///test.øl" },
+        "position":     { "line": 2, "character": 0 },
+        "context":      { "includeDeclaration": false },
+      },
+    });
+
+    let range = |line: u64, start: u64, end: u64| json!({
+      "uri":   "file:///test.øl",
+      "range": { "start": { "line": line, "character": start }, "end": { "line": line, "character": end } },
+    });
+
+    assert_eq!(server.handle(request("textDocument/definition"))[0]["result"], range(0, 3, 4));
+    assert_eq!(server.handle(request("textDocument/references"))[0]["result"], json!([range(2, 0, 1)]))
+  }
+
+  #[test]
+  fn goes_on_after_messages_it_cant_read() {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" }).to_string();
+    let input   = format!("Content-Length: 8\r\n\r\nnot jsonContent-Length: {}\r\n\r\n{}", request.len(), request);
+
+    let mut output = Vec::new();
+
+    serve(&mut io::Cursor::new(input.into_bytes()), &mut output).unwrap();
+
+    let mut output = io::Cursor::new(output);
+
+    let error = protocol::read_message(&mut output).unwrap().unwrap();
+
+    assert_eq!((error["id"].clone(), error["error"]["code"].clone()), (Value::Null, json!(-32700)));
+    assert_eq!(protocol::read_message(&mut output).unwrap(), Ok(json!({ "jsonrpc": "2.0", "id": 1, "result": null })))
+  }
+}
@@ -0,0 +1,153 @@
+use std::io::{ self, BufRead, Write };
+
+use serde_json::Value;
+
+use super::øl::source::Source;
+use super::øl::lexer::Pos;
+
+// Reads one `Content-Length` framed JSON-RPC message, None once the input is closed and an error
+// for a message that can't be read, which is skipped so the next one can be
+pub fn read_message<R: BufRead>(input: &mut R) -> Option<Result<Value, String>> {
+  let mut length = None;
+
+  loop {
+    let mut header = String::new();
+
+    if input.read_line(&mut header).ok()? == 0 {
+      return None
+    }
+
+    let header = header.trim_end();
+
+    if header.is_empty() {
+      break
+    }
+
+    if header.to_lowercase().starts_with("content-length:") {
+      length = Some(header["content-length:".len() ..].trim().parse::<usize>())
+    }
+  }
+
+  let length = match length {
+    Some(Ok(length)) => length,
+    Some(Err(_))     => return Some(Err(String::from("the `Content-Length` isn't a number"))),
+    None             => return Some(Err(String::from("the message has no `Content-Length`"))),
+  };
+
+  let mut content = vec![0; length];
+
+  input.read_exact(&mut content).ok()?;
+
+  Some(serde_json::from_slice(&content).map_err(|error| format!("the message isn't JSON: {}", error)))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+  let content = message.to_string();
+
+  write!(output, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+
+  output.flush()
+}
+
+
+
+// LSP columns count UTF-16 code units
+pub fn to_position(source: &Source, offset: usize) -> Value {
+  let (line, column) = source.location(offset);
+
+  let character = source.line(line).chars().take(column - 1).map(|c| c.len_utf16()).sum::<usize>();
+
+  json!({ "line": line - 1, "character": character })
+}
+
+pub fn to_range(source: &Source, pos: &Pos) -> Value {
+  json!({ "start": to_position(source, pos.0), "end": to_position(source, pos.1) })
+}
+
+pub fn to_offset(source: &Source, position: &Value) -> usize {
+  let line      = position["line"].as_u64().unwrap_or(0) as usize + 1;
+  let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+  let mut units  = 0;
+  let mut column = 1;
+
+  for c in source.line(line).chars() {
+    if units >= character {
+      break
+    }
+
+    units  += c.len_utf16();
+    column += 1;
+  }
+
+  source.offset(line, column)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::io::Cursor;
+
+  fn read(input: &str) -> Vec<Result<Value, String>> {
+    let mut input    = Cursor::new(input.as_bytes().to_vec());
+    let mut messages = Vec::new();
+
+    while let Some(message) = read_message(&mut input) {
+      messages.push(message)
+    }
+
+    messages
+  }
+
+  #[test]
+  fn reads_framed_messages() {
+    let input = "Content-Length: 8\r\n\r\n{\"a\":1}\ncontent-length: 7\r\nContent-Type: application/json\r\n\r\n{\"b\":2}";
+
+    assert_eq!(read(input), vec!(Ok(json!({ "a": 1 })), Ok(json!({ "b": 2 }))))
+  }
+
+  #[test]
+  fn writes_what_it_reads() {
+    let mut output = Vec::new();
+
+    write_message(&mut output, &json!({ "ø": "😀" })).unwrap();
+
+    let written = String::from_utf8(output).unwrap();
+
+    assert!(written.starts_with("Content-Length: 13\r\n\r\n"));
+    assert_eq!(read(&written), vec!(Ok(json!({ "ø": "😀" }))))
+  }
+
+  #[test]
+  fn skips_messages_it_cant_read() {
+    let input = "Content-Length: many\r\n\r\nContent-Length: 8\r\n\r\nnot jsonContent-Length: 7\r\n\r\n{\"a\":1}";
+
+    assert_eq!(read(input), vec!(
+      Err(String::from("the `Content-Length` isn't a number")),
+      Err(String::from("the message isn't JSON: expected ident at line 1 column 2")),
+      Ok(json!({ "a": 1 })),
+    ))
+  }
+
+  #[test]
+  fn stops_when_the_input_ends() {
+    assert_eq!(read(""), vec!());
+    assert_eq!(read("Content-Length: 20\r\n\r\n{\"a\""), vec!())
+  }
+
+  #[test]
+  fn counts_columns_in_utf16() {
+    let source = Source::from("<test>", String::from("øl 😀 = 1\nx"));
+    let equals = source.content.find('=').unwrap();
+
+    assert_eq!(to_position(&source, equals), json!({ "line": 0, "character": 6 }));
+    assert_eq!(to_position(&source, source.content.len()), json!({ "line": 1, "character": 1 }));
+
+    assert_eq!(to_offset(&source, &json!({ "line": 0, "character": 6 })), equals);
+    assert_eq!(to_offset(&source, &json!({ "line": 0, "character": 1 })), "ø".len());
+    assert_eq!(to_offset(&source, &json!({ "line": 1, "character": 0 })), source.content.len() - 1)
+  }
+}
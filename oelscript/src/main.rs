@@ -1,135 +1,140 @@
-#![feature(non_ascii_idents)]
-#![feature(proc_macro_hygiene, decl_macro)]
+extern crate oelscript;
 
-#[macro_use] extern crate rocket;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
 
-extern crate rocket_cors;
+use oelscript::øl;
+use oelscript::repl::Repl;
 
-use rocket::http::Method;
-use rocket::response::{Responder, Response};
-use rocket::http::{ContentType, Status};
-use std::io::Cursor;
+use self::øl::error;
+use self::øl::source::*;
+use self::øl::lexer::Lexer;
+use self::øl::parser::{ Parser, Statement };
+use self::øl::compiler::{ self, Compiler };
+use self::øl::formatter;
+use self::øl::types::Checker;
 
-use rocket_cors::{AllowedOrigins, AllowedHeaders};
+const USAGE: &str = "\
+usage: ølscript <command> [files]
 
-extern crate colored;
+  fmt [--check] <files>    format files in place, or list the ones that would change
+  check [--types] <files>  report what's wrong with files without running them
+  build <files>            write the JavaScript and TypeScript declarations of files
+  repl                     run ØlScript line by line";
 
-mod øl;
+fn main() {
+  let args = env::args().skip(1).collect::<Vec<String>>();
+
+  match args.first().map(|arg| arg.as_str()) {
+    Some("fmt")   => process::exit(format_files(&args[1 ..])),
+    Some("check") => process::exit(check_files(&args[1 ..])),
+    Some("build") => process::exit(build_files(&args[1 ..])),
+    Some("repl")  => Repl::new().run(),
+    _             => {
+      eprintln!("{}", USAGE);
+      process::exit(2)
+    },
+  }
+}
 
-mod config;
-use config::*;
+// `ølscript fmt [--check] <files>`, formats files in place or with `--check` lists the ones that would change
+fn format_files(args: &[String]) -> i32 {
+  let check = args.iter().any(|arg| arg == "--check");
 
-use self::øl::source::*;
-use self::øl::lexer::*;
-use self::øl::parser::*;
-use self::øl::compiler::*;
+  let mut status = 0;
 
-#[post("/compile", data = "<code>")]
-fn compile<'c>(code: String) -> Result<Response<'c>, ()> {
-  let source = Source::from("<main>", code.lines().map(|x| x.into()).collect::<Vec<String>>());
-  let lexer  = Lexer::default(code.chars().collect(), &source);
+  for path in args.iter().filter(|arg| !arg.starts_with("--")) {
+    let source = Source::new(path.clone());
 
-  let mut body = String::new();
+    match formatter::format(&source) {
+      Ok(ref formatted) if *formatted == source.content => (),
 
-  let mut tokens = Vec::new();
+      Ok(formatted) => if check {
+        println!("would reformat {}", path);
+        status = 1
+      } else {
+        fs::write(path, formatted).unwrap()
+      },
 
-  for token_result in lexer {
-    if let Ok(token) = token_result {
-      tokens.push(token)
-    } else {
-      return Response::build()
-          .status(Status::Ok)
-          .sized_body(Cursor::new("".to_string()))
-          .header(ContentType::Plain)
-          .raw_header("Access-Control-Allow-Origin", "*")
-          .ok()
+      Err(_) => status = 1,
     }
   }
 
-  let mut parser  = Parser::new(tokens, &source);
+  status
+}
 
-  match parser.parse() {
-    Ok(ref ast) => {
-      let mut compiler = Compiler { source: &source };
+// `ølscript check [--types] <files>`, reports what's wrong with files without running them
+fn check_files(args: &[String]) -> i32 {
+  let types = args.iter().any(|arg| arg == "--types");
 
-      let res = compiler.compile(ast);
+  let mut status = 0;
 
-      println!("{}", res);
+  for path in args.iter().filter(|arg| !arg.starts_with("--")) {
+    let source = Source::new(path.clone());
 
-      body = res;
-    },
-    Err(ref e) => (),
+    let checked = match parse(&source) {
+      Some(ref ast) if types => Checker::new(&source).check(ast).is_ok(),
+      Some(_)                => true,
+      None                   => false,
+    };
+
+    if !checked {
+      status = 1
+    }
   }
 
-  Response::build()
-      .status(Status::Ok)
-      .sized_body(Cursor::new(body.to_string()))
-      .header(ContentType::Plain)
-      .raw_header("Access-Control-Allow-Origin", "*")
-      .ok()
+  status
 }
 
-fn main() {
-  let (app_config, rocket_config) = config::config().map_err(|x| format!("{}", x)).unwrap();
-
-  let options = rocket_cors::Cors {
-    allowed_origins: AllowedOrigins::all(),
-    allowed_methods: vec![Method::Get, Method::Post, Method::Put].into_iter().map(From::from).collect(),
-    allowed_headers: AllowedHeaders::all(),
-    allow_credentials: true,
-    ..Default::default()
-  };
-
-  rocket_cors::catch_all_options_routes();
-
-  rocket::ignite()
-      .manage(app_config)
-      .manage(options)
-      .mount("/", rocket_cors::catch_all_options_routes())
-      .mount("/", routes![compile]).launch()
-  ;
-}
+// `ølscript build <files>`, writes the JavaScript of `x.øl` to `x.js` with its TypeScript declarations in `x.d.ts`
+fn build_files(args: &[String]) -> i32 {
+  let mut status = 0;
 
-fn test() {
-  let test= r#"
-// øltionary
-iskold øl foo = {
-  lager: "god"
-  stick_a_finger_in_the_soil: "rigtig god og fin"
-}
+  for path in args.iter() {
+    let source = Source::new(path.clone());
+
+    let ast = match parse(&source) {
+      Some(ast) => ast,
+      None      => {
+        status = 1;
+        continue
+      },
+    };
 
-// øltion
-øl main(x) =
-  print("bob: ", x)
+    let mut checker = Checker::new(&source);
 
-  øl x + 100
+    // the JavaScript is written anyway, the declarations tell which types are unknown
+    if checker.check(&ast).is_err() {
+      status = 1
+    }
 
-foo = 10
-  "#;
+    let path = Path::new(path);
 
-  let source = Source::from("<main>", test.lines().map(|x| x.into()).collect::<Vec<String>>());
-  let lexer  = Lexer::default(test.chars().collect(), &source);
+    let written = [
+      (path.with_extension("js"),   Compiler { source: &source }.compile(&ast)),
+      (path.with_extension("d.ts"), compiler::declarations(&ast, &checker)),
+    ];
 
-  let mut tokens = Vec::new();
-  
-  for token_result in lexer {
-    if let Ok(token) = token_result {
-      tokens.push(token)
-    } else {
-      return
+    for (path, content) in written.iter() {
+      if let Err(reason) = fs::write(path, content) {
+        error::report(&error::Response::Wrong(format!("can't write `{}`: {}", path.display(), reason)), &source.file, None);
+
+        status = 1
+      }
     }
   }
 
-  println!("{:#?}", tokens);
-
-  let mut parser  = Parser::new(tokens, &source);
+  status
+}
 
-  match parser.parse() {
-    Ok(ref ast) => {
-      let mut compiler = Compiler { source: &source };
+fn parse(source: &Source) -> Option<Vec<Statement>> {
+  let mut tokens = Vec::new();
 
-      println!("{}", compiler.compile(ast))
-    },
-    _ => (),
+  for token in Lexer::default(source.content.chars().collect(), source) {
+    tokens.push(token.ok()?)
   }
+
+  Parser::new(tokens, source).parse().ok()
 }
@@ -0,0 +1,273 @@
+use std::io::{ self, BufRead, Write };
+use std::panic::{ self, AssertUnwindSafe };
+
+use super::øl::error;
+use super::øl::source::*;
+use super::øl::lexer::*;
+use super::øl::parser::*;
+use super::øl::compiler::*;
+use super::øl::vm::*;
+
+const HELP: &str = "\
+:tokens    tokens of the last input
+:ast       syntax tree of the last input
+:js        JavaScript compiled from the last input, `asynk` functions only run as that
+:bytecode  bytecode compiled from the last input
+:help      this
+:quit      leave";
+
+// Everything accepted so far, lexed again as one source so positions keep pointing at the right lines
+pub struct Repl {
+  history: String,
+  last:    Option<usize>, // where the last parsed input starts within `history`
+  globals: Globals,
+}
+
+impl Default for Repl {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Repl {
+  pub fn new() -> Self {
+    Repl {
+      history: String::new(),
+      last:    None,
+      globals: Globals::new(),
+    }
+  }
+
+
+
+  pub fn run(&mut self) {
+    let stdin     = io::stdin();
+    let mut input = stdin.lock();
+
+    let mut buffer = String::new();
+
+    loop {
+      print!("{}", if buffer.is_empty() { "øl> " } else { "... " });
+      io::stdout().flush().unwrap();
+
+      let mut line = String::new();
+
+      if input.read_line(&mut line).unwrap_or(0) == 0 {
+        println!();
+        break
+      }
+
+      let line = line.trim_end();
+
+      if buffer.is_empty() && line.trim_start().starts_with(':') {
+        match line.trim() {
+          ":quit" | ":q" => break,
+          ":help"        => println!("{}", HELP),
+          command        => self.meta(command),
+        }
+
+        continue
+      }
+
+      buffer.push_str(line);
+      buffer.push('\n');
+
+      if Self::is_complete(&buffer) {
+        match self.eval(&buffer) {
+          Some(Value::Nil) | None => (),
+          Some(value)             => println!("{}", value.repr()),
+        }
+
+        buffer.clear()
+      }
+    }
+  }
+
+  // Whether `buffer` can be run as is, or needs more lines first. Decided on tokens, so
+  // brackets, `=` and `:` within strings and comments don't count
+  fn is_complete(buffer: &str) -> bool {
+    let source = Source::from("<repl>", buffer.to_string());
+
+    let (tokens, diagnostics) = error::capture(|| {
+      Lexer::default(source.content.chars().collect(), &source).collect::<Result<Vec<Token>, ()>>()
+    });
+
+    let tokens = match tokens {
+      Ok(tokens) => tokens,
+
+      // a string may go on over the next lines, anything else is for `eval` to report
+      Err(_) => return !diagnostics.iter().any(|diagnostic| diagnostic.message.starts_with("unterminated")),
+    };
+
+    let symbol = |token: &Token, symbols: &[&str]| token.token_type == TokenType::Symbol && symbols.contains(&token.lexeme.as_str());
+
+    let opened = tokens.iter().filter(|token| symbol(token, &["(", "[", "{"])).count();
+    let closed = tokens.iter().filter(|token| symbol(token, &[")", "]", "}"])).count();
+
+    if opened > closed {
+      return false
+    }
+
+    // the last token of every line, `None` for empty ones
+    let mut lines = vec!(None);
+
+    for token in tokens.iter() {
+      if token.token_type == TokenType::EOL {
+        lines.push(None)
+      } else {
+        *lines.last_mut().unwrap() = Some(token)
+      }
+    }
+
+    // the buffer always ends in a newline, which leaves nothing after it
+    lines.pop();
+
+    let opens = |line: &Option<&Token>| line.is_some_and(|token| symbol(token, &["=", ":"]));
+
+    if lines.last().is_some_and(opens) {
+      return false
+    }
+
+    // an indented block goes on until an empty line
+    !lines.iter().any(&opens) || lines.last() == Some(&None)
+  }
+
+
+
+  // Runs `input` against what was bound before, yielding its value when it ran
+  fn eval(&mut self, input: &str) -> Option<Value> {
+    let start = self.history.len();
+
+    let mut history = self.history.clone();
+    history.push_str(input);
+
+    let source = Source::from("<repl>", history);
+
+    let ast = Self::parse(&source, start)?;
+
+    // kept even when running failed, functions bound before the failure point into it, and
+    // `:js` still shows what can't run here like `asynk` functions
+    let result = match CodeGen::new(&source).compile(&ast) {
+      Ok(prototype) => Vm::new(&mut self.globals).run(prototype).ok(),
+      Err(_)        => None,
+    };
+
+    self.last    = Some(start);
+    self.history = source.content;
+
+    result
+  }
+
+  fn tokenize(source: &Source, start: usize) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+
+    for token in Lexer::default(source.content.chars().collect(), source) {
+      match token {
+        Ok(token) => if token.pos.0 >= start {
+          tokens.push(token)
+        },
+
+        Err(_) => return None,
+      }
+    }
+
+    Some(tokens)
+  }
+
+  fn parse(source: &Source, start: usize) -> Option<Vec<Statement>> {
+    let tokens = Self::tokenize(source, start)?;
+
+    Parser::new(tokens, source).parse().ok()
+  }
+
+
+
+  fn meta(&self, command: &str) {
+    let start = match self.last {
+      Some(start) => start,
+      None        => return println!("nothing has been entered yet"),
+    };
+
+    let source = Source::from("<repl>", self.history.clone());
+
+    match command {
+      ":tokens" => if let Some(tokens) = Self::tokenize(&source, start) {
+        for token in tokens.iter() {
+          println!("{:?} {:?}", token.token_type, token.lexeme)
+        }
+      },
+
+      ":ast" => if let Some(ast) = Self::parse(&source, start) {
+        println!("{:#?}", ast)
+      },
+
+      ":js" => if let Some(ast) = Self::parse(&source, start) {
+        match panic::catch_unwind(AssertUnwindSafe(|| Compiler { source: &source }.compile(&ast))) {
+          Ok(js) => print!("{}", js),
+          Err(_) => println!("this can't be compiled to JavaScript yet"),
+        }
+      },
+
+      ":bytecode" => if let Some(ast) = Self::parse(&source, start) {
+        if let Ok(prototype) = CodeGen::new(&source).compile(&ast) {
+          print!("{}", disassemble(&prototype))
+        }
+      },
+
+      _ => println!("unknown command `{}`, try :help", command),
+    }
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn waits_for_open_brackets() {
+    assert!(!Repl::is_complete("øl xs = [1,\n"));
+    assert!(Repl::is_complete("øl xs = [1,\n  2]\n"))
+  }
+
+  #[test]
+  fn waits_for_blocks_to_end() {
+    assert!(!Repl::is_complete("øl f(x) =\n"));
+    assert!(!Repl::is_complete("øl f(x) =\n  øl x\n"));
+    assert!(Repl::is_complete("øl f(x) =\n  øl x\n\n"))
+  }
+
+  #[test]
+  fn ignores_strings_and_comments() {
+    assert!(Repl::is_complete("print(\"(:\")\n"));
+    assert!(Repl::is_complete("øl x = 1 // [ or =\n"));
+    assert!(Repl::is_complete("print(\"a = \")\n"))
+  }
+
+  #[test]
+  fn waits_for_open_strings() {
+    assert!(!Repl::is_complete("print(\"two\n"));
+    assert!(Repl::is_complete("print(\"two\nlines\")\n"))
+  }
+
+  #[test]
+  fn keeps_bindings_between_inputs() {
+    let mut repl = Repl::new();
+
+    assert_eq!(repl.eval("øl x = 20\n"), Some(Value::Nil));
+    assert_eq!(repl.eval("øl f(y) =\n  øl x + y\n\n"), Some(Value::Nil));
+    assert_eq!(repl.eval("f(22)\n").map(|value| value.repr()), Some("42".to_string()))
+  }
+
+  #[test]
+  fn reports_async_functions_but_keeps_them_for_js() {
+    let mut repl = Repl::new();
+
+    let (result, diagnostics) = error::capture(|| repl.eval("asynk øl f(url) =\n  øl vent url\n\n"));
+
+    assert_eq!(result, None);
+    assert_eq!(diagnostics.first().map(|diagnostic| diagnostic.message.as_str()), Some("`asynk` functions have no event loop to wait on here, they only run as JavaScript"));
+    assert_eq!(repl.last, Some(0))
+  }
+}
@@ -12,6 +12,9 @@ pub struct AppConfig {
     pub cors_allow_methods: String,
     pub cors_allow_headers: String,
     pub environment_name: String,
+    pub run_fuel: u64,
+    pub run_timeout: Duration,
+    pub run_memory: usize,
 }
 
 impl Default for AppConfig {
@@ -22,6 +25,9 @@ impl Default for AppConfig {
             cors_allow_methods: "*".to_owned(),
             cors_allow_headers: "*".to_owned(),
             environment_name: String::from("unconfigured"),
+            run_fuel: 10_000_000,
+            run_timeout: Duration::seconds(2),
+            run_memory: 16 * 1024 * 1024,
         }
     }
 }
@@ -0,0 +1,155 @@
+#![feature(proc_macro_hygiene, decl_macro)]
+
+#[macro_use] extern crate rocket;
+#[macro_use] extern crate serde_json;
+
+extern crate rocket_cors;
+
+use rocket::http::Method;
+use rocket::response::{Responder, Response};
+use rocket::http::{ContentType, Status};
+use rocket::State;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use rocket_cors::{AllowedOrigins, AllowedHeaders};
+
+use std::thread;
+
+extern crate oelscript;
+
+mod config;
+use config::*;
+
+use oelscript::øl;
+
+use self::øl::source::*;
+use self::øl::formatter;
+use self::øl::vm::{ Globals, Limits, Value };
+
+#[post("/compile", data = "<code>")]
+fn compile<'c>(code: String) -> Result<Response<'c>, ()> {
+  let body = oelscript::compile_str("<main>", &code).result.unwrap_or_default();
+
+  Response::build()
+      .status(Status::Ok)
+      .sized_body(Cursor::new(body))
+      .header(ContentType::Plain)
+      .raw_header("Access-Control-Allow-Origin", "*")
+      .ok()
+}
+
+#[post("/format", data = "<code>")]
+fn format<'c>(code: String) -> Result<Response<'c>, ()> {
+  let source = Source::from("<main>", code);
+
+  let (status, body) = match formatter::format(&source) {
+    Ok(formatted) => (Status::Ok, formatted),
+    Err(_)        => (Status::BadRequest, String::new()),
+  };
+
+  Response::build()
+      .status(status)
+      .sized_body(Cursor::new(body))
+      .header(ContentType::Plain)
+      .raw_header("Access-Control-Allow-Origin", "*")
+      .ok()
+}
+
+// How much stack a run gets, the same however rocket set up its own threads
+const RUN_STACK: usize = 64 * 1024 * 1024;
+
+// Runs a snippet on the server instead of `eval`ing its JavaScript in the browser
+#[post("/run", data = "<code>")]
+fn run<'r>(code: String, config: State<AppConfig>) -> Result<Response<'r>, ()> {
+  let limits = Limits {
+    fuel:    Some(config.run_fuel),
+    timeout: config.run_timeout.to_std().ok(),
+    memory:  Some(config.run_memory),
+  };
+
+  let printed = config.run_memory;
+
+  let body = thread::Builder::new()
+      .stack_size(RUN_STACK)
+      .spawn(move || run_code(code, limits, printed))
+      .map_err(|_| ())?
+      .join()
+      .map_err(|_| ())?;
+
+  Response::build()
+      .status(Status::Ok)
+      .sized_body(Cursor::new(body.to_string()))
+      .header(ContentType::JSON)
+      .raw_header("Access-Control-Allow-Origin", "*")
+      .ok()
+}
+
+// The response of `/run`, at most `printed` bytes of what was printed are kept
+fn run_code(code: String, limits: Limits, printed: usize) -> serde_json::Value {
+  let stdout = Rc::new(RefCell::new(String::new()));
+
+  let mut globals = Globals::new();
+
+  {
+    let stdout = stdout.clone();
+
+    globals.native("print", move |_, args| {
+      let args = args.iter().map(|arg| format!("{}", arg)).collect::<Vec<String>>();
+
+      let mut stdout = stdout.borrow_mut();
+
+      stdout.push_str(&args.join(" "));
+      stdout.push('\n');
+
+      if stdout.len() > printed {
+        return Err("printed too much".into())
+      }
+
+      Ok(Value::Nil)
+    });
+  }
+
+  let output = oelscript::run_str("<run>", &code, &mut globals, limits);
+  let source = Source::from("<run>", code);
+
+  let error = output.diagnostics.iter().find(|diagnostic| diagnostic.severity == oelscript::Severity::Wrong).map(|diagnostic| {
+    let pos = diagnostic.pos.map(|pos| {
+      let (line, column) = source.location(pos.0);
+
+      json!({ "start": pos.0, "end": pos.1, "line": line, "column": column })
+    });
+
+    json!({ "message": diagnostic.message, "pos": pos })
+  });
+
+  let stdout = stdout.borrow().clone();
+
+  json!({
+    "stdout": stdout,
+    "value":  output.result.map(|value| value.repr()),
+    "error":  error,
+  })
+}
+
+fn main() {
+  let (app_config, rocket_config) = config::config().map_err(|x| format!("{}", x)).unwrap();
+
+  let options = rocket_cors::Cors {
+    allowed_origins: AllowedOrigins::all(),
+    allowed_methods: vec![Method::Get, Method::Post, Method::Put].into_iter().map(From::from).collect(),
+    allowed_headers: AllowedHeaders::all(),
+    allow_credentials: true,
+    ..Default::default()
+  };
+
+  rocket_cors::catch_all_options_routes();
+
+  rocket::ignite()
+      .manage(app_config)
+      .manage(options)
+      .mount("/", rocket_cors::catch_all_options_routes())
+      .mount("/", routes![compile, format, run]).launch()
+  ;
+}
@@ -1,38 +1,74 @@
 use super::*;
-use super::super::error::Response::Wrong;
 
 pub struct Compiler<'c> {
     pub source: &'c Source,
 }
 
 impl<'c> Compiler<'c> {
-    pub fn compile(&mut self, ast: &'c Vec<Statement>) -> String {
-        let mut result = String::new();
+    pub fn compile(&mut self, ast: &'c [Statement]) -> String {
+        // definitions of the prelude functions the program calls
+        let mut result = prelude::runtime_for(ast);
 
         for statement in ast.iter() {
-            result.push_str(&self.generate_statement(&statement));
-            result.push_str(";\n");
+            result.push_str(&self.generate_line(statement, ""));
         }
 
         result
     }
 
+    // A statement ending in `;`, type aliases are only for the checker and leave no line at all
+    fn generate_line(&mut self, statement: &Statement, indent: &str) -> String {
+        match statement.node {
+            StatementNode::TypeAlias(..) => String::new(),
+            _                            => format!("{}{};\n", indent, self.generate_statement(statement)),
+        }
+    }
+
     fn generate_statement(&mut self, statement: &Statement) -> String {
         use self::StatementNode::*;
 
         match statement.node {
             Expression(ref expression) => self.generate_expression(expression),
-            Variable(ref left, ref right, is_const) => if is_const {
+            Variable(ref left, _, ref right, is_const) => if is_const {
                 format!("const {} = {}", left, self.generate_expression(right))
             } else {
                 format!("var {} = {}", left, self.generate_expression(right))
             },
             Assignment(ref left, ref right) => self.generate_assignment(left, right),
-            Function(ref name, ref params, ref body) => {
-                let mut result = format!("let {} = function(", self.generate_expression(name));
+            // JS evaluates what's assigned to once as well, `++` has no operator of its own there so helpers take the place
+            Compound(ref left, ref op, ref right) => {
+                let right = self.generate_expression(right);
+
+                match (op, &left.node) {
+                    (Operator::Concat, ExpressionNode::Index(indexed, index, _)) => {
+                        format!("concat_at$({}, {}, {})", self.generate_expression(indexed), self.generate_expression(index), right)
+                    },
+
+                    (Operator::Concat, _) => {
+                        let left = self.generate_expression(left);
+
+                        format!("{} = concat$({}, {})", left, left, right)
+                    },
+
+                    _ => format!("{} {}= {}", self.generate_expression(left), self.generate_operator(op), right),
+                }
+            },
+            Function(ref name, ref params, _, ref body, is_async, is_generator) => {
+                let keyword = match (is_async, is_generator) {
+                    (false, false) => "function",
+                    (false, true)  => "function*",
+                    (true,  false) => "async function",
+                    (true,  true)  => "async function*",
+                };
+
+                let mut result = format!("let {} = {}(", self.generate_expression(name), keyword);
 
                 for (i, param) in params.iter().enumerate() {
-                    result.push_str(&param);
+                    if param.rest {
+                        result.push_str("...")
+                    }
+
+                    result.push_str(&param.name);
 
                     if i < params.len() - 1 {
                         result.push_str(", ")
@@ -41,12 +77,18 @@ impl<'c> Compiler<'c> {
 
                 result.push_str(") {\n");
 
+                // like in records, null arguments get the defaults too
+                for param in params.iter() {
+                    if let Some(ref default) = param.default {
+                        result.push_str(&format!("if ({0} == null) {0} = {1};\n", param.name, self.generate_expression(default)))
+                    }
+                }
+
                 for e in body.iter() {
-                    result.push_str(&self.generate_statement(e));
-                    result.push_str(";\n");
+                    result.push_str(&self.generate_line(e, ""));
                 }
 
-                result.push_str("}");
+                result.push('}');
 
                 result
             },
@@ -55,7 +97,7 @@ impl<'c> Compiler<'c> {
                 let mut result = format!("if ({}) {{", self.generate_expression(condition));
 
                 for statement in body {
-                    result.push_str(&format!("\t{};\n", self.generate_statement(statement)))
+                    result.push_str(&self.generate_line(statement, "\t"))
                 }
 
                 result.push('}');
@@ -64,7 +106,7 @@ impl<'c> Compiler<'c> {
                     result.push_str("else {");
 
                     for statement in body.0.iter() {
-                        result.push_str(&format!("\t{};\n", self.generate_statement(statement)))
+                        result.push_str(&self.generate_line(statement, "\t"))
                     }
 
                     result.push_str("}\n");
@@ -75,39 +117,188 @@ impl<'c> Compiler<'c> {
                 result
             },
 
+            // errors JavaScript raises itself are caught as their message, like the VM does
+            Try(ref body, ref catch, ref finally) => {
+                let mut result = String::from("try {\n");
+
+                for statement in body.iter() {
+                    result.push_str(&self.generate_line(statement, "\t"))
+                }
+
+                result.push('}');
+
+                if let Some(ref catch) = *catch {
+                    match catch.name {
+                        Some(ref name) => result.push_str(&format!(" catch ({}) {{\n{} = {} instanceof Error ? {}.message : {};\n", name, name, name, name, name)),
+                        None           => result.push_str(" catch {\n"),
+                    }
+
+                    for statement in catch.body.iter() {
+                        result.push_str(&self.generate_line(statement, "\t"))
+                    }
+
+                    result.push('}')
+                }
+
+                if let Some((ref body, _)) = *finally {
+                    result.push_str(" finally {\n");
+
+                    for statement in body.iter() {
+                        result.push_str(&self.generate_line(statement, "\t"))
+                    }
+
+                    result.push('}')
+                }
+
+                result
+            },
+
+            Throw(ref value) => format!("throw {}", self.generate_expression(value)),
+
+            // arrays, strings and generators are all iterable in JavaScript
+            For(ref name, ref iterable, ref body) => {
+                let mut result = match iterable.node {
+                    // counting, without making the array of the range
+                    ExpressionNode::Range(Some(ref start), Some(ref end), inclusive) => {
+                        let start = self.generate_expression(start);
+                        let end   = self.generate_bound(end, inclusive);
+
+                        format!("for (var {0} = {1}, {0}$end = {2}; {0} < {0}$end; {0}++) {{\n", name, start, end)
+                    },
+
+                    _ => format!("for (var {} of {}) {{\n", name, self.generate_expression(iterable)),
+                };
+
+                for statement in body.iter() {
+                    result.push_str(&self.generate_line(statement, "\t"))
+                }
+
+                result.push('}');
+
+                result
+            },
+
+            Break => String::from("break"),
+            Skip  => String::from("continue"),
+
             Return(ref expr) => {
                 format!("return {}", self.generate_expression(&expr.clone().unwrap()))
             },
 
+            // a factory function, missing and null arguments get the defaults
+            Record(ref name, ref fields) => {
+                let params = fields.iter().map(|field| field.name.clone()).collect::<Vec<String>>();
+
+                let mut result = format!("let {} = function({}) {{\n", name, params.join(", "));
+
+                result.push_str("return {\n");
+
+                for field in fields.iter() {
+                    let value = match field.default {
+                        Some(ref default) => format!("{} == null ? {} : {}", field.name, self.generate_expression(default), field.name),
+                        None              => field.name.clone(),
+                    };
+
+                    result.push_str(&self.make_line(&format!("{}: {},", field.name, value)))
+                }
+
+                result.push_str("};\n}");
+
+                result
+            },
+
+            // an object of constructors and of the variants without fields
+            Enum(ref name, ref variants) => {
+                let mut result = format!("let {} = {{\n", name);
+
+                for variant in variants.iter() {
+                    let mut tagged = format!("{}: \"{}\"", TAG, variant.name);
+
+                    for field in variant.fields.iter() {
+                        tagged.push_str(&format!(", {}: {}", field.name, field.name))
+                    }
+
+                    let value = if variant.fields.is_empty() {
+                        format!("{{ {} }}", tagged)
+                    } else {
+                        let params = variant.fields.iter().map(|field| field.name.clone()).collect::<Vec<String>>();
+
+                        format!("function({}) {{ return {{ {} }} }}", params.join(", "), tagged)
+                    };
+
+                    result.push_str(&self.make_line(&format!("{}: {},", variant.name, value)))
+                }
+
+                result.push('}');
+
+                result
+            },
+
+            // fields are bound by position, they come right after the tag
+            Match(ref subject, ref arms) => {
+                let mut result = format!("{{\nconst match$ = {};\n", self.generate_expression(subject));
+
+                for (i, arm) in arms.iter().enumerate() {
+                    if i > 0 {
+                        result.push_str(" else ")
+                    }
+
+                    match arm.pattern {
+                        Pattern::Variant(_, ref name, ref bindings) => {
+                            result.push_str(&format!("if (match$.{} === \"{}\") {{\n", TAG, name));
+
+                            for (j, (binding, _)) in bindings.iter().enumerate() {
+                                result.push_str(&format!("var {} = Object.values(match$)[{}];\n", binding, j + 1))
+                            }
+                        },
+
+                        Pattern::Wildcard => result.push_str("{\n"),
+                    }
+
+                    for statement in arm.body.iter() {
+                        result.push_str(&self.generate_line(statement, "\t"))
+                    }
+
+                    result.push('}')
+                }
+
+                result.push_str("\n}");
+
+                result
+            },
+
+            // types only matter to the checker
+            TypeAlias(..) => String::new(),
+
             _ => unreachable!()
         }
     }
 
     fn generate_expression(&mut self, expression: &Expression) -> String {
         use self::ExpressionNode::*;
-        use std::string;
 
         match expression.node {
             Binary(ref left, ref op, ref right) => {
-                let mut result = string::String::new();
-
-                result.push_str(
-                    &format!(
-                        "({} {} {})",
-                        self.generate_expression(&left),
-                        self.generate_operator(&op),
-                        self.generate_expression(&right),
-                    )
-                );
-
-                result
+                let left  = self.generate_expression(left);
+                let right = self.generate_expression(right);
+
+                // JS has no operators of its own for these
+                match *op {
+                    Operator::Concat => format!("concat$({}, {})", left, right),
+                    Operator::PipeR  => format!("{}({})", right, left),
+                    Operator::PipeL  => format!("{}({})", left, right),
+                    _                => format!("({} {} {})", left, self.generate_operator(op), right),
+                }
             },
 
             Table(ref table) => {
                 let mut result = "{ ".to_string();
 
                 for (key, value) in table.iter() {
-                    result.push_str(&format!("{}: {}, ", key, self.generate_expression(&value)))
+                    match value.node {
+                        Spread(_) => result.push_str(&format!("{}, ", self.generate_expression(value))),
+                        _         => result.push_str(&format!("{}: {}, ", key, self.generate_expression(value))),
+                    }
                 }
 
                 result.push('}');
@@ -116,12 +307,7 @@ impl<'c> Compiler<'c> {
             },
 
             Call(ref called, ref args) => {
-                let mut caller = self.generate_expression(called);
-                let mut result = format!("{}(", caller);
-
-                if let Index(ref left, ..) = called.node {
-                    caller = self.generate_expression(left)
-                }
+                let mut result = format!("{}{}(", self.generate_expression(called), Self::link(called));
 
                 for (i, arg) in args.iter().enumerate() {
                     result.push_str(&self.generate_expression(arg));
@@ -136,12 +322,25 @@ impl<'c> Compiler<'c> {
                 result
             },
 
+            // the defaults first, then the given fields over them
+            Construct(ref record, ref fields) => {
+                let mut result = format!("Object.assign({}(), {{ ", self.generate_expression(record));
+
+                for (key, value) in fields.iter() {
+                    result.push_str(&format!("{}: {}, ", key, self.generate_expression(value)))
+                }
+
+                result.push_str("})");
+
+                result
+            },
+
             Array(ref content) => {
                 let mut result = "[\n".to_string();
 
                 for (i, arg) in content.iter().enumerate() {
                     let value    = self.generate_expression(arg);
-                    let mut line = format!("{}", value);
+                    let mut line = value.to_string();
 
                     if i < content.len() - 1 {
                         line.push(',')
@@ -150,37 +349,83 @@ impl<'c> Compiler<'c> {
                     result.push_str(&self.make_line(&line));
                 }
 
-                result.push_str("]");
+                result.push(']');
 
                 result
             },
 
             Index(ref source, ref index, _) => {
+                let link   = Self::link(source);
+                let dot    = if link.is_empty() { "." } else { link };
                 let source = self.generate_expression(source);
 
-                if let Identifier(ref name) = index.node {
-                    format!("{}.{}", source, name)
-                } else {
-                    let right = self.generate_expression(index);
+                match index.node {
+                    Range(ref start, ref end, inclusive) => {
+                        let start = start.as_ref().map_or(String::from("0"), |start| self.generate_expression(start));
 
-                    format!("{}[{}]", source, right)
+                        match *end {
+                            Some(ref end) => format!("{}{}slice({}, {})", source, dot, start, self.generate_bound(end, inclusive)),
+                            None          => format!("{}{}slice({})", source, dot, start),
+                        }
+                    },
+
+                    Str(ref name) if name.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                        format!("{}{}{}", source, dot, name)
+                    },
+
+                    _ => {
+                        let right = self.generate_expression(index);
+
+                        format!("{}{}[{}]", source, link, right)
+                    },
                 }
             },
 
+            // the `?.` goes on whatever comes after it
+            Optional(ref n) => self.generate_expression(n),
+
             Int(ref n)        => format!("{}", n),
             Float(ref n)      => format!("{}", n),
             Bool(ref n)       => format!("{}", n),
             Str(ref n)        => format!("\"{}\"", n),
-            Identifier(ref n) => format!("{}", n),
+            Identifier(ref n) => n.to_string(),
+
+            Neg(ref n)                  => format!("(-{})", self.generate_expression(n)),
+            Not(ref n)                  => format!("(!{})", self.generate_expression(n)),
+            Await(ref n)                => format!("(await {})", self.generate_expression(n)),
+            Yield(ref n)                => format!("(yield {})", self.generate_expression(n)),
+            Spread(ref n)               => format!("...{}", self.generate_expression(n)),
 
-            Neg(ref n)                  => format!("-{}", self.generate_expression(n)),
-            Not(ref n)                  => format!("not {}", self.generate_expression(n)),
+            Range(Some(ref start), Some(ref end), inclusive) => {
+                let start = self.generate_expression(start);
 
-            Empty => String::from("null"),
+                format!("range$({}, {})", start, self.generate_bound(end, inclusive))
+            },
+
+            Nil | Empty => String::from("null"),
             _     => String::new()
         }
     }
 
+    // `?.` right after what's optional in a chain, so `a?.b`, `a?.[i]` and `a?.(x)` are left as they are
+    fn link(chained: &Expression) -> &'static str {
+        match chained.node {
+            ExpressionNode::Optional(_) => "?.",
+            _                           => "",
+        }
+    }
+
+    // an end taken in is one past it left out
+    fn generate_bound(&mut self, end: &Expression, inclusive: bool) -> String {
+        let end = self.generate_expression(end);
+
+        if inclusive {
+            format!("({} + 1)", end)
+        } else {
+            end
+        }
+    }
+
     fn generate_assignment<'b>(&mut self, left: &'b Expression, right: &'b Expression) -> String {
         let left_string  = self.generate_expression(left);
         let right_string = self.generate_expression(right);
@@ -190,13 +435,14 @@ impl<'c> Compiler<'c> {
         result
     }
 
-    fn generate_operator<'b>(&mut self, op: &'b Operator) -> String {
+    fn generate_operator(&mut self, op: &Operator) -> String {
         use self::Operator::*;
 
         match *op {
-            Concat => "..".to_string(),
-            NEq    => "~=".to_string(),
-            _ => format!("{}", op)
+            Pow => "**".to_string(),
+            Or  => "||".to_string(),
+            And => "&&".to_string(),
+            _   => format!("{}", op)
         }
     }
 
@@ -206,14 +452,95 @@ impl<'c> Compiler<'c> {
         for line in value.lines() {
             output.push_str("  ");
 
-            output.push_str(&line);
+            output.push_str(line);
             output.push('\n')
         }
 
         output
     }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    fn js(code: &str) -> String {
+        crate::compile_str("<test>", code).result.expect("it should compile")
+    }
+
+    #[test]
+    fn compiles_nil_to_null() {
+        assert_eq!(js("øl x = nil\n"), "var x = null;\n")
+    }
+
+    #[test]
+    fn leaves_out_type_aliases() {
+        assert_eq!(js("type Id = int\nøl x: Id = 1\n"), "var x = 1;\n");
+        assert_eq!(js("øl f() =\n  type Name = str\n  øl 1\n"), "let f = function() {\nreturn 1;\n};\n")
+    }
+
+    #[test]
+    fn compiles_try_to_javascript() {
+        assert_eq!(
+            js("øl x = 0\nprøv:\n  kast \"out of cheese\"\nfang e:\n  x = e\nendelig:\n  x = 1\n"),
+            "var x = 0;\ntry {\n\tthrow \"out of cheese\";\n} catch (e) {\ne = e instanceof Error ? e.message : e;\n\tx = e;\n} finally {\n\tx = 1;\n};\n"
+        )
+    }
+
+    #[test]
+    fn compiles_async_functions_to_javascript() {
+        assert_eq!(js("asynk øl f(url) =\n  øl vent fetch(url)\n"), "let f = async function(url) {\nreturn (await fetch(url));\n};\n")
+    }
 
-    fn push_line(&mut self, target: &mut String, value: &str) {
-        target.push_str(&self.make_line(&value))
+    #[test]
+    fn compiles_records_to_factories() {
+        assert_eq!(
+            js("record Player = { name: str, score: int = 0 }\nøl p = Player { name: \"a\" }\n"),
+            "let Player = function(name, score) {\nreturn {\n  name: name,\n  score: score == null ? 0 : score,\n};\n};\nvar p = Object.assign(Player(), { name: \"a\", });\n"
+        )
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn compiles_generators_to_javascript() {
+        assert_eq!(js("øl f() =\n  gi 1\n"), "let f = function*() {\n(yield 1);\n};\n")
+    }
+
+    #[test]
+    fn compiles_ranges_to_loops_and_slices() {
+        assert_eq!(js("for i in 0...3:\n  i\n"), "for (var i = 0, i$end = (3 + 1); i < i$end; i++) {\n\ti;\n};\n");
+        assert_eq!(js("øl f(xs) =\n  øl xs[1..]\n"), "let f = function(xs) {\nreturn xs.slice(1);\n};\n");
+        assert_eq!(js("øl f(xs) =\n  øl xs[..2]\n"), "let f = function(xs) {\nreturn xs.slice(0, 2);\n};\n")
+    }
+
+    #[test]
+    fn compiles_spread_and_rest_to_javascript() {
+        assert_eq!(js("øl log(level, ...msgs) =\n  øl again = log(level, ...msgs)\n  øl again\n"), "let log = function(level, ...msgs) {\nvar again = log(level, ...msgs);\nreturn again;\n};\n");
+        assert_eq!(js("øl f(xs, t) =\n  øl [{ ...t, b: 2 }, ...xs]\n"), "let f = function(xs, t) {\nreturn [\n  { ...t, b: 2, },\n  ...xs\n];\n};\n")
+    }
+
+    #[test]
+    fn compiles_optional_chains_to_javascript() {
+        assert_eq!(js("øl f(p) =\n  øl p?.bag?.[0] ?? 1\n"), "let f = function(p) {\nreturn (p?.bag?.[0] ?? 1);\n};\n")
+    }
+
+    #[test]
+    fn lowers_compound_operators_javascript_lacks() {
+        assert_eq!(js("øl f(x) =\n  x ^= 2\n  x += 1\n  øl x\n"), "let f = function(x) {\nx **= 2;\nx += 1;\nreturn x;\n};\n");
+
+        let concat = js("øl f(s, t) =\n  s ++= \"!\"\n  t.name ++= s\n  øl t\n");
+
+        assert!(concat.starts_with("function concat$(a, b) {"));
+        assert!(concat.ends_with("let f = function(s, t) {\ns = concat$(s, \"!\");\nconcat_at$(t, \"name\", s);\nreturn t;\n};\n"))
+    }
+
+    #[test]
+    fn lowers_binary_operators_like_their_compound_ones() {
+        assert_eq!(js("øl f(x) =\n  x ^= 2\n  x = x ^ 2\n  øl x\n"), "let f = function(x) {\nx **= 2;\nx = (x ** 2);\nreturn x;\n};\n");
+
+        let concat = js("øl f(s) =\n  s ++= \"!\"\n  s = s ++ \"!\"\n  øl s\n");
+
+        assert!(concat.ends_with("let f = function(s) {\ns = concat$(s, \"!\");\ns = concat$(s, \"!\");\nreturn s;\n};\n"));
+
+        assert_eq!(js("øl f(a, b) =\n  øl not a != b or a and b\n"), "let f = function(a, b) {\nreturn (((!(a != b)) || a) && b);\n};\n")
+    }
+}
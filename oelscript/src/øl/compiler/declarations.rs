@@ -0,0 +1,304 @@
+use super::*;
+
+use std::collections::HashMap;
+
+// TypeScript declarations of what the top level of `ast` defines, for TypeScript code using its JavaScript
+pub fn declarations(ast: &[Statement], checker: &Checker) -> String {
+  let mut result = String::new();
+
+  if checker.failed() {
+    result.push_str("// the types didn't check out, the ones in question are `any`\n")
+  }
+
+  for (i, statement) in ast.iter().enumerate() {
+    // the last definition of a name is the one left once the script has run
+    if let Some(name) = defined_name(statement) {
+      if ast[i + 1 ..].iter().any(|later| defined_name(later) == Some(name)) {
+        continue
+      }
+    }
+
+    match statement.node {
+      StatementNode::TypeAlias(ref name, ref ty) => {
+        result.push_str(&format!("type {} = {};\n", name, from_annotation(ty)))
+      },
+
+      StatementNode::Variable(ref name, ref annotation, _, is_const) => {
+        let ty = match *annotation {
+          Some(ref annotation) => from_annotation(annotation),
+          None                 => checker.global(name).map_or(String::from("any"), |scheme| from_type(&scheme.ty, &HashMap::new())),
+        };
+
+        let keyword = if is_const { "const" } else { "let" };
+
+        result.push_str(&format!("declare {} {}: {};\n", keyword, name, ty))
+      },
+
+      StatementNode::Function(Expression { node: ExpressionNode::Identifier(ref name), .. }, ref params, ref ret, _, is_async, is_generator) => {
+        let scheme = checker.global(name);
+
+        let signature = match scheme {
+          Some(Scheme { ty: Type::Function(ref signature), .. }) => Some(signature),
+          _                                                      => None,
+        };
+
+        // variables the function is generic over become type parameters
+        let mut generics = HashMap::new();
+
+        if let Some(ref scheme) = scheme {
+          for (i, var) in scheme.vars.iter().enumerate() {
+            generics.insert(*var, generic_name(i));
+          }
+        }
+
+        let required = signature.map_or(params.len(), |signature| signature.required);
+
+        let params = params.iter().enumerate().map(|(i, param)| {
+          let ty = match param.ty {
+            Some(ref ty) if param.default.is_some() => format!("{} | null", from_annotation(ty)),
+            Some(ref ty)                            => from_annotation(ty),
+
+            None if param.rest => match signature.and_then(|signature| signature.rest.as_ref()) {
+              Some(rest) => array(&from_type(rest, &generics), is_compound(rest)),
+              None       => String::from("any[]"),
+            },
+
+            None => signature.and_then(|signature| signature.params.get(i)).map_or(String::from("any"), |ty| from_type(ty, &generics)),
+          };
+
+          let dots     = if param.rest { "..." } else { "" };
+          let optional = if i < required || param.rest { "" } else { "?" };
+
+          format!("{}{}{}: {}", dots, param.name, optional, ty)
+        }).collect::<Vec<String>>();
+
+        let ret = match *ret {
+          Some(ref ret) => from_annotation(ret),
+          None          => match signature {
+            Some(signature) if *signature.ret == Type::Nil => String::from("void"),
+            Some(signature)                               => from_type(&signature.ret, &generics),
+            None                                          => String::from("any"),
+          },
+        };
+
+        // what a generator hands out isn't inferred
+        let ret = match (is_async, is_generator) {
+          (false, false) => ret,
+          (true,  false) => format!("Promise<{}>", ret),
+          (false, true)  => String::from("Generator"),
+          (true,  true)  => String::from("AsyncGenerator"),
+        };
+
+        let mut type_params = generics.values().cloned().collect::<Vec<String>>();
+        type_params.sort();
+
+        let type_params = if type_params.is_empty() { String::new() } else { format!("<{}>", type_params.join(", ")) };
+
+        result.push_str(&format!("declare function {}{}({}): {};\n", name, type_params, params.join(", "), ret))
+      },
+
+      // a type and a factory function of the same name, TypeScript keeps the two apart
+      StatementNode::Record(ref name, ref fields) => {
+        let types = match checker.global(name) {
+          Some(Scheme { ty: Type::Function(ref signature), .. }) => signature.params.clone(),
+          _                                                      => Vec::new(),
+        };
+
+        let types = fields.iter().enumerate().map(|(i, field)| match field.ty {
+          Some(ref ty) => from_annotation(ty),
+          None         => types.get(i).map_or(String::from("any"), |ty| from_type(ty, &HashMap::new())),
+        }).collect::<Vec<String>>();
+
+        // only the defaults after the last required field can be left out
+        let required = fields.iter().rposition(|field| field.default.is_none()).map_or(0, |i| i + 1);
+
+        let params = fields.iter().zip(types.iter()).enumerate().map(|(i, (field, ty))| {
+          let optional = if i < required { "" } else { "?" };
+
+          format!("{}{}: {}", field.name, optional, ty)
+        }).collect::<Vec<String>>();
+
+        result.push_str(&format!("type {} = {};\n", name, record(fields.iter().map(|field| field.name.as_str()).zip(types.into_iter()))));
+        result.push_str(&format!("declare function {}({}): {};\n", name, params.join(", "), name))
+      },
+
+      // a union told apart by the tag, and an object of the constructors of its variants
+      StatementNode::Enum(ref name, ref variants) => {
+        let inferred = match checker.global(name) {
+          Some(Scheme { ty: Type::Record(ref constructors, _), .. }) => constructors.clone(),
+          _                                                          => Vec::new(),
+        };
+
+        let mut union        = Vec::new();
+        let mut constructors = Vec::new();
+
+        for variant in variants.iter() {
+          let types = match inferred.iter().find(|&(other, _)| *other == variant.name) {
+            Some(&(_, Type::Function(ref signature))) => signature.params.clone(),
+            _                                         => Vec::new(),
+          };
+
+          let types = variant.fields.iter().enumerate().map(|(i, field)| match field.ty {
+            Some(ref ty) => from_annotation(ty),
+            None         => types.get(i).map_or(String::from("any"), |ty| from_type(ty, &HashMap::new())),
+          }).collect::<Vec<String>>();
+
+          let tag = (TAG, format!("{:?}", variant.name));
+
+          union.push(record(Some(tag).into_iter().chain(variant.fields.iter().map(|field| field.name.as_str()).zip(types.iter().cloned()))));
+
+          if variant.fields.is_empty() {
+            constructors.push((variant.name.as_str(), name.clone()))
+          } else {
+            let params = variant.fields.iter().zip(types.iter()).map(|(field, ty)| format!("{}: {}", field.name, ty)).collect::<Vec<String>>();
+
+            constructors.push((variant.name.as_str(), format!("({}) => {}", params.join(", "), name)))
+          }
+        }
+
+        result.push_str(&format!("type {} = {};\n", name, union.join(" | ")));
+        result.push_str(&format!("declare const {}: {};\n", name, record(constructors.into_iter())))
+      },
+
+      _ => (),
+    }
+  }
+
+  result
+}
+
+fn defined_name(statement: &Statement) -> Option<&str> {
+  match statement.node {
+    StatementNode::Variable(ref name, ..) | StatementNode::TypeAlias(ref name, _) | StatementNode::Record(ref name, _) | StatementNode::Enum(ref name, _) => Some(name),
+    StatementNode::Function(ref name, ..) => match name.node {
+      ExpressionNode::Identifier(ref name) => Some(name),
+      _                                    => None,
+    },
+    _ => None,
+  }
+}
+
+fn generic_name(i: usize) -> String {
+  if i < 26 {
+    ((b'A' + i as u8) as char).to_string()
+  } else {
+    format!("T{}", i)
+  }
+}
+
+
+
+fn from_type(ty: &Type, generics: &HashMap<usize, String>) -> String {
+  match *ty {
+    Type::Int | Type::Float => String::from("number"),
+    Type::Str               => String::from("string"),
+    Type::Bool              => String::from("boolean"),
+    Type::Nil               => String::from("null"),
+    Type::Any               => String::from("any"),
+
+    Type::Var(ref var) => generics.get(var).cloned().unwrap_or_else(|| String::from("any")),
+
+    Type::Array(ref element) => array(&from_type(element, generics), is_compound(element)),
+
+    Type::Record(ref fields, _) => record(fields.iter().map(|(name, ty)| (name.as_str(), from_type(ty, generics)))),
+
+    Type::Function(ref signature) => {
+      let mut params = signature.params.iter().enumerate().map(|(i, ty)| {
+        let optional = if i < signature.required { "" } else { "?" };
+
+        format!("arg{}{}: {}", i, optional, from_type(ty, generics))
+      }).collect::<Vec<String>>();
+
+      if let Some(ref rest) = signature.rest {
+        params.push(format!("...rest: {}", array(&from_type(rest, generics), is_compound(rest))))
+      }
+
+      let ret = match *signature.ret {
+        Type::Nil => String::from("void"),
+        ref ret   => from_type(ret, generics),
+      };
+
+      format!("({}) => {}", params.join(", "), ret)
+    },
+
+    Type::Nullable(ref ty) => format!("{} | null", from_type(ty, generics)),
+
+    Type::Enum(ref name) => name.clone(),
+  }
+}
+
+fn from_annotation(annotation: &TypeExpression) -> String {
+  match annotation.node {
+    TypeNode::Name(ref name) => match name.as_str() {
+      "int" | "float" => String::from("number"),
+      "str"           => String::from("string"),
+      "bool"          => String::from("boolean"),
+      "nil"           => String::from("null"),
+      name            => name.to_string(), // `any` and aliases
+    },
+
+    TypeNode::Array(ref element) => {
+      let compound = matches!(element.node, TypeNode::Function(..) | TypeNode::Nullable(_));
+
+      array(&from_annotation(element), compound)
+    },
+
+    TypeNode::Record(ref fields) => record(fields.iter().map(|(name, ty)| (name.as_str(), from_annotation(ty)))),
+
+    TypeNode::Function(ref params, ref ret) => {
+      let params = params.iter().enumerate().map(|(i, ty)| format!("arg{}: {}", i, from_annotation(ty))).collect::<Vec<String>>();
+
+      format!("({}) => {}", params.join(", "), from_annotation(ret))
+    },
+
+    TypeNode::Nullable(ref ty) => format!("{} | null", from_annotation(ty)),
+  }
+}
+
+// `T[]` reads as an array of what's right before `[]`, so unions and functions need the long form
+fn array(element: &str, compound: bool) -> String {
+  if compound {
+    format!("Array<{}>", element)
+  } else {
+    format!("{}[]", element)
+  }
+}
+
+fn is_compound(ty: &Type) -> bool {
+  matches!(*ty, Type::Function(_) | Type::Nullable(_))
+}
+
+fn record<'a, I: Iterator<Item = (&'a str, String)>>(fields: I) -> String {
+  let fields = fields.map(|(name, ty)| {
+    if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+      format!("{}: {}", name, ty)
+    } else {
+      format!("{:?}: {}", name, ty)
+    }
+  }).collect::<Vec<String>>();
+
+  if fields.is_empty() {
+    String::from("{}")
+  } else {
+    format!("{{ {} }}", fields.join("; "))
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  fn declare(code: &str) -> String {
+    crate::declarations_str("<test>", code).result.expect("it should parse")
+  }
+
+  #[test]
+  fn declares_what_was_inferred() {
+    assert_eq!(declare("øl x = 1\nøl f(a) =\n  øl a\n"), "declare let x: number;\ndeclare function f<A>(a: A): A;\n")
+  }
+
+  #[test]
+  fn notes_types_that_fell_back() {
+    assert_eq!(declare("øl x = \"a\" - 1\n"), "// the types didn't check out, the ones in question are `any`\ndeclare let x: any;\n")
+  }
+}
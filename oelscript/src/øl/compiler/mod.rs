@@ -1,7 +1,11 @@
+#[allow(clippy::module_inception)]
 pub mod compiler;
+pub mod declarations;
 
 use super::parser::*;
 use super::source::*;
-use super::lexer::*;
+use super::prelude;
+use super::types::*;
 
-pub use self::compiler::*;
\ No newline at end of file
+pub use self::compiler::*;
+pub use self::declarations::*;
\ No newline at end of file
@@ -0,0 +1,230 @@
+use super::*;
+
+struct LogicalLine {
+  elements: Vec<SyntaxElement>,
+  indent:   Option<usize>, // None for lines holding only whitespace and comments
+  comment:  Option<usize>, // column of the comment on such a line, which places it
+}
+
+impl LogicalLine {
+  // a comment left of the block it follows belongs to an outer one
+  fn outdented(&self, indent: usize) -> bool {
+    self.indent.is_none() && self.comment.is_some_and(|column| column < indent)
+  }
+}
+
+// Builds a concrete syntax tree from the tokens of `Lexer::lossless`
+pub struct Builder<'b> {
+  index:  usize,
+  tokens: Vec<Token>,
+  source: &'b Source,
+}
+
+impl<'b> Builder<'b> {
+  pub fn new(tokens: Vec<Token>, source: &'b Source) -> Self {
+    Builder {
+      tokens,
+      source,
+      index: 0,
+    }
+  }
+
+
+
+  pub fn build(&mut self) -> SyntaxNode {
+    let mut lines = Vec::new();
+
+    while self.index < self.tokens.len() {
+      lines.push(self.build_line())
+    }
+
+    let mut lines = lines.into_iter().peekable();
+
+    SyntaxNode::new(SyntaxKind::Root, Self::build_block(&mut lines, 0))
+  }
+
+
+
+  // Lines indented at least `indent`, a line followed by deeper ones owns them as a `Block`
+  fn build_block<I: Iterator<Item = LogicalLine>>(lines: &mut ::std::iter::Peekable<I>, indent: usize) -> Vec<SyntaxElement> {
+    let mut elements = Vec::new();
+
+    while let Some(line) = lines.peek() {
+      if line.outdented(indent) {
+        break
+      }
+
+      let line_indent = line.indent;
+
+      match line_indent {
+        None => elements.extend(lines.next().unwrap().elements),
+
+        Some(line_indent) if line_indent < indent => break,
+
+        Some(line_indent) => {
+          let mut children = lines.next().unwrap().elements;
+
+          // blank lines in between belong to the block, not to the line above it
+          let mut trivia = Vec::new();
+
+          while lines.peek().is_some_and(|line| line.indent.is_none() && !line.outdented(indent)) {
+            trivia.extend(lines.next().unwrap().elements)
+          }
+
+          match lines.peek().and_then(|line| line.indent) {
+            Some(next_indent) if next_indent > line_indent => {
+              trivia.extend(Self::build_block(lines, next_indent));
+
+              children.push(SyntaxElement::Node(SyntaxNode::new(SyntaxKind::Block, trivia)));
+              elements.push(SyntaxElement::Node(SyntaxNode::new(SyntaxKind::Line, children)));
+            },
+
+            _ => {
+              elements.push(SyntaxElement::Node(SyntaxNode::new(SyntaxKind::Line, children)));
+              elements.extend(trivia);
+            },
+          }
+        },
+      }
+    }
+
+    elements
+  }
+
+
+
+  fn build_line(&mut self) -> LogicalLine {
+    let mut elements = Vec::new();
+    let mut indent   = None;
+    let mut comment  = None;
+
+    while self.index < self.tokens.len() {
+      let token = self.tokens[self.index].clone();
+
+      if indent.is_none() && !token.token_type.is_trivia() && token.token_type != TokenType::EOL {
+        indent = Some(self.source.location(token.pos.0).1 - 1)
+      }
+
+      if comment.is_none() && token.token_type == TokenType::Comment {
+        comment = Some(self.source.location(token.pos.0).1 - 1)
+      }
+
+      if let Some(close) = Self::closing(&token) {
+        elements.push(SyntaxElement::Node(self.build_group(close)));
+        continue
+      }
+
+      self.index += 1;
+
+      elements.push(SyntaxElement::Token(token.clone()));
+
+      if token.token_type == TokenType::EOL {
+        break
+      }
+    }
+
+    LogicalLine {
+      elements,
+      indent,
+      comment,
+    }
+  }
+
+
+
+  fn build_group(&mut self, close: &str) -> SyntaxNode {
+    let mut elements = vec!(SyntaxElement::Token(self.tokens[self.index].clone()));
+
+    self.index += 1;
+
+    while self.index < self.tokens.len() {
+      let token = self.tokens[self.index].clone();
+
+      if let Some(inner) = Self::closing(&token) {
+        elements.push(SyntaxElement::Node(self.build_group(inner)));
+        continue
+      }
+
+      self.index += 1;
+
+      let done = token.token_type == TokenType::Symbol && token.lexeme == close;
+
+      elements.push(SyntaxElement::Token(token));
+
+      if done {
+        break
+      }
+    }
+
+    SyntaxNode::new(SyntaxKind::Group, elements)
+  }
+
+
+
+  fn closing(token: &Token) -> Option<&'static str> {
+    if token.token_type != TokenType::Symbol {
+      return None
+    }
+
+    match token.lexeme.as_str() {
+      "(" => Some(")"),
+      "[" => Some("]"),
+      "{" => Some("}"),
+      _   => None,
+    }
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn build(source: &Source) -> SyntaxNode {
+    let tokens = Lexer::lossless(source.content.chars().collect(), source).collect::<Result<Vec<Token>, ()>>().unwrap();
+
+    Builder::new(tokens, source).build()
+  }
+
+  fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+    node.nodes().iter().map(|node| node.kind).collect()
+  }
+
+  #[test]
+  fn gives_back_the_source() {
+    let samples = [
+      "øl x = 1\n",
+      "øl f(a, b) =   // sums\n  øl a + b\n\n\n// end\nf(1, 2)",
+      "xs = [\n  1,\n    2, // two\n]\n",
+      "øl blåbær = {\n  navn: \"日本\"\n}\r\n",
+      "\n\n  \n",
+      "",
+    ];
+
+    for code in samples.iter() {
+      let source = Source::from("<test>", code.to_string());
+
+      assert_eq!(build(&source).text(&source), *code)
+    }
+  }
+
+  #[test]
+  fn nests_indented_lines_in_blocks() {
+    let source = Source::from("<test>", "øl f(x) =\n  øl x\nf(1)\n".to_string());
+    let root   = build(&source);
+
+    assert_eq!(kinds(&root), vec!(SyntaxKind::Line, SyntaxKind::Line));
+    assert_eq!(kinds(root.nodes()[0]).last(), Some(&SyntaxKind::Block));
+    assert_eq!(kinds(root.nodes()[1]), vec!(SyntaxKind::Group));
+  }
+
+  #[test]
+  fn keeps_groups_on_their_line() {
+    let source = Source::from("<test>", "xs = [\n  1,\n  2,\n]\nys = xs\n".to_string());
+    let root   = build(&source);
+
+    assert_eq!(kinds(&root), vec!(SyntaxKind::Line, SyntaxKind::Line));
+    assert_eq!(kinds(root.nodes()[0]), vec!(SyntaxKind::Group));
+  }
+}
@@ -0,0 +1,8 @@
+pub mod node;
+pub mod builder;
+
+use super::lexer::*;
+use super::source::*;
+
+pub use self::node::*;
+pub use self::builder::*;
@@ -0,0 +1,73 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntaxKind {
+  Root,
+  Line,  // a logical line, up to and including its newline
+  Block, // the lines indented under the line owning it
+  Group, // a `( )`, `[ ]` or `{ }` region, newlines inside don't end the line
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+  Node(SyntaxNode),
+  Token(Token),
+}
+
+impl SyntaxElement {
+  pub fn pos(&self) -> Pos {
+    match *self {
+      SyntaxElement::Node(ref node)   => node.pos,
+      SyntaxElement::Token(ref token) => token.pos,
+    }
+  }
+}
+
+// Concrete syntax tree node, trivia tokens are kept as children in source order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+  pub kind:     SyntaxKind,
+  pub pos:      Pos,
+  pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+  pub fn new(kind: SyntaxKind, children: Vec<SyntaxElement>) -> Self {
+    let pos = match (children.first(), children.last()) {
+      (Some(first), Some(last)) => first.pos().to(&last.pos()),
+      _                         => Pos::default(),
+    };
+
+    SyntaxNode {
+      kind,
+      pos,
+      children,
+    }
+  }
+
+  // Every token below this node in source order, trivia included
+  pub fn tokens(&self) -> Vec<&Token> {
+    let mut tokens = Vec::new();
+
+    for child in self.children.iter() {
+      match *child {
+        SyntaxElement::Node(ref node)   => tokens.extend(node.tokens()),
+        SyntaxElement::Token(ref token) => tokens.push(token),
+      }
+    }
+
+    tokens
+  }
+
+  pub fn nodes(&self) -> Vec<&SyntaxNode> {
+    self.children.iter().filter_map(|child| match *child {
+      SyntaxElement::Node(ref node) => Some(node),
+      _                             => None,
+    }).collect()
+  }
+
+  // The original text, a tree built from a lossless lexer gives back the source byte for byte
+  pub fn text(&self, source: &Source) -> String {
+    self.tokens().iter().map(|token| source.slice(&token.pos)).collect()
+  }
+}
@@ -1,6 +1,10 @@
 use std::fmt;
+use std::cell::RefCell;
 use colored::Colorize;
 
+use super::lexer::Pos;
+use super::source::{ FilePath, Snippet };
+
 pub enum Response<T: fmt::Display> {
   Wrong(T),
   Weird(T),
@@ -11,11 +15,11 @@ use self::Response::*;
 
 #[macro_export]
 macro_rules! response {
-  ( $( $r:expr ),+ ) => {{
-    $(
-        print!("{}", $r);
-    )*
-    println!();
+  ( $r:expr, $file:expr ) => {{
+    $crate::øl::error::report(&$r, &$file, None)
+  }};
+  ( $r:expr, $file:expr, $snippet:expr ) => {{
+    $crate::øl::error::report(&$r, &$file, Some(&$snippet))
   }};
 }
 
@@ -35,3 +39,74 @@ impl<T: fmt::Display> fmt::Display for Response<T> {
     write!(f, "{}", message)
   }
 }
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+  Wrong,
+  Weird,
+  Note,
+}
+
+// A reported response kept as data instead of being printed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub message:  String,
+  pub file:     String,
+  pub pos:      Option<Pos>,
+  pub labels:   Vec<(Pos, String)>,
+}
+
+thread_local! {
+  static CAPTURED: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+}
+
+// Prints a response, or records it when called from within `capture`
+pub fn report<T: fmt::Display>(response: &Response<T>, file: &FilePath, snippet: Option<&Snippet>) {
+  let diagnostic = {
+    let (severity, message) = match *response {
+      Wrong(ref m) => (Severity::Wrong, m),
+      Weird(ref m) => (Severity::Weird, m),
+      Note(ref m)  => (Severity::Note,  m),
+    };
+
+    Diagnostic {
+      severity,
+      message: message.to_string(),
+      file:    file.0.clone(),
+      pos:     snippet.map(|snippet| snippet.pos()),
+      labels:  snippet.map_or(Vec::new(), |snippet| snippet.labels()),
+    }
+  };
+
+  let diagnostic = CAPTURED.with(|captured| match *captured.borrow_mut() {
+    Some(ref mut diagnostics) => {
+      diagnostics.push(diagnostic);
+      None
+    },
+
+    None => Some(diagnostic),
+  });
+
+  if diagnostic.is_some() {
+    print!("{}{}", response, file);
+
+    if let Some(snippet) = snippet {
+      print!("{}", snippet)
+    }
+
+    println!()
+  }
+}
+
+// Runs `f` collecting every response reported on this thread instead of printing it
+pub fn capture<T, F: FnOnce() -> T>(f: F) -> (T, Vec<Diagnostic>) {
+  let outer  = CAPTURED.with(|captured| captured.replace(Some(Vec::new())));
+  let result = f();
+
+  let diagnostics = CAPTURED.with(|captured| captured.replace(outer)).unwrap_or_default();
+
+  (result, diagnostics)
+}
@@ -0,0 +1,432 @@
+use super::*;
+
+const INDENT: &str = "  ";
+
+// Reprints `source` in canonical style: two space indentation, one space around operators
+// and one blank line at most. Multi-line arrays and argument lists get a comma after every
+// element, multi-line tables separate entries by newlines only. Comments are kept.
+// Code that doesn't parse is reported and left alone.
+pub fn format(source: &Source) -> Result<String, ()> {
+  let mut tokens = Vec::new();
+
+  for token in Lexer::default(source.content.chars().collect(), source) {
+    tokens.push(token?)
+  }
+
+  Parser::new(tokens, source).parse()?;
+
+  let mut tokens = Vec::new();
+
+  for token in Lexer::lossless(source.content.chars().collect(), source) {
+    tokens.push(token?)
+  }
+
+  let root = Builder::new(tokens, source).build();
+
+  let mut formatter = Formatter::new(source);
+
+  formatter.format_block(&root, 0);
+
+  Ok(formatter.output)
+}
+
+
+
+enum GroupItem<'a> {
+  Element(Vec<&'a SyntaxElement>, Option<&'a Token>),
+  Comment(&'a Token),
+  Blank,
+}
+
+pub struct Formatter<'f> {
+  source: &'f Source,
+  output: String,
+
+  blank:       bool, // a blank line is waiting for the next printed line
+  block_start: bool, // nothing has been printed in the current block yet
+}
+
+impl<'f> Formatter<'f> {
+  pub fn new(source: &'f Source) -> Self {
+    Formatter {
+      source,
+      output: String::new(),
+
+      blank:       false,
+      block_start: true,
+    }
+  }
+
+
+
+  fn format_block(&mut self, node: &SyntaxNode, depth: usize) {
+    self.block_start = true;
+
+    let mut line_empty = true;
+
+    for child in node.children.iter() {
+      match *child {
+        SyntaxElement::Node(ref line) => {
+          self.format_line(line, depth);
+
+          line_empty = true
+        },
+
+        SyntaxElement::Token(ref token) => match token.token_type {
+          TokenType::Comment => {
+            let comment = self.text(token).trim_end().to_string();
+
+            self.push_line(depth, &comment);
+
+            line_empty = false
+          },
+
+          TokenType::EOL => {
+            if line_empty {
+              self.blank = true
+            }
+
+            line_empty = true
+          },
+
+          _ => (),
+        },
+      }
+    }
+  }
+
+
+
+  fn format_line(&mut self, line: &SyntaxNode, depth: usize) {
+    let mut items   = Vec::new();
+    let mut comment = None;
+    let mut block   = None;
+
+    for child in line.children.iter() {
+      match *child {
+        SyntaxElement::Node(ref node) if node.kind == SyntaxKind::Block => block = Some(node),
+
+        SyntaxElement::Token(ref token) => match token.token_type {
+          TokenType::Whitespace | TokenType::EOL => (),
+          TokenType::Comment                     => comment = Some(token),
+          _                                      => items.push(child),
+        },
+
+        _ => items.push(child),
+      }
+    }
+
+    let mut text = self.format_items(&items, depth);
+
+    if let Some(comment) = comment {
+      text.push(' ');
+      text.push_str(self.text(comment).trim_end())
+    }
+
+    self.push_line(depth, &text);
+
+    if let Some(block) = block {
+      self.format_block(block, depth + 1)
+    }
+  }
+
+
+
+  fn format_items(&self, items: &[&SyntaxElement], depth: usize) -> String {
+    let mut result = String::new();
+
+    let mut previous: Option<&Token> = None;
+    let mut unary                    = false;
+
+    for item in items.iter() {
+      let (first, last) = match **item {
+        SyntaxElement::Node(ref group)  => (group.tokens()[0], *group.tokens().last().unwrap()),
+        SyntaxElement::Token(ref token) => (token, token),
+      };
+
+      if let Some(previous) = previous {
+        if !unary && Self::spaced(previous, first) {
+          result.push(' ')
+        }
+      }
+
+      unary = first.token_type == TokenType::Operator && first.lexeme == "-" && Self::is_prefix(previous);
+
+      match **item {
+        SyntaxElement::Node(ref group)  => result.push_str(&self.format_group(group, depth)),
+        SyntaxElement::Token(ref token) => result.push_str(self.text(token)),
+      }
+
+      previous = Some(last)
+    }
+
+    result
+  }
+
+
+
+  fn format_group(&self, group: &SyntaxNode, depth: usize) -> String {
+    let open  = match group.children.first() {
+      Some(SyntaxElement::Token(token)) => token,
+      _                                 => unreachable!(),
+    };
+
+    let close = match group.children.last() {
+      Some(SyntaxElement::Token(token)) if group.children.len() > 1 && Self::closes(open, token) => Some(token),
+      _                                                                                        => None,
+    };
+
+    let inner = &group.children[1 .. group.children.len() - if close.is_some() { 1 } else { 0 }];
+    let items = self.split_group(open, inner);
+
+    let elements  = items.iter().filter(|item| matches!(**item, GroupItem::Element(..))).count();
+    let has_comma = inner.iter().any(|child| Self::is_symbol(child, ","));
+
+    let mut multiline = inner.iter().any(|child| match *child {
+      SyntaxElement::Token(ref token) => token.token_type == TokenType::EOL || token.token_type == TokenType::Comment,
+      _                               => false,
+    });
+
+    // a parenthesised expression can't take a trailing comma
+    if open.lexeme == "(" && !has_comma {
+      multiline = false
+    }
+
+    let close = close.map_or("", |close| self.text(close));
+
+    if items.is_empty() {
+      return format!("{}{}", open.lexeme, close)
+    }
+
+    if !multiline || elements == 0 {
+      let pad = if open.lexeme == "{" { " " } else { "" };
+
+      let elements = items.iter().filter_map(|item| match *item {
+        GroupItem::Element(ref element, _) => Some(self.format_items(element, depth)),
+        _                                  => None,
+      }).collect::<Vec<String>>();
+
+      return format!("{}{}{}{}{}", open.lexeme, pad, elements.join(", "), pad, close)
+    }
+
+    let separator = if open.lexeme == "{" { "" } else { "," };
+    let indent    = INDENT.repeat(depth + 1);
+
+    let mut result = format!("{}\n", open.lexeme);
+
+    for (i, item) in items.iter().enumerate() {
+      match *item {
+        GroupItem::Element(ref element, ref comment) => {
+          result.push_str(&format!("{}{}{}", indent, self.format_items(element, depth + 1), separator));
+
+          if let Some(comment) = *comment {
+            result.push(' ');
+            result.push_str(self.text(comment).trim_end())
+          }
+
+          result.push('\n')
+        },
+
+        GroupItem::Comment(comment) => result.push_str(&format!("{}{}\n", indent, self.text(comment).trim_end())),
+
+        GroupItem::Blank => if i + 1 < items.len() {
+          result.push('\n')
+        },
+      }
+    }
+
+    result.push_str(&INDENT.repeat(depth));
+    result.push_str(close);
+
+    result
+  }
+
+
+
+  // Elements of a group are separated by commas, entries of a table by newlines as well
+  fn split_group<'a>(&self, open: &Token, inner: &'a [SyntaxElement]) -> Vec<GroupItem<'a>> {
+    let mut items = Vec::new();
+
+    let mut current  = Vec::new();
+    let mut comment  = None;
+    let mut newlines = 0;
+
+    for child in inner.iter() {
+      if let SyntaxElement::Token(ref token) = *child {
+        match token.token_type {
+          TokenType::Whitespace => continue,
+
+          TokenType::EOL => {
+            if open.lexeme == "{" && !current.is_empty() {
+              items.push(GroupItem::Element(current, comment.take()));
+              current = Vec::new();
+            }
+
+            newlines += 1;
+            continue
+          },
+
+          TokenType::Comment => {
+            if !current.is_empty() {
+              comment = Some(token)
+            } else if newlines == 0 && !items.is_empty() {
+              if let Some(&mut GroupItem::Element(_, ref mut last)) = items.last_mut() {
+                *last = Some(token)
+              }
+            } else {
+              if newlines > 1 && !items.is_empty() {
+                items.push(GroupItem::Blank)
+              }
+
+              items.push(GroupItem::Comment(token))
+            }
+
+            newlines = 0;
+            continue
+          },
+
+          TokenType::Symbol if token.lexeme == "," => {
+            if !current.is_empty() {
+              items.push(GroupItem::Element(current, comment.take()));
+              current = Vec::new();
+            }
+
+            newlines = 0;
+            continue
+          },
+
+          _ => (),
+        }
+      }
+
+      if current.is_empty() && newlines > 1 && !items.is_empty() {
+        items.push(GroupItem::Blank)
+      }
+
+      current.push(child);
+      newlines = 0
+    }
+
+    if !current.is_empty() {
+      items.push(GroupItem::Element(current, comment))
+    }
+
+    items
+  }
+
+
+
+  fn push_line(&mut self, depth: usize, text: &str) {
+    if self.blank && !self.block_start {
+      self.output.push('\n')
+    }
+
+    self.blank       = false;
+    self.block_start = false;
+
+    self.output.push_str(&INDENT.repeat(depth));
+    self.output.push_str(text);
+    self.output.push('\n')
+  }
+
+  fn text(&self, token: &Token) -> &'f str {
+    self.source.slice(&token.pos)
+  }
+
+
+
+  // Whether `previous` leaves the operator after it in prefix position, like `x = -y` or `f(-y)`
+  fn is_prefix(previous: Option<&Token>) -> bool {
+    match previous {
+      None           => true,
+      Some(previous) => match previous.token_type {
+        TokenType::Operator | TokenType::Keyword => true,
+        TokenType::Symbol                        => ![")", "]", "}"].contains(&previous.lexeme.as_str()),
+        _                                        => false,
+      },
+    }
+  }
+
+  fn spaced(previous: &Token, next: &Token) -> bool {
+    if next.token_type == TokenType::Symbol && [",", ":", ")", "]", "}", ".", "?.", "\\", "..", "...", "?"].contains(&next.lexeme.as_str()) {
+      return false
+    }
+
+    if previous.token_type == TokenType::Symbol && ["(", "[", ".", "?.", "\\", "..", "..."].contains(&previous.lexeme.as_str()) {
+      return false
+    }
+
+    // calls and indexing
+    if next.token_type == TokenType::Symbol && ["(", "["].contains(&next.lexeme.as_str()) {
+      return match previous.token_type {
+        // `for x in [1, 2]:`
+        TokenType::Identifier if previous.lexeme == "in" => true,
+        TokenType::Identifier | TokenType::Str           => false,
+        TokenType::Symbol                                => ![")", "]", "}"].contains(&previous.lexeme.as_str()),
+        _                                                => true,
+      }
+    }
+
+    true
+  }
+
+  fn is_symbol(element: &SyntaxElement, symbol: &str) -> bool {
+    match *element {
+      SyntaxElement::Token(ref token) => token.token_type == TokenType::Symbol && token.lexeme == symbol,
+      _                               => false,
+    }
+  }
+
+  fn closes(open: &Token, close: &Token) -> bool {
+    close.token_type == TokenType::Symbol && match open.lexeme.as_str() {
+      "(" => close.lexeme == ")",
+      "[" => close.lexeme == "]",
+      _   => close.lexeme == "}",
+    }
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn format_str(code: &str) -> Option<String> {
+    let source = Source::from("<test>", code.to_string());
+
+    crate::øl::error::capture(|| format(&source)).0.ok()
+  }
+
+  #[test]
+  fn formats_in_canonical_style() {
+    let code = "øl f(a,b) =\n    øl a+b\n\n\n\nøl xs = [\n  1,\n    2 // two\n]\nprint(f(1,2))\n";
+
+    assert_eq!(
+      format_str(code).unwrap(),
+      "øl f(a, b) =\n  øl a + b\n\nøl xs = [\n  1,\n  2, // two\n]\nprint(f(1, 2))\n"
+    )
+  }
+
+  #[test]
+  fn is_idempotent() {
+    let code = "øl move(p) =\n  p.x = 1\n  // inside\n// slut\nøl y   =  2\nøl t = { a: 1,\n  b: [1, 2]\n}\n\n\nøl y > 1:\n      print(\"blåbær\")\nølse:\n  print(\"日本\")\n";
+
+    let once = format_str(code).unwrap();
+
+    assert_eq!(format_str(&once).unwrap(), once)
+  }
+
+  #[test]
+  fn keeps_comments_at_their_column() {
+    let code = "øl move(p) =\n  p.x = 1\n  // inside\n// slut\nøl y = 2\n";
+
+    assert_eq!(format_str(code).unwrap(), code)
+  }
+
+  #[test]
+  fn refuses_what_does_not_parse() {
+    assert_eq!(format_str("øl x = (1\n"), None);
+    assert_eq!(format_str("øl f(a =\n  øl a\n"), None)
+  }
+}
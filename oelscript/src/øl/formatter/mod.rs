@@ -0,0 +1,9 @@
+#[allow(clippy::module_inception)]
+pub mod formatter;
+
+use super::lexer::*;
+use super::parser::*;
+use super::source::*;
+use super::cst::*;
+
+pub use self::formatter::*;
@@ -6,8 +6,9 @@ use std::rc::Rc;
 
 pub struct Lexer<'l> {
   tokenizer: Tokenizer<'l>,
-  matchers:  Vec<Rc<Matcher<'l>>>,
+  matchers:  Vec<Rc<dyn Matcher<'l>>>,
   source:    &'l Source,
+  trivia:    bool,
 }
 
 impl<'l> Lexer<'l> {
@@ -16,9 +17,18 @@ impl<'l> Lexer<'l> {
       tokenizer,
       matchers: Vec::new(),
       source,
+      trivia:   false,
     }
   }
 
+  // Keeps whitespace and comments, so the tokens cover the source byte for byte
+  pub fn lossless(data: Vec<char>, source: &'l Source) -> Self {
+    let mut lexer = Self::default(data, source);
+
+    lexer.trivia = true;
+    lexer
+  }
+
   pub fn default(data: Vec<char>, source: &'l Source) -> Self {
     use self::TokenType::*;
 
@@ -46,7 +56,7 @@ impl<'l> Lexer<'l> {
 
     lexer.matchers.push(
       Rc::new(
-        ConstantStringMatcher::new(Symbol, &["...", ".."])
+        ConstantStringMatcher::new(Symbol, &["...", "..", "->", "?.", "++=", "+=", "-=", "*=", "/=", "%=", "^="])
       )
     );
 
@@ -55,7 +65,7 @@ impl<'l> Lexer<'l> {
 
     lexer.matchers.push(
       Rc::new(
-        ConstantStringMatcher::new(Operator, &["^", "++", "+", "-", "*", "/", "%", "==", "!=", "<=", ">=", "|>", "<|", "<", ">", "or", "and", "not"])
+        ConstantStringMatcher::new(Operator, &["^", "++", "??", "+", "-", "*", "/", "%", "==", "!=", "<=", ">=", "|>", "<|", "<", ">", "or", "and", "not"])
       )
     );
 
@@ -63,7 +73,7 @@ impl<'l> Lexer<'l> {
 
     lexer.matchers.push(
       Rc::new(
-        ConstantCharMatcher::new(Symbol, &['(', ')', '[', ']', '{', '}', ',', ':', ';', '=', '.', '|', '\\'])
+        ConstantCharMatcher::new(Symbol, &['(', ')', '[', ']', '{', '}', ',', ':', ';', '=', '.', '|', '\\', '?'])
       )
     );
 
@@ -90,17 +100,12 @@ impl<'l> Iterator for Lexer<'l> {
       Ok(hmm) => match hmm {
         Some(n) => n,
         None    => {
-          let pos = self.tokenizer.pos;
-
           return Some(
             Err(
               response!(
                 Wrong("bumped into weird character"),
                 self.source.file,
-                Pos(
-                  (pos.0, self.source.lines.get(pos.0.saturating_sub(1)).unwrap_or(self.source.lines.last().unwrap_or(&String::new())).to_string()),
-                  (pos.1 + 1, pos.1 + 1),
-                )
+                self.source.snippet(&self.tokenizer.current_position())
               )
             )
           )
@@ -111,9 +116,9 @@ impl<'l> Iterator for Lexer<'l> {
     };
 
     match token.token_type {
-      TokenType::EOF        => None,
-      TokenType::Whitespace => self.next(),
-      _                     => Some(Ok(token)),
+      TokenType::EOF                         => None,
+      ref t if t.is_trivia() && !self.trivia => self.next(),
+      _                                      => Some(Ok(token)),
     }
   }
 }
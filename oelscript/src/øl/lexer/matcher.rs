@@ -11,15 +11,8 @@ macro_rules! token {
     let token_type = $token_type as $crate::øl::lexer::token::TokenType;
 
     let accum: String = $accum;
-    let pos           = tokenizer.last_position();
 
-    let line = tokenizer.source.lines.get(pos.0.saturating_sub(1)).unwrap_or(tokenizer.source.lines.last().unwrap()).to_string();
-
-    if TokenType::Str == token_type {
-      Token::new(token_type, (pos.0, line), (pos.1 + 1, pos.1 + accum.len() + 2), &accum) // delimeters
-    } else {
-      Token::new(token_type, (pos.0, line), (pos.1 + 1, pos.1 + accum.len()), &accum)
-    }
+    Token::new(token_type, tokenizer.last_position(), &accum)
   }};
 }
 
@@ -35,12 +28,14 @@ pub struct CommentMatcher;
 
 impl<'t> Matcher<'t> for CommentMatcher {
   fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
-    if tokenizer.peek_range(2).unwrap_or_else(String::new) == "//" {
+    if tokenizer.peek_range(2).unwrap_or_default() == "//" {
+      let mut comment = String::new();
+
       while !tokenizer.end() && tokenizer.peek() != Some('\n') {
-        tokenizer.advance()
+        comment.push(tokenizer.next().unwrap())
       }
 
-      Ok(Some(token!(tokenizer, EOL, "\n".into())))
+      Ok(Some(token!(tokenizer, Comment, comment)))
     } else {
       Ok(None)
     }
@@ -66,7 +61,7 @@ impl ConstantStringMatcher {
 impl<'t> Matcher<'t> for ConstantStringMatcher {
   fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
     for constant in self.constants {
-      let len = constant.len();
+      let len = constant.chars().count();
       let c   = match tokenizer.peek_range(len) {
         Some(len) => len,
         _         => return Ok(None),
@@ -75,14 +70,7 @@ impl<'t> Matcher<'t> for ConstantStringMatcher {
       if c == *constant {
         tokenizer.advance_n(len);
 
-        let token = token!(tokenizer, self.token_type.clone(), constant.to_string());
-
-        if c == "\n" {
-          tokenizer.pos.0 += 1;
-          tokenizer.pos.1 = 0;
-        }
-
-        return Ok(Some(token))
+        return Ok(Some(token!(tokenizer, self.token_type.clone(), constant.to_string())))
       }
     }
 
@@ -114,14 +102,7 @@ impl<'t> Matcher<'t> for ConstantCharMatcher {
       if c == *constant {
         tokenizer.advance();
 
-        let token = token!(tokenizer, self.token_type.clone(), constant.to_string());
-
-        if c == '\n' {
-          tokenizer.pos.0 += 1;
-          tokenizer.pos.1 = 0;
-        }
-
-        return Ok(Some(token))
+        return Ok(Some(token!(tokenizer, self.token_type.clone(), constant.to_string())))
       }
     }
     Ok(None)
@@ -136,8 +117,6 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
   fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
     let mut raw_marker = false;
 
-    let mut pos = tokenizer.pos;
-
     let delimeter  = match tokenizer.peek().unwrap() {
       '"'  => '"',
       '\'' => '\'',
@@ -146,18 +125,13 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
           raw_marker = true;
           tokenizer.advance();
 
-          pos = tokenizer.pos;
-
           '"'
         } else if tokenizer.peek_n(1) == Some('\'') {
           return Err(
             response!(
               Wrong("no such thing as a raw character literal"),
               tokenizer.source.file,
-              Pos(
-                (pos.0, tokenizer.source.lines.get(pos.0.saturating_sub(1)).unwrap_or(tokenizer.source.lines.last().unwrap()).to_string()),
-                (pos.1 - 1, pos.1),
-              )
+              tokenizer.source.snippet(&Pos(tokenizer.offset, tokenizer.offset + 2))
             )
           )
         } else {
@@ -167,6 +141,8 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
       _ => return Ok(None),
     };
 
+    let start = tokenizer.offset;
+
     tokenizer.advance();
 
     let mut string       = String::new();
@@ -178,10 +154,7 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
           response!(
             Wrong(format!("unterminated delimeter `{}`", delimeter)),
             tokenizer.source.file,
-            Pos(
-              (pos.0 + 1, tokenizer.source.lines.get(pos.0.saturating_sub(1)).unwrap_or(tokenizer.source.lines.last().unwrap()).to_string()),
-              (pos.1.saturating_sub(1), pos.1 + 1),
-            )
+            tokenizer.source.snippet(&Pos(start, start + 1))
           )
         )
       }
@@ -203,10 +176,7 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
               response!(
                 Wrong(format!("unexpected escape character: {}", escaped)),
                 tokenizer.source.file,
-                Pos(
-                  (tokenizer.pos.0, tokenizer.source.lines.get(pos.0.saturating_sub(1)).unwrap_or(tokenizer.source.lines.last().unwrap()).to_string()),
-                  (tokenizer.pos.1 - 1, tokenizer.pos.1),
-                )
+                tokenizer.source.snippet(&Pos(tokenizer.offset - escaped.len_utf8() - 1, tokenizer.offset))
               )
             ),
           }
@@ -222,7 +192,7 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
 
           // check for valid closing delimeter and alternative
           c => if c == delimeter {
-            if string.len() > 0 && string != " " {
+            if !string.is_empty() && string != " " {
               break
             } else {
               string.push(tokenizer.next().unwrap())
@@ -236,13 +206,7 @@ impl<'t> Matcher<'t> for StringLiteralMatcher {
 
     tokenizer.advance();
 
-    let mut token = token!(tokenizer, Str, string);
-
-    if raw_marker {
-      token.slice.1 += 1
-    }
-
-    Ok(Some(token))
+    Ok(Some(token!(tokenizer, Str, string)))
   }
 }
 
@@ -252,11 +216,24 @@ pub struct IdentifierMatcher;
 
 impl<'t> Matcher<'t> for IdentifierMatcher {
   fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
-    if !tokenizer.peek().unwrap().is_alphabetic() {
+    let first = tokenizer.peek().unwrap();
+
+    // `_` alone is the wildcard of match arms
+    if !first.is_alphabetic() && first != '_' {
       return Ok(None)
     }
 
-    let accum = tokenizer.collect_while(|c| c.is_alphanumeric() || "_-!?".contains(c));
+    let mut accum = String::new();
+
+    // `a?.b` chains on `a`, so a name doesn't end with a `?` that has a `.` right after it
+    while let Some(c) = tokenizer.peek() {
+      if !(c.is_alphanumeric() || "_-!?".contains(c)) || c == '?' && tokenizer.peek_n(1) == Some('.') {
+        break
+      }
+
+      accum.push(c);
+      tokenizer.advance()
+    }
 
     if accum.is_empty() {
       Ok(None)
@@ -274,30 +251,28 @@ impl<'t> Matcher<'t> for NumberLiteralMatcher {
     let mut accum = String::new();
 
     let curr = tokenizer.next().unwrap();
-    if curr.is_digit(10) {
+    if curr.is_ascii_digit() {
       accum.push(curr)
     } else if curr == '.' {
       accum.push_str("0.")
-    } else if curr == '-' {
-      accum.push('-')
-    }  else {
+    } else {
       return Ok(None)
     }
 
     while !tokenizer.end() {
       let current = tokenizer.peek().unwrap();
-      if !current.is_whitespace() && current.is_digit(10) || current == '.' {
-        if current == '.' && accum.contains('.') {
-          let pos = tokenizer.pos;
+      if !current.is_whitespace() && current.is_ascii_digit() || current == '.' {
+        // `1..3` is a range
+        if current == '.' && tokenizer.peek_n(1) == Some('.') {
+          break
+        }
 
+        if current == '.' && accum.contains('.') {
           return Err(
             response!(
               Wrong("unexpected extra decimal point"),
               tokenizer.source.file,
-              Pos(
-                (pos.0, tokenizer.source.lines.get(pos.0.saturating_sub(1)).unwrap_or(tokenizer.source.lines.last().unwrap()).to_string()),
-                (pos.1 + 1, pos.1 + 1),
-              )
+              tokenizer.source.snippet(&tokenizer.current_position())
             )
           )
         }
@@ -307,7 +282,7 @@ impl<'t> Matcher<'t> for NumberLiteralMatcher {
       }
     }
 
-    if ["-", "-0.", "-.", "0."].contains(&accum.as_str()) {
+    if ["0."].contains(&accum.as_str()) {
       Ok(None)
     } else {
 
@@ -347,7 +322,7 @@ impl KeyMatcher {
 impl<'t> Matcher<'t> for KeyMatcher {
   fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
     for constant in self.constants {
-      let len = if *constant == "øl" { 2 } else { constant.len() };
+      let len = constant.chars().count();
 
       if let Some(s) = tokenizer.peek_range(len) {
         if s == *constant {
@@ -357,7 +332,7 @@ impl<'t> Matcher<'t> for KeyMatcher {
             }
           }
 
-          tokenizer.advance_n(constant.len());
+          tokenizer.advance_n(len);
           return Ok(Some(token!(tokenizer, self.token_type.clone(), constant.to_string())))
         }
       }
@@ -374,9 +349,7 @@ pub struct EOLMatcher;
 impl<'t> Matcher<'t> for EOLMatcher {
   fn try_match(&self, tokenizer: &mut Tokenizer<'t>) -> Result<Option<Token>, ()> {
     if tokenizer.peek() == Some('\n') {
-      tokenizer.pos.0 += 1;
-      tokenizer.pos.1 = 0;
-      tokenizer.index += 1;
+      tokenizer.advance();
 
       Ok(Some(token!(tokenizer, TokenType::EOL, String::from("\n"))))
     } else {
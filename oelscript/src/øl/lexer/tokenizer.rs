@@ -2,15 +2,15 @@ use super::token::*;
 use super::{ Source, Matcher, };
 
 pub struct Snapshot {
-  pub index: usize,
-  pub pos:   (usize, usize),
+  pub index:  usize,
+  pub offset: usize,
 }
 
 impl Snapshot {
-  fn new(index: usize, pos: (usize, usize)) -> Self {
+  fn new(index: usize, offset: usize) -> Self {
     Snapshot {
       index,
-      pos,
+      offset,
     }
   }
 }
@@ -18,9 +18,9 @@ impl Snapshot {
 
 
 pub struct Tokenizer<'t> {
-  pub pos: (usize, usize),
+  pub offset: usize, // byte offset of `index` into the source
 
-  pub index:     usize,
+  pub index:     usize, // char index into `items`
   pub items:     Vec<char>,
   pub source:    &'t Source,
   pub snapshots: Vec<Snapshot>
@@ -29,7 +29,7 @@ pub struct Tokenizer<'t> {
 impl<'t> Tokenizer<'t> {
   pub fn new(items: Vec<char>, source: &'t Source) -> Self {
     Tokenizer {
-      pos: (1, 0),
+      offset: 0,
 
       items,
       source,
@@ -43,8 +43,8 @@ impl<'t> Tokenizer<'t> {
   }
 
   pub fn advance(&mut self) {
-    if let Some(item) = self.items.get(self.index + 1) {
-      self.pos.1 += 1
+    if let Some(item) = self.items.get(self.index) {
+      self.offset += item.len_utf8()
     }
 
     self.index += 1
@@ -69,7 +69,7 @@ impl<'t> Tokenizer<'t> {
   }
 
   pub fn take_snapshot(&mut self) {
-    self.snapshots.push(Snapshot::new(self.index, self.pos));
+    self.snapshots.push(Snapshot::new(self.index, self.offset));
   }
 
   pub fn peek_snapshot(&self) -> Option<&Snapshot> {
@@ -78,31 +78,32 @@ impl<'t> Tokenizer<'t> {
 
   pub fn rollback_snapshot(&mut self) {
     let snapshot = self.snapshots.pop().unwrap();
-    self.index = snapshot.index;
-    self.pos = snapshot.pos;
+    self.index  = snapshot.index;
+    self.offset = snapshot.offset;
   }
 
   pub fn commit_snapshot(&mut self) {
     self.snapshots.pop();
   }
 
-  pub fn last_position(&self) -> (usize, usize) {
-    self.peek_snapshot().unwrap_or(&Snapshot::new(0, (0, 0))).pos
+  // Span from the start of the token being matched up to the current offset
+  pub fn last_position(&self) -> Pos {
+    Pos(self.peek_snapshot().map_or(0, |snapshot| snapshot.offset), self.offset)
   }
 
-  pub fn try_match_token(&mut self, matcher: &Matcher<'t>) -> Result<Option<Token>, ()> {
+  pub fn current_position(&self) -> Pos {
+    let len = self.peek().map_or(0, |c| c.len_utf8());
+
+    Pos(self.offset, self.offset + len)
+  }
+
+  pub fn try_match_token(&mut self, matcher: &dyn Matcher<'t>) -> Result<Option<Token>, ()> {
     if self.end() {
       return Ok(
         Some(
           Token::new(
             TokenType::EOF,
-            (self.pos.0, if self.source.lines.len() > 0 {
-                self.source.lines.get(self.pos.0).unwrap_or(self.source.lines.first().unwrap()).to_string()
-              } else {
-                String::new()
-              }
-            ),
-            (self.pos.1, 0),
+            Pos(self.offset, self.offset),
             ""
           )
         )
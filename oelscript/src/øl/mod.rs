@@ -3,4 +3,9 @@ pub mod error;
 pub mod source;
 pub mod lexer;
 pub mod parser;
-pub mod compiler;
\ No newline at end of file
+pub mod compiler;
+pub mod cst;
+pub mod formatter;
+pub mod vm;
+pub mod prelude;
+pub mod types;
\ No newline at end of file
@@ -0,0 +1,254 @@
+use super::*;
+use super::super::error::Response::Wrong;
+
+use std::mem;
+
+// Puts named arguments where the params of the called function are, so calls are positional from here on
+pub fn arrange_arguments(ast: &mut [Statement], source: &Source) -> Result<(), ()> {
+  let mut arguments = Arguments {
+    scopes: Vec::new(),
+    source,
+  };
+
+  arguments.function(&[], ast)
+}
+
+// What a name is defined as in a function, the params of it when it's a function or a record
+type Definition = (String, Option<Vec<Param>>);
+
+fn definition(defined: Defined) -> Definition {
+  let params = match defined {
+    Defined::Statement(_, &Statement { node: StatementNode::Function(_, ref params, ..), .. }) => Some(params.clone()),
+    Defined::Statement(_, &Statement { node: StatementNode::Record(_, ref fields), .. })       => Some(fields.clone()),
+    _                                                                                          => None,
+  };
+
+  (defined.name().to_string(), params)
+}
+
+
+
+struct Arguments<'a> {
+  scopes: Vec<Vec<Definition>>, // one for each function being walked, blocks in it share it
+  source: &'a Source,
+}
+
+impl<'a> Arguments<'a> {
+  fn function(&mut self, params: &[Param], body: &mut [Statement]) -> Result<(), ()> {
+    let mut definitions = params.iter().map(|param| (param.name.clone(), None)).collect::<Vec<Definition>>();
+
+    visit::definitions(body, &mut |defined| definitions.push(definition(defined)));
+
+    self.scopes.push(definitions);
+
+    let result = self.body(body);
+
+    self.scopes.pop();
+
+    result
+  }
+
+
+
+  // The arguments in the order of the params, the ones left out before a named one are nil so they get their defaults
+  fn arrange(&self, called: &Expression, args: Vec<Expression>, pos: Pos) -> Result<Vec<Expression>, ()> {
+    let name = match called.node {
+      ExpressionNode::Identifier(ref name) => name,
+      _                                    => return Err(self.wrong(String::from("only functions called by their name can be given named arguments"), called.pos)),
+    };
+
+    let params = match self.params(name) {
+      Some(params) => params,
+      None         => return Err(self.wrong(format!("`{}` isn't a function defined here, its arguments can't be named", name), called.pos)),
+    };
+
+    let mut arranged: Vec<Option<Expression>> = Vec::new();
+    let mut named = false;
+
+    for arg in args {
+      let (key, value) = match arg.node {
+        ExpressionNode::Named(key, value) => (key, value),
+        _ if named                        => return Err(self.wrong(String::from("named arguments go after the others"), arg.pos)),
+        _                                 => {
+          arranged.push(Some(arg));
+
+          continue
+        },
+      };
+
+      named = true;
+
+      let index = match params.iter().position(|param| param.name == key) {
+        Some(index) => index,
+        None        => return Err(self.wrong(format!("`{}` has no parameter `{}`", name, key), arg.pos)),
+      };
+
+      if params[index].rest {
+        return Err(self.wrong(format!("`...{}` gathers the arguments left, it can't be named", key), arg.pos))
+      }
+
+      if arranged.len() <= index {
+        arranged.resize(index + 1, None)
+      }
+
+      if arranged[index].is_some() {
+        return Err(self.wrong(format!("`{}` is given twice", key), arg.pos))
+      }
+
+      arranged[index] = Some((*value).clone())
+    }
+
+    for (i, param) in params.iter().enumerate() {
+      if !param.rest && param.default.is_none() && arranged.get(i).is_none_or(Option::is_none) {
+        return Err(self.wrong(format!("missing argument `{}` of `{}`", param.name, name), pos))
+      }
+    }
+
+    self.arity(name, params, arranged.len(), pos)?;
+
+    Ok(arranged.into_iter().map(|arg| arg.unwrap_or_else(|| Expression::new(ExpressionNode::Empty, pos))).collect())
+  }
+
+  // `count` arguments have to reach every param without a default, and can only go past the last one into a rest param
+  fn arity(&self, name: &str, params: &[Param], count: usize, pos: Pos) -> Result<(), ()> {
+    if let Some(param) = params.iter().skip(count).find(|param| !param.rest && param.default.is_none()) {
+      return Err(self.wrong(format!("missing argument `{}` of `{}`", param.name, name), pos))
+    }
+
+    if count > params.len() && !params.last().is_some_and(|param| param.rest) {
+      return Err(self.wrong(format!("`{}` takes {} arguments, found {}", name, params.len(), count), pos))
+    }
+
+    Ok(())
+  }
+
+  // The params of what `name` is in the innermost function defining it, when it's known to be one function or record
+  fn params(&self, name: &str) -> Option<&[Param]> {
+    for scope in self.scopes.iter().rev() {
+      let mut definitions = scope.iter().filter(|&(other, _)| other == name);
+
+      if let Some((_, params)) = definitions.next() {
+        if definitions.next().is_some() {
+          return None
+        }
+
+        return params.as_ref().map(|params| params.as_slice())
+      }
+    }
+
+    None
+  }
+
+  fn wrong(&self, message: String, pos: Pos) {
+    response!(Wrong(message), self.source.file, self.source.snippet(&pos))
+  }
+}
+
+impl<'a> VisitMut for Arguments<'a> {
+  fn statement(&mut self, statement: &mut Statement) -> Result<(), ()> {
+    match statement.node {
+      // defaults are evaluated in the function, where the params before them are known
+      StatementNode::Function(ref mut name, ref mut params, _, ref mut body, ..) => {
+        self.expression(name)?;
+
+        self.scopes.push(params.iter().map(|param| (param.name.clone(), None)).collect());
+
+        let defaults = walk_defaults_mut(self, params);
+
+        self.scopes.pop();
+
+        defaults?;
+
+        self.function(params, body)
+      },
+
+      _ => walk_statement_mut(self, statement),
+    }
+  }
+
+  fn expression(&mut self, expression: &mut Expression) -> Result<(), ()> {
+    use self::ExpressionNode::*;
+
+    walk_expression_mut(self, expression)?;
+
+    let pos = expression.pos;
+
+    if let Call(ref called, ref mut args) = expression.node {
+      if args.iter().any(|arg| matches!(arg.node, Named(..))) {
+        let given = mem::take(args);
+
+        *args = self.arrange(called, given, pos)?
+      } else if let Identifier(ref name) = called.node {
+        // a spread can be any number of arguments
+        if !args.iter().any(|arg| matches!(arg.node, Spread(_))) {
+          if let Some(params) = self.params(name) {
+            self.arity(name, params, args.len(), pos)?
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::super::error;
+
+  use std::rc::Rc;
+
+  const GREET: &str = "øl greet(name, greeting = \"skål\") =\n  øl greeting ++ name\n";
+
+  fn message(code: &str) -> Option<String> {
+    let output = crate::parse_str("<test>", &format!("{}{}", GREET, code));
+
+    output.diagnostics.first().map(|diagnostic| diagnostic.message.clone())
+  }
+
+  #[test]
+  fn arranges_named_arguments() {
+    assert_eq!(message("greet(\"Niels\", greeting: \"hej\")\ngreet(greeting: \"hej\", name: \"Niels\")\n"), None);
+    assert_eq!(message("greet(\"Niels\", name: \"Niels\")\n"), Some("`name` is given twice".to_string()));
+    assert_eq!(message("greet(nmae: \"Niels\")\n"), Some("`greet` has no parameter `nmae`".to_string()))
+  }
+
+  #[test]
+  fn refuses_positional_arguments_after_named_ones() {
+    assert_eq!(message("greet(greeting: \"hej\", \"Niels\")\n"), Some("named arguments go after the others".to_string()));
+
+    // the parser won't have them that way around, so the arranged call is made into one
+    let source = Source::from("<test>", format!("{}greet(\"Niels\", \"hej\")\n", GREET));
+    let tokens = Lexer::default(source.content.chars().collect(), &source).collect::<Result<Vec<Token>, ()>>().unwrap();
+
+    let mut ast = Parser::new(tokens, &source).parse().unwrap();
+
+    if let StatementNode::Expression(Expression { node: ExpressionNode::Call(_, ref mut args), .. }) = ast[1].node {
+      let greeting = args.pop().unwrap();
+      let pos      = greeting.pos;
+
+      args.insert(0, Expression::new(ExpressionNode::Named(String::from("greeting"), Rc::new(greeting)), pos))
+    }
+
+    let (_, diagnostics) = error::capture(|| arrange_arguments(&mut ast, &source));
+
+    assert_eq!(diagnostics.first().map(|diagnostic| diagnostic.message.as_str()), Some("named arguments go after the others"))
+  }
+
+  #[test]
+  fn checks_the_number_of_arguments() {
+    assert_eq!(message("greet(\"Niels\")\ngreet(\"Niels\", \"hej\")\n"), None);
+    assert_eq!(message("greet()\n"), Some("missing argument `name` of `greet`".to_string()));
+    assert_eq!(message("greet(\"Niels\", \"hej\", \"!\")\n"), Some("`greet` takes 2 arguments, found 3".to_string()));
+    assert_eq!(message("greet(\"Niels\", \"hej\", greeting: \"!\")\n"), Some("`greeting` is given twice".to_string()))
+  }
+
+  #[test]
+  fn lets_rest_params_take_the_arguments_left() {
+    assert_eq!(message("øl log(level, ...msgs) =\n  øl msgs\nlog(1, 2, 3)\nøl xs = [1]\ngreet(...xs)\n"), None);
+    assert_eq!(message("øl log(level, ...msgs) =\n  øl msgs\nlog()\n"), Some("missing argument `level` of `log`".to_string()))
+  }
+}
@@ -6,12 +6,20 @@ use super::*;
 #[derive(Debug, Clone, PartialEq)]
 pub enum StatementNode {
   Expression(Expression),
-  Variable(String, Expression, bool),
+  Variable(String, Option<TypeExpression>, Expression, bool),
   Assignment(Expression, Expression),
+  Compound(Expression, Operator, Expression), // `a += b`, what's assigned to is only evaluated once
   Return(Option<Expression>),
-  Function(Expression, Vec<(String)>, Vec<Statement>),
+  Function(Expression, Vec<Param>, Option<TypeExpression>, Vec<Statement>, bool, bool), // Option: return type, bools: asynk, whether it yields
+  TypeAlias(String, TypeExpression),
+  Record(String, Vec<Param>), // the fields are the params of its constructor
+  Enum(String, Vec<Variant>),
+  Match(Expression, Vec<Arm>),
   Import(String),
   If(Expression, Vec<Statement>, Option<(Vec<Statement>, Pos)>),
+  Try(Vec<Statement>, Option<Catch>, Option<(Vec<Statement>, Pos)>), // Option: `endelig` body
+  Throw(Expression),
+  For(String, Expression, Vec<Statement>), // `for x in xs:`
   Skip,
   Break,
 }
@@ -33,20 +41,117 @@ impl Statement {
 
 
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+  pub name:    String,
+  pub ty:      Option<TypeExpression>,
+  pub default: Option<Expression>, // used in place of a missing or nil argument
+  pub rest:    bool,               // `...name` gathers the arguments after the others into an array
+  pub pos:     Pos,
+}
+
+impl Param {
+  pub fn new(name: String, ty: Option<TypeExpression>, pos: Pos) -> Self {
+    Param {
+      name,
+      ty,
+      default: None,
+      rest:    false,
+      pos,
+    }
+  }
+}
+
+
+
+// A case of an enum, its values are tables tagged with its name and holding the fields in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+  pub name:   String,
+  pub fields: Vec<Param>, // none makes it a value instead of a constructor
+  pub pos:    Pos,
+}
+
+impl Variant {
+  pub fn new(name: String, fields: Vec<Param>, pos: Pos) -> Self {
+    Variant {
+      name,
+      fields,
+      pos,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+  Variant(Option<String>, String, Vec<(String, Pos)>), // `Shape.Circle(r)` or `Circle(r)`, binding the fields by position
+  Wildcard,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arm {
+  pub pattern: Pattern,
+  pub body:    Vec<Statement>,
+  pub pos:     Pos, // the pattern
+}
+
+impl Arm {
+  pub fn new(pattern: Pattern, body: Vec<Statement>, pos: Pos) -> Self {
+    Arm {
+      pattern,
+      body,
+      pos,
+    }
+  }
+}
+
+// The key variants keep their name under
+pub const TAG: &str = "tag";
+
+
+
+// `fang e:` after a `prøv:`, errors raised by the runtime are caught as their message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+  pub name: Option<String>,
+  pub body: Vec<Statement>,
+  pub pos:  Pos, // `fang` and the name
+}
+
+impl Catch {
+  pub fn new(name: Option<String>, body: Vec<Statement>, pos: Pos) -> Self {
+    Catch {
+      name,
+      body,
+      pos,
+    }
+  }
+}
+
+
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExpressionNode {
   Int(u64),
   Float(f64),
   Str(String),
   Bool(bool),
+  Nil,
   Neg(Rc<Expression>),
   Not(Rc<Expression>),
   Identifier(String),
   Binary(Rc<Expression>, Operator, Rc<Expression>),
   Array(Vec<Expression>),
-  Table(Vec<(String, Expression)>),
+  Table(Vec<(String, Expression)>), // a `Spread` value has no key, the entries of what it spreads are added
   Call(Rc<Expression>, Vec<Expression>),
   Index(Rc<Expression>, Rc<Expression>, bool), // bool: whether or not it's a method index
+  Construct(Rc<Expression>, Vec<(String, Expression)>), // `Player { x: 1 }`, fields left out get their defaults
+  Await(Rc<Expression>),
+  Yield(Rc<Expression>),
+  Range(Option<Rc<Expression>>, Option<Rc<Expression>>, bool), // bool: whether the end is taken in, the ends are only left out when slicing
+  Spread(Rc<Expression>), // `...xs`, only in arguments, arrays and tables
+  Named(String, Rc<Expression>), // `name: value` in arguments, put where the param is right after parsing
+  Optional(Rc<Expression>), // `a?` of `a?.b`, `a?.[i]` and `a?.(x)`, the chain after it is nil when `a` is
   Empty,
   EOF,
 }
@@ -64,64 +169,145 @@ impl Expression {
       pos,
     }
   }
+
+  // Whether this ends a chain of indexes and calls with a `?.` in it, which may make the whole of it nil
+  pub fn is_optional(&self) -> bool {
+    match self.node {
+      ExpressionNode::Optional(_)                                                   => true,
+      ExpressionNode::Index(ref chained, ..) | ExpressionNode::Call(ref chained, _) => chained.is_optional(),
+      _                                                                             => false,
+    }
+  }
 }
 
 
 
+// Types written in annotations, checked by `types::Checker` and erased when compiling
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeNode {
+  Name(String), // a primitive like `int` or an alias
+  Array(Rc<TypeExpression>),
+  Record(Vec<(String, TypeExpression)>),
+  Function(Vec<TypeExpression>, Rc<TypeExpression>),
+  Nullable(Rc<TypeExpression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeExpression {
+  pub node: TypeNode,
+  pub pos:  Pos,
+}
+
+impl TypeExpression {
+  pub fn new(node: TypeNode, pos: Pos) -> Self {
+    TypeExpression {
+      node,
+      pos,
+    }
+  }
+}
+
+pub const PRIMITIVES: &[&str] = &["int", "float", "str", "bool", "nil", "any"];
+
+
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Operator {
-  Add, Sub, Mul, Div, Mod, Pow, Concat, Eq, Lt, Gt, NEq, LtEq, GtEq, Or, And, PipeL, PipeR,
+  Add, Sub, Mul, Div, Mod, Pow, Concat, Eq, Lt, Gt, NEq, LtEq, GtEq, Or, And, Coalesce, PipeL, PipeR,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+  Left, Right,
 }
 
 impl Operator {
-  pub fn from_str(operator: &str) -> Option<(Operator, u8)> {
+  pub fn from_symbol(operator: &str) -> Option<(Operator, u8)> {
     use self::Operator::*;
 
     let op_prec = match operator {
-      "|>"  => (PipeR,  0),
-      "<|"  => (PipeL,  0),
-      "or"  => (Or,     0),
-      "and" => (And,    0),
-      "=="  => (Eq,     1),
-      "<"   => (Lt,     1),
-      ">"   => (Gt,     1),
-      "!="  => (NEq,    1),
-      "<="  => (LtEq,   1),
-      ">="  => (GtEq,   1),
-      "+"   => (Add,    2),
-      "-"   => (Sub,    2),
-      "++"  => (Concat, 2),
-      "*"   => (Mul,    3),
-      "/"   => (Div,    3),
-      "%"   => (Mod,    3),
-      "^"   => (Pow,    4),
+      "|>"  => (PipeR,    0),
+      "<|"  => (PipeL,    0),
+      "or"  => (Or,       0),
+      "and" => (And,      0),
+      "??"  => (Coalesce, 0),
+      "=="  => (Eq,       1),
+      "<"   => (Lt,       1),
+      ">"   => (Gt,       1),
+      "!="  => (NEq,      1),
+      "<="  => (LtEq,     1),
+      ">="  => (GtEq,     1),
+      "+"   => (Add,      2),
+      "-"   => (Sub,      2),
+      "++"  => (Concat,   2),
+      "*"   => (Mul,      3),
+      "/"   => (Div,      3),
+      "%"   => (Mod,      3),
+      "^"   => (Pow,      4),
       _     => return None,
     };
 
     Some(op_prec)
   }
 
+  // The operator of a compound assignment like `+=`
+  pub fn from_assignment(symbol: &str) -> Option<Operator> {
+    use self::Operator::*;
+
+    if !symbol.ends_with('=') {
+      return None
+    }
+
+    let (op, _) = Self::from_symbol(&symbol[.. symbol.len() - 1])?;
+
+    match op {
+      Add | Sub | Mul | Div | Mod | Pow | Concat => Some(op),
+      _                                          => None,
+    }
+  }
+
+  // Binding power of the operand following a prefix operator, `-a ^ b` is `-(a ^ b)` and `not a == b` is `not (a == b)`
+  pub fn prefix_precedence(operator: &str) -> Option<u8> {
+    let prec = match operator {
+      "not" => 1,
+      "-"   => 4,
+      _     => return None,
+    };
+
+    Some(prec)
+  }
+
+  pub fn associativity(&self) -> Associativity {
+    use self::Operator::*;
+
+    match *self {
+      Pow | PipeL => Associativity::Right,
+      _           => Associativity::Left,
+    }
+  }
+
   pub fn as_str(&self) -> &str {
     use self::Operator::*;
 
     match *self {
-      PipeL  => "<|",
-      PipeR  => "|>",
-      Add    => "+",
-      Sub    => "-",
-      Concat => "++",
-      Pow    => "^",
-      Mul    => "*",
-      Div    => "/",
-      Mod    => "%",
-      Eq     => "==",
-      Lt     => "<",
-      Gt     => ">",
-      NEq    => "!=",
-      LtEq   => "<=",
-      GtEq   => ">=",
-      Or     => "or",
-      And    => "and",
+      PipeL    => "<|",
+      PipeR    => "|>",
+      Add      => "+",
+      Sub      => "-",
+      Concat   => "++",
+      Pow      => "^",
+      Mul      => "*",
+      Div      => "/",
+      Mod      => "%",
+      Eq       => "==",
+      Lt       => "<",
+      Gt       => ">",
+      NEq      => "!=",
+      LtEq     => "<=",
+      GtEq     => ">=",
+      Or       => "or",
+      And      => "and",
+      Coalesce => "??",
     }
   }
 }
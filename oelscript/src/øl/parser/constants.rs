@@ -0,0 +1,96 @@
+use super::*;
+use super::super::error::Response::Wrong;
+
+// Refuses assignments to `iskold` names, which JavaScript would only refuse when running them
+pub fn check_constants(ast: &[Statement], source: &Source) -> Result<(), ()> {
+  let mut constants = Constants {
+    scopes: Vec::new(),
+    source,
+  };
+
+  constants.function(&[], ast)
+}
+
+struct Constants<'a> {
+  scopes: Vec<Vec<(&'a str, bool)>>, // the names a function defines, and whether they're `iskold`
+  source: &'a Source,
+}
+
+impl<'a> Constants<'a> {
+  fn function(&mut self, params: &'a [Param], body: &'a [Statement]) -> Result<(), ()> {
+    let mut names = params.iter().map(|param| (param.name.as_str(), false)).collect::<Vec<(&str, bool)>>();
+
+    visit::definitions(body, &mut |defined| {
+      let is_const = matches!(defined, Defined::Statement(_, &Statement { node: StatementNode::Variable(.., true), .. }));
+
+      names.push((defined.name(), is_const))
+    });
+
+    self.scopes.push(names);
+
+    let result = self.body(body);
+
+    self.scopes.pop();
+
+    result
+  }
+
+  // Whether `name` is `iskold` in the innermost function defining it
+  fn is_const(&self, name: &str) -> bool {
+    for scope in self.scopes.iter().rev() {
+      let mut names = scope.iter().filter(|&&(other, _)| other == name).peekable();
+
+      if names.peek().is_some() {
+        return names.any(|&(_, is_const)| is_const)
+      }
+    }
+
+    false
+  }
+
+  fn wrong(&self, message: String, pos: Pos) {
+    response!(Wrong(message), self.source.file, self.source.snippet(&pos))
+  }
+}
+
+impl<'a> Visit<'a> for Constants<'a> {
+  fn statement(&mut self, statement: &'a Statement) -> Result<(), ()> {
+    match statement.node {
+      StatementNode::Function(_, ref params, _, ref body, ..) => self.function(params, body),
+
+      StatementNode::Assignment(ref left, _) | StatementNode::Compound(ref left, ..) => match left.node {
+        ExpressionNode::Identifier(ref name) if self.is_const(name) => {
+          Err(self.wrong(format!("`{}` is `iskold`, it can't be assigned to", name), left.pos))
+        },
+
+        _ => walk_statement(self, statement),
+      },
+
+      _ => walk_statement(self, statement),
+    }
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  fn message(code: &str) -> Option<String> {
+    let output = crate::parse_str("<test>", code);
+
+    output.diagnostics.first().map(|diagnostic| diagnostic.message.clone())
+  }
+
+  #[test]
+  fn refuses_assignments_to_constants() {
+    assert_eq!(message("iskold øl c = 1\nc = 2\n"), Some("`c` is `iskold`, it can't be assigned to".to_string()));
+    assert_eq!(message("iskold øl c = 1\nc += 2\n"), Some("`c` is `iskold`, it can't be assigned to".to_string()));
+    assert_eq!(message("iskold øl c = 1\nøl f() =\n  c = 2\n"), Some("`c` is `iskold`, it can't be assigned to".to_string()))
+  }
+
+  #[test]
+  fn lets_what_shadows_constants_be_assigned() {
+    assert_eq!(message("iskold øl c = 1\nøl f(c) =\n  c += 2\n  øl c\n"), None);
+    assert_eq!(message("iskold øl c = [1]\nc[0] = 2\n"), None)
+  }
+}
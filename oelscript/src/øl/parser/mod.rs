@@ -1,8 +1,19 @@
 pub mod ast;
+pub mod visit;
+#[allow(clippy::module_inception)]
 pub mod parser;
+pub mod variants;
+pub mod arguments;
+pub mod constants;
+pub mod records;
 
 use super::lexer::*;
 use super::source::*;
 
 pub use self::ast::*;
+pub use self::visit::*;
 pub use self::parser::*;
+pub use self::variants::*;
+pub use self::arguments::*;
+pub use self::constants::*;
+pub use self::records::*;
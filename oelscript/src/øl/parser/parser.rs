@@ -1,7 +1,8 @@
 use super::*;
-use super::super::error::Response::Wrong;
+use super::super::error::{ self, Response::Wrong };
 
 use std::rc::Rc;
+use std::mem;
 
 pub struct Parser<'p> {
   index:  usize,
@@ -9,7 +10,24 @@ pub struct Parser<'p> {
   source: &'p Source,
 
   indent_standard: usize,
+  indent_origin:   Pos,
   indent:          usize,
+
+  async_next: bool, // `asynk` was just read, the function after it is one
+  context:    Context,
+  depth:      usize, // of the operations and chains being parsed, brackets parsed apart carry it on
+}
+
+// How deep expressions can nest, parsing past it would run out of stack
+pub const NESTING_MAX: usize = 128;
+
+// What the statements being parsed are in, reset for the body of every function
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+  function: bool,
+  is_async: bool, // where `vent` can be used
+  looping:  bool, // where `break` and `skip` can be used
+  yields:   bool, // a `gi` was found, making the function a generator
 }
 
 impl<'p> Parser<'p> {
@@ -20,7 +38,12 @@ impl<'p> Parser<'p> {
       index: 0,
 
       indent_standard: 0,
+      indent_origin:   Pos::default(),
       indent:          0,
+
+      async_next: false,
+      context:    Context::default(),
+      depth:      0,
     }
   }
 
@@ -33,6 +56,11 @@ impl<'p> Parser<'p> {
       ast.push(self.parse_statement()?)
     }
 
+    check_variants(&ast, self.source)?;
+    check_constants(&ast, self.source)?;
+    check_records(&ast, self.source)?;
+    arrange_arguments(&mut ast, self.source)?;
+
     Ok(ast)
   }
 
@@ -48,7 +76,147 @@ impl<'p> Parser<'p> {
     let position = self.current_position();
 
     let statement = match self.current_type() {
+      // `type Player = { x: float, y: float }`
+      Identifier if self.current_lexeme() == "type" && self.peek_type(1) == Some(Identifier) => {
+        self.next()?;
+
+        let name = self.eat_type(&Identifier)?;
+
+        self.eat_lexeme("=")?;
+
+        let ty = self.parse_type()?;
+
+        let result = Statement::new(
+          StatementNode::TypeAlias(name, ty),
+          self.span_from(position),
+        );
+
+        self.new_line()?;
+
+        result
+      },
+
+      // `record Player = { name: str, hp: int = 100 }`
+      Identifier if self.current_lexeme() == "record" && self.peek_type(1) == Some(Identifier) => {
+        self.next()?;
+
+        let name = self.eat_type(&Identifier)?;
+
+        self.eat_lexeme("=")?;
+
+        let fields = self.parse_block_of(("{", "}"), &Self::_parse_field_comma)?;
+
+        let result = Statement::new(
+          StatementNode::Record(name, fields),
+          self.span_from(position),
+        );
+
+        self.new_line()?;
+
+        result
+      },
+
+      // `enum Shape = { Circle(radius: float), Empty }`
+      Identifier if self.current_lexeme() == "enum" && self.peek_type(1) == Some(Identifier) => {
+        self.next()?;
+
+        let name = self.eat_type(&Identifier)?;
+
+        self.eat_lexeme("=")?;
+
+        let variants = self.parse_block_of(("{", "}"), &Self::_parse_variant_comma)?;
+
+        let result = Statement::new(
+          StatementNode::Enum(name, variants),
+          self.span_from(position),
+        );
+
+        self.new_line()?;
+
+        result
+      },
+
+      // `prøv:` followed by `fang e:`, `endelig:` or both
+      Identifier if self.current_lexeme() == "prøv" && self.peek_lexeme(1) == Some(String::from(":")) => {
+        return self.parse_try(position)
+      },
+
+      // `kast "out of cheese"`
+      Identifier if self.is_prefix("kast") => {
+        self.next()?;
+
+        let value = self.parse_expression()?;
+
+        let result = Statement::new(
+          StatementNode::Throw(value),
+          self.span_from(position),
+        );
+
+        self.new_line()?;
+
+        result
+      },
+
+      // `asynk øl fetch-all(urls) =`
+      Identifier if self.current_lexeme() == "asynk" && self.peek_lexeme(1) == Some(String::from("øl")) => {
+        self.next()?;
+
+        self.async_next = true;
+
+        let statement = self.parse_statement();
+
+        self.async_next = false;
+
+        let statement = statement?;
+
+        match statement.node {
+          StatementNode::Function(..) => Statement::new(statement.node, self.span_from(position)),
+          _                           => return Err(
+            response!(
+              Wrong("only functions can be `asynk`"),
+              self.source.file,
+              self.source.snippet(&statement.pos)
+            )
+          ),
+        }
+      },
+
+      // `for x in xs:`
+      Identifier if self.current_lexeme() == "for" && self.peek_type(1) == Some(Identifier) && self.peek_lexeme(2) == Some(String::from("in")) => {
+        return self.parse_for(position)
+      },
+
+      // `break` and `skip` alone on their line
+      Identifier if (self.current_lexeme() == "break" || self.current_lexeme() == "skip") && self.peek_lexeme(1).is_none_or(|lexeme| lexeme == "\n") => {
+        let word = self.eat()?;
+
+        if !self.context.looping {
+          return Err(
+            response!(
+              Wrong(format!("`{}` outside of a loop", word)),
+              self.source.file,
+              self.source.snippet(&position)
+            )
+          )
+        }
+
+        let node = if word == "break" { StatementNode::Break } else { StatementNode::Skip };
+
+        let result = Statement::new(node, self.span_from(position));
+
+        self.new_line()?;
+
+        result
+      },
+
       Identifier => {
+        // `match shape:` with the arms indented below it
+        if self.current_lexeme() == "match" {
+          if let Some(subject) = self.speculate(Self::parse_match_head) {
+            return self.parse_match(subject, position)
+          }
+        }
+
         let backup_index = self.index;
         let position     = self.current_position();
         let name         = self.eat_type(&Identifier)?;
@@ -61,7 +229,7 @@ impl<'p> Parser<'p> {
               StatementNode::Assignment(
                 Expression::new(
                   ExpressionNode::Identifier(name),
-                  position.clone()
+                  position
                 ),
 
                 self.parse_expression()?
@@ -79,19 +247,10 @@ impl<'p> Parser<'p> {
 
             let expression = self.parse_expression()?;
 
-            let position = expression.pos.clone();
+            let position = expression.pos;
 
-            if self.current_lexeme() == "=" {
-              self.next()?;
-
-              let result = Statement::new(
-                StatementNode::Assignment(expression, self.parse_expression()?),
-                position
-              );
-
-              self.new_line()?;
-
-              result
+            if self.is_assignment() {
+              self.parse_assignment(expression)?
             } else {
               self.new_line()?;
 
@@ -108,21 +267,21 @@ impl<'p> Parser<'p> {
         "iskold" => {
           self.next()?;
 
-          let position = self.current_position().clone();
+          let position = self.current_position();
 
-          let mut potential_var = self.parse_statement()?;
+          let potential_var = self.parse_statement()?;
 
-          if let StatementNode::Variable(ref left, ref right, _) = potential_var.node {
+          if let StatementNode::Variable(ref left, ref ty, ref right, _) = potential_var.node {
             Statement::new(
-              StatementNode::Variable(left.to_owned(), right.to_owned(), true),
+              StatementNode::Variable(left.to_owned(), ty.to_owned(), right.to_owned(), true),
               potential_var.pos
             )
           } else {
             return Err(
               response!(
-                Wrong(format!("invalid øldentifier!")),
+                Wrong("invalid øldentifier!".to_string()),
                 self.source.file,
-                position
+                self.source.snippet(&position)
               )
             )
           }
@@ -150,26 +309,6 @@ impl<'p> Parser<'p> {
           }
         },
 
-        "break" => {
-          self.next()?;
-          self.new_line()?;
-
-          Statement::new(
-            StatementNode::Break,
-            position
-          )
-        },
-
-        "skip" => {
-          self.next()?;
-          self.new_line()?;
-
-          Statement::new(
-            StatementNode::Skip,
-            position
-          )
-        },
-
         "ølport" => {
           self.next()?;
 
@@ -186,133 +325,174 @@ impl<'p> Parser<'p> {
         "øl" => {
           self.next()?;
 
-          let mut params = Vec::new();
-          let expr = self.parse_expression()?;
-
-          let name = match expr.node {
-            ExpressionNode::Call(ref called, ref args) => {
-              let name = (**called).clone();
-
-              for param in args.iter() {
-                match &param.node {
-                  &ExpressionNode::Identifier(ref name) => params.push(name.to_owned()),
-                  _ => return Err(
-                    response!(
-                      Wrong(format!("what?")),
-                      self.source.file,
-                      param.pos
-                    )
-                  )
-                }
-              }
+          let is_async = mem::replace(&mut self.async_next, false);
 
-              name
-            },
-            ExpressionNode::Identifier(ref name) if self.current_lexeme() == "=" => {
-              self.next()?;
+          // `øl x: int = 4`
+          if let Some((name, ty)) = self.speculate(Self::parse_annotated_name) {
+            let value = self.parse_expression()?;
 
-              let result = Statement::new(
-                StatementNode::Variable(
-                  name.clone(),
-                  self.parse_expression()?,
-                  false
-                ),
-                position,
-              );
+            let result = Statement::new(
+              StatementNode::Variable(name, Some(ty), value, false),
+              self.span_from(position),
+            );
 
-              self.new_line()?;
+            self.new_line()?;
 
-              return Ok(result)
-            },
-            ref e => {
-              let condition = Expression::new(
-                e.clone(),
-                expr.pos.clone()
-              );
-
-              if self.current_lexeme() == ":" {
-                self.next()?;
-
-                let pos = self.span_from(expr.pos);
-                let mut else_pos = position.clone();
-                let body = if self.current_lexeme() == "\n" {
+            return Ok(result)
+          }
+
+          let (name, params, ret) = match self.speculate(Self::parse_function_head) {
+            Some(head) => head,
+            None       => {
+              let mut params = Vec::new();
+              let expr = self.parse_expression()?;
+
+              let name = match expr.node {
+                ExpressionNode::Call(ref called, ref args) => {
+                  let name = (**called).clone();
+
+                  for param in args.iter() {
+                    match &param.node {
+                      ExpressionNode::Identifier(name) => params.push(Param::new(name.to_owned(), None, param.pos)),
+                      _ => return Err(
+                        response!(
+                          Wrong("what?".to_string()),
+                          self.source.file,
+                          self.source.snippet(&param.pos)
+                        )
+                      )
+                    }
+                  }
+
+                  name
+                },
+                ExpressionNode::Identifier(ref name) if self.current_lexeme() == "=" => {
                   self.next()?;
-                  self.next_newline()?;
 
-                  self.parse_body()?
-                } else {
-                  vec!(self.parse_statement()?)
-                };
+                  let value = self.parse_expression()?;
+
+                  let result = Statement::new(
+                    StatementNode::Variable(
+                      name.clone(),
+                      None,
+                      value,
+                      false
+                    ),
+                    self.span_from(position),
+                  );
 
-                if self.current_lexeme() == "ølse" {
-                  let pos = self.current_position();
+                  self.new_line()?;
 
-                  self.next()?;
-                  self.eat_lexeme(":")?;
+                  return Ok(result)
+                },
+                ref e => {
+                  let condition = Expression::new(
+                    e.clone(),
+                    expr.pos
+                  );
 
-                  let else_body = if self.current_lexeme() == "\n" {
+                  if self.current_lexeme() == ":" {
                     self.next()?;
-                    self.next_newline()?;
 
-                    self.parse_body()?
+                    let body = if self.current_lexeme() == "\n" {
+                      self.next()?;
+                      self.next_newline()?;
+
+                      self.parse_body()?
+                    } else {
+                      vec!(self.parse_statement()?)
+                    };
+
+                    if self.current_lexeme() == "ølse" {
+                      let pos = self.current_position();
+
+                      self.next()?;
+                      self.eat_lexeme(":")?;
+
+                      let else_body = if self.current_lexeme() == "\n" {
+                        self.next()?;
+                        self.next_newline()?;
+
+                        self.parse_body()?
+                      } else {
+                        vec!(self.parse_statement()?)
+                      };
+
+                      return Ok(
+                        Statement::new(
+                          StatementNode::If(condition, body, Some((else_body, pos))),
+                          self.span_from(position),
+                        )
+                      )
+                    } else {
+                      return Ok(
+                        Statement::new(
+                          StatementNode::If(condition, body, None),
+                          self.span_from(position),
+                        )
+                      )
+                    }
                   } else {
-                    vec!(self.parse_statement()?)
-                  };
-
-                  return Ok(
-                    Statement::new(
-                      StatementNode::If(condition, body, Some((else_body, pos.clone()))),
-                      pos,
-                    )
-                  )
-                } else {
-                  return Ok(
-                    Statement::new(
-                      StatementNode::If(condition, body, None),
-                      pos,
+                    return Ok(
+                      Statement::new(
+                        StatementNode::Return(
+                          Some(
+                            condition
+                          ),
+                        ),
+                        self.span_from(position)
+                      )
                     )
-                  )
+                  }
                 }
-              } else {
-                return Ok(
-                  Statement::new(
-                    StatementNode::Return(
-                      Some(
-                        condition
-                      ),
-                    ),
-                    self.span_from(position.clone())
-                  )
-                )
-              }
-            }
+              };
+
+              (name, params, None)
+            },
           };
 
+          if let Some(param) = params.iter().rev().skip(1).find(|param| param.rest) {
+            return Err(
+              response!(
+                Wrong(format!("`...{}` gathers the arguments left, it has to be the last parameter", param.name)),
+                self.source.file,
+                self.source.snippet(&param.pos)
+              )
+            )
+          }
+
+          if let Some(param) = params.iter().find(|param| param.rest && param.default.is_some()) {
+            return Err(
+              response!(
+                Wrong(format!("`...{}` can't have a default, it's empty when no arguments are left", param.name)),
+                self.source.file,
+                self.source.snippet(&param.pos)
+              )
+            )
+          }
+
           self.eat_lexeme("=")?;
 
-          let position = self.span_from(position);
+          let outer = mem::replace(&mut self.context, Context { function: true, is_async, ..Context::default() });
 
-          let body = if self.current_lexeme() == "\n" {
-            self.next()?;
-            self.next_newline()?;
+          let body = self.parse_block_body();
 
-            self.parse_body()?
-          } else {
-            vec!(self.parse_statement()?)
-          };
+          let is_generator = self.context.yields;
+
+          self.context = outer;
 
           Statement::new(
             StatementNode::Function(
-              name, params, body
+              name, params, ret, body?, is_async, is_generator
             ),
-            position
+            self.span_from(position)
           )
         },
 
         _ => {
           let expression = self.parse_expression()?;
 
-          let position = expression.pos.clone();
+          let position = expression.pos;
 
           Statement::new(
             StatementNode::Expression(expression),
@@ -323,19 +503,10 @@ impl<'p> Parser<'p> {
 
       _ => {
         let expression = self.parse_expression()?;
-        let position   = expression.pos.clone();
-
-        if self.current_lexeme() == "=" {
-          self.next()?;
-
-          let result = Statement::new(
-            StatementNode::Assignment(expression, self.parse_expression()?),
-            position
-          );
-
-          self.new_line()?;
+        let position   = expression.pos;
 
-          result
+        if self.is_assignment() {
+          self.parse_assignment(expression)?
         } else {
           self.new_line()?;
 
@@ -353,18 +524,42 @@ impl<'p> Parser<'p> {
 
 
   fn parse_body(&mut self) -> Result<Vec<Statement>, ()> {
+    self.parse_indented(Self::parse_statement)
+  }
+
+  // `: statement` on the same line, or `:` with an indented body below
+  fn parse_block(&mut self) -> Result<Vec<Statement>, ()> {
+    self.eat_lexeme(":")?;
+
+    self.parse_block_body()
+  }
+
+  fn parse_block_body(&mut self) -> Result<Vec<Statement>, ()> {
+    if self.current_lexeme() == "\n" {
+      self.next()?;
+      self.next_newline()?;
+
+      self.parse_body()
+    } else {
+      Ok(vec!(self.parse_statement()?))
+    }
+  }
+
+  // Parses with `parse` until the indentation drops back below the block's
+  fn parse_indented<T>(&mut self, parse: fn(&mut Self) -> Result<T, ()>) -> Result<Vec<T>, ()> {
     let backup_indent = self.indent;
     self.indent       = self.get_indent();
 
     if self.indent_standard == 0 {
-      self.indent_standard = self.indent
+      self.indent_standard = self.indent;
+      self.indent_origin   = self.current_position();
     } else {
-      if self.indent % self.indent_standard != 0 {
+      if !self.indent.is_multiple_of(self.indent_standard) {
         return Err(
           response!(
-            Wrong(format!("found inconsistently indented token, expected {} found {}", self.indent, self.indent_standard)),
+            Wrong(format!("found inconsistently indented token, expected multiple of {} found {}", self.indent_standard, self.indent)),
             self.source.file,
-            self.current_position()
+            self.source.snippet(&self.current_position()).label(&self.indent_origin, "indentation first set here")
           )
         )
       }
@@ -373,11 +568,11 @@ impl<'p> Parser<'p> {
     let mut stack = Vec::new();
 
     while !self.is_dedent() && self.remaining() > 0 {
-      let statement = self.parse_statement()?;
+      let element = parse(self)?;
 
       self.next_newline()?;
 
-      stack.push(statement)
+      stack.push(element)
     }
 
     self.indent = backup_indent;
@@ -388,113 +583,712 @@ impl<'p> Parser<'p> {
 
 
   fn parse_expression(&mut self) -> Result<Expression, ()> {
-    let atom = self.parse_atom()?;
+    let expression = self.parse_binary(0)?;
 
-    if self.current_type() == TokenType::Operator {
-      self.parse_binary(atom)
+    if self.is_range() {
+      self.parse_range(Some(expression), false)
     } else {
-      Ok(atom)
+      Ok(expression)
     }
   }
 
+  // A slice is a copy, assigning to it wouldn't change what it was taken from
+  fn is_assignment(&self) -> bool {
+    self.remaining() > 0 && self.current_type() == TokenType::Symbol && (self.current_lexeme() == "=" || Operator::from_assignment(&self.current_lexeme()).is_some())
+  }
 
+  // `a = b`, or `a += b` and the like updating it with an operator
+  fn parse_assignment(&mut self, target: Expression) -> Result<Statement, ()> {
+    self.expect_assignable(&target)?;
 
-  fn parse_atom(&mut self) -> Result<Expression, ()> {
-    use self::TokenType::*;
+    let position = target.pos;
+    let operator = Operator::from_assignment(&self.eat()?);
+    let value    = self.parse_expression()?;
 
-    if self.remaining() == 0 {
-      Ok(
-        Expression::new(
-          ExpressionNode::EOF,
-          self.current_position()
-        )
-      )
-    } else {
-      let token_type = self.current_type().clone();
-      let position   = self.current_position();
+    let node = match operator {
+      Some(op) => StatementNode::Compound(target, op, value),
+      None     => StatementNode::Assignment(target, value),
+    };
 
-      let expression = match token_type {
-        Int => Expression::new(
-          ExpressionNode::Int(self.eat()?.parse::<u64>().unwrap()),
-          position
-        ),
+    let result = Statement::new(node, position);
 
-        Float => Expression::new(
-          ExpressionNode::Float(self.eat()?.parse::<f64>().unwrap()),
-          position
-        ),
+    self.new_line()?;
 
-        Str => Expression::new(
-          ExpressionNode::Str(self.eat()?),
-          position
-        ),
+    Ok(result)
+  }
 
-        Identifier => Expression::new(
-          ExpressionNode::Identifier(self.eat()?),
-          position
-        ),
+  fn expect_assignable(&self, target: &Expression) -> Result<(), ()> {
+    if target.is_optional() {
+      return Err(
+        response!(
+          Wrong("can't assign through `?.`, there may be nothing to assign to"),
+          self.source.file,
+          self.source.snippet(&target.pos)
+        )
+      )
+    }
 
-        Bool => Expression::new(
-          ExpressionNode::Bool(self.eat()? == "true"),
-          position
-        ),
+    if let ExpressionNode::Index(_, ref index, _) = target.node {
+      if let ExpressionNode::Range(..) = index.node {
+        return Err(
+          response!(
+            Wrong("can't assign to a slice"),
+            self.source.file,
+            self.source.snippet(&target.pos)
+          )
+        )
+      }
+    }
 
-        Operator => match self.current_lexeme().as_str() {
-          "-" => {
-            self.next()?;
+    Ok(())
+  }
 
-            Expression::new(
-              ExpressionNode::Neg(
-                Rc::new(self.parse_expression()?)
-              ),
+  fn is_spread(&self) -> bool {
+    self.remaining() > 0 && self.current_type() == TokenType::Symbol && self.current_lexeme() == "..."
+  }
 
-              self.span_from(position)
-            )
-          },
+  fn is_spread_of(expression: &Expression) -> bool {
+    matches!(expression.node, ExpressionNode::Spread(_))
+  }
 
-          "not" => {
-            self.next()?;
+  fn is_named_arg(expression: &Expression) -> bool {
+    matches!(expression.node, ExpressionNode::Named(..))
+  }
 
-            Expression::new(
-              ExpressionNode::Not(
-                Rc::new(self.parse_expression()?)
-              ),
+  // Named arguments go after the others, where they'd go after a spread isn't known
+  fn check_named(&self, args: &[Expression]) -> Result<(), ()> {
+    let first = match args.iter().position(Self::is_named_arg) {
+      Some(first) => first,
+      None        => return Ok(()),
+    };
 
-              self.span_from(position)
-            )
-          },
+    if let Some(arg) = args[first ..].iter().find(|arg| !Self::is_named_arg(arg)) {
+      return Err(
+        response!(
+          Wrong("named arguments go after the others"),
+          self.source.file,
+          self.source.snippet(&arg.pos)
+        )
+      )
+    }
 
-          ref symbol => return Err(
-            response!(
-              Wrong(format!("unexpected operator `{}`", symbol)),
-              self.source.file,
-              self.current_position()
-            )
-          )
-        },
+    if let Some(arg) = args.iter().find(|arg| Self::is_spread_of(arg)) {
+      return Err(
+        response!(
+          Wrong("arguments can't be spread in a call with named ones"),
+          self.source.file,
+          self.source.snippet(&arg.pos)
+        )
+      )
+    }
 
-        Symbol => match self.current_lexeme().as_str() {
-          "{" => Expression::new(
-            ExpressionNode::Table(self.parse_block_of(("{", "}"), &Self::_parse_definition_comma)?),
-            self.span_from(position)
-          ),
+    Ok(())
+  }
 
-          "[" => Expression::new(
-            ExpressionNode::Array(self.parse_block_of(("[", "]"), &Self::_parse_expression_comma)?),
-            self.span_from(position)
-          ),
+  // `...xs` in arguments, arrays and tables
+  fn parse_spread(&mut self) -> Result<Expression, ()> {
+    let position = self.current_position();
 
-          "(" => {
-            self.next()?;
-            self.next_newline()?;
+    self.next()?;
 
-            if self.current_lexeme() == ")" {
-              self.next()?;
+    let spread = self.parse_expression()?;
 
-              Expression::new(
-                ExpressionNode::Empty,
-                self.span_from(position)
-              )
+    Ok(
+      Expression::new(
+        ExpressionNode::Spread(Rc::new(spread)),
+        self.span_from(position)
+      )
+    )
+  }
+
+  fn is_named(&self) -> bool {
+    self.remaining() > 1 && self.current_type() == TokenType::Identifier && self.peek_lexeme(1) == Some(String::from(":"))
+  }
+
+  // `name: value` passes `value` to the param called `name`
+  fn parse_named(&mut self) -> Result<Expression, ()> {
+    let position = self.current_position();
+    let name     = self.eat()?;
+
+    self.next()?;
+
+    let value = self.parse_expression()?;
+
+    Ok(
+      Expression::new(
+        ExpressionNode::Named(name, Rc::new(value)),
+        self.span_from(position)
+      )
+    )
+  }
+
+  fn is_range(&self) -> bool {
+    self.remaining() > 0 && self.current_type() == TokenType::Symbol && ["..", "..."].contains(&self.current_lexeme().as_str())
+  }
+
+  // `a..b` leaves out `b` and `a...b` takes it in, `xs[..2]` and `xs[1..]` slice from the start and to the end
+  fn parse_range(&mut self, start: Option<Expression>, slicing: bool) -> Result<Expression, ()> {
+    let position = start.as_ref().map_or(self.current_position(), |start| start.pos);
+    let operator = self.current_position();
+
+    let inclusive = self.eat()? == "...";
+
+    let end = if slicing && self.current_lexeme() == "]" {
+      None
+    } else {
+      Some(self.parse_binary(0)?)
+    };
+
+    if start.is_none() && end.is_none() {
+      return Err(
+        response!(
+          Wrong("a slice needs a start, an end or both"),
+          self.source.file,
+          self.source.snippet(&operator)
+        )
+      )
+    }
+
+    Ok(
+      Expression::new(
+        ExpressionNode::Range(start.map(Rc::new), end.map(Rc::new), inclusive),
+        self.span_from(position)
+      )
+    )
+  }
+
+
+
+  // `name(a: int, b): int`, leaving the `=` after it
+  fn parse_function_head(&mut self) -> Result<(Expression, Vec<Param>, Option<TypeExpression>), ()> {
+    let position = self.current_position();
+
+    let mut name = Expression::new(
+      ExpressionNode::Identifier(self.eat_type(&TokenType::Identifier)?),
+      position
+    );
+
+    // `øl a.b(c) =` defines a field
+    while [".", "\\"].contains(&self.current_lexeme().as_str()) {
+      let method         = self.eat()? == "\\";
+      let field_position = self.current_position();
+
+      let field = Expression::new(
+        ExpressionNode::Str(self.eat_type(&TokenType::Identifier)?),
+        field_position
+      );
+
+      name = Expression::new(
+        ExpressionNode::Index(Rc::new(name), Rc::new(field), method),
+        self.span_from(position)
+      )
+    }
+
+    let params = self.parse_block_of(("(", ")"), &Self::_parse_param_comma)?;
+
+    let ret = if self.current_lexeme() == ":" {
+      self.next()?;
+
+      Some(self.parse_annotation()?)
+    } else {
+      None
+    };
+
+    self.expect_lexeme("=")?;
+
+    Ok((name, params, ret))
+  }
+
+  // `word` with a value after it like `kast` and `vent`, a call of something named `word` has parentheses right after it
+  fn is_prefix(&self, word: &str) -> bool {
+    use self::TokenType::*;
+
+    self.current_lexeme() == word && match self.peek_type(1) {
+      Some(Identifier) | Some(Str) | Some(Int) | Some(Float) | Some(Bool) => true,
+      _                                                                   => self.peek_lexeme(1) == Some(String::from("{")),
+    }
+  }
+
+  fn parse_try(&mut self, position: Pos) -> Result<Statement, ()> {
+    let indent = self.get_indent();
+
+    self.next()?;
+
+    let body = self.parse_block()?;
+
+    self.next_newline()?;
+
+    // only what's lined up with `prøv` belongs to it
+    let catch = if self.current_lexeme() == "fang" && self.get_indent() == indent {
+      let pos = self.current_position();
+
+      self.next()?;
+
+      let name = if self.current_type() == TokenType::Identifier {
+        Some(self.eat_type(&TokenType::Identifier)?)
+      } else {
+        None
+      };
+
+      let pos = self.span_from(pos);
+
+      let body = self.parse_block()?;
+
+      self.next_newline()?;
+
+      Some(Catch::new(name, body, pos))
+    } else {
+      None
+    };
+
+    let finally = if self.current_lexeme() == "endelig" && self.get_indent() == indent {
+      let pos = self.current_position();
+
+      self.next()?;
+
+      Some((self.parse_block()?, pos))
+    } else {
+      None
+    };
+
+    if catch.is_none() && finally.is_none() {
+      return Err(
+        response!(
+          Wrong("`prøv` needs a `fang` or an `endelig` after it"),
+          self.source.file,
+          self.source.snippet(&position)
+        )
+      )
+    }
+
+    Ok(
+      Statement::new(
+        StatementNode::Try(body, catch, finally),
+        self.span_from(position),
+      )
+    )
+  }
+
+  fn parse_for(&mut self, position: Pos) -> Result<Statement, ()> {
+    self.next()?;
+
+    let name = self.eat_type(&TokenType::Identifier)?;
+
+    self.eat_lexeme("in")?;
+
+    let iterable = self.parse_expression()?;
+
+    let looping = mem::replace(&mut self.context.looping, true);
+
+    let body = self.parse_block();
+
+    self.context.looping = looping;
+
+    Ok(
+      Statement::new(
+        StatementNode::For(name, iterable, body?),
+        self.span_from(position),
+      )
+    )
+  }
+
+  // `match subject:`, leaving the newline after it
+  fn parse_match_head(&mut self) -> Result<Expression, ()> {
+    self.eat_lexeme("match")?;
+
+    let subject = self.parse_expression()?;
+
+    self.eat_lexeme(":")?;
+    self.expect_lexeme("\n")?;
+
+    Ok(subject)
+  }
+
+  fn parse_match(&mut self, subject: Expression, position: Pos) -> Result<Statement, ()> {
+    self.next()?;
+    self.next_newline()?;
+
+    let arms = self.parse_indented(Self::parse_arm)?;
+
+    if let Some(i) = arms.iter().position(|arm| arm.pattern == Pattern::Wildcard) {
+      if i < arms.len() - 1 {
+        return Err(
+          response!(
+            Wrong("arms after `_` are never matched"),
+            self.source.file,
+            self.source.snippet(&arms[i + 1].pos)
+          )
+        )
+      }
+    }
+
+    Ok(
+      Statement::new(
+        StatementNode::Match(subject, arms),
+        self.span_from(position),
+      )
+    )
+  }
+
+  // `Circle(r): body`, `Shape.Circle(r): body` or `_: body`
+  fn parse_arm(&mut self) -> Result<Arm, ()> {
+    let position = self.current_position();
+
+    let pattern = if self.current_lexeme() == "_" {
+      self.next()?;
+
+      Pattern::Wildcard
+    } else {
+      let mut name    = self.eat_type(&TokenType::Identifier)?;
+      let mut of_enum = None;
+
+      if self.current_lexeme() == "." {
+        self.next()?;
+
+        of_enum = Some(name);
+        name    = self.eat_type(&TokenType::Identifier)?;
+      }
+
+      let bindings = if self.current_lexeme() == "(" {
+        self.parse_block_of(("(", ")"), &Self::_parse_binding_comma)?
+      } else {
+        Vec::new()
+      };
+
+      Pattern::Variant(of_enum, name, bindings)
+    };
+
+    let position = self.span_from(position);
+
+    self.eat_lexeme(":")?;
+
+    let body = if self.current_lexeme() == "\n" {
+      self.next()?;
+      self.next_newline()?;
+
+      self.parse_body()?
+    } else {
+      vec!(self.parse_statement()?)
+    };
+
+    Ok(Arm::new(pattern, body, position))
+  }
+
+  // `x: int =`
+  fn parse_annotated_name(&mut self) -> Result<(String, TypeExpression), ()> {
+    let name = self.eat_type(&TokenType::Identifier)?;
+
+    self.eat_lexeme(":")?;
+
+    let ty = self.parse_annotation()?;
+
+    self.eat_lexeme("=")?;
+
+    Ok((name, ty))
+  }
+
+  // `øl ready: go = true` is an if statement, so a lone lowercase name is only a type when it's a primitive
+  fn parse_annotation(&mut self) -> Result<TypeExpression, ()> {
+    let ty = self.parse_type()?;
+
+    let mut inner = &ty;
+
+    while let TypeNode::Nullable(ref nullable) = inner.node {
+      inner = nullable
+    }
+
+    if let TypeNode::Name(ref name) = inner.node {
+      if !PRIMITIVES.contains(&name.as_str()) && !name.starts_with(char::is_uppercase) {
+        return Err(())
+      }
+    }
+
+    Ok(ty)
+  }
+
+  fn parse_type(&mut self) -> Result<TypeExpression, ()> {
+    let position = self.current_position();
+
+    let node = match self.current_lexeme().as_str() {
+      "[" => {
+        self.next()?;
+
+        let element = self.parse_type()?;
+
+        self.eat_lexeme("]")?;
+
+        TypeNode::Array(Rc::new(element))
+      },
+
+      "{" => TypeNode::Record(self.parse_block_of(("{", "}"), &Self::_parse_field_type_comma)?),
+
+      // `(int, str) -> bool`, or parentheses around a type
+      "(" => {
+        let mut params = self.parse_block_of(("(", ")"), &Self::_parse_type_comma)?;
+
+        if self.remaining() > 0 && self.current_lexeme() == "->" {
+          self.next()?;
+
+          TypeNode::Function(params, Rc::new(self.parse_type()?))
+        } else if params.len() == 1 {
+          params.pop().unwrap().node
+        } else {
+          return Err(
+            response!(
+              Wrong("expected `->` and the return type"),
+              self.source.file,
+              self.source.snippet(&self.current_position())
+            )
+          )
+        }
+      },
+
+      // identifiers may end with `?`, so `int?` is a single token
+      _ => {
+        let name = self.eat_type(&TokenType::Identifier)?;
+        let bare = name.trim_end_matches('?');
+
+        let mut ty = TypeExpression::new(
+          TypeNode::Name(bare.to_string()),
+          Pos(position.0, position.0 + bare.len())
+        );
+
+        for _ in bare.len() .. name.len() {
+          ty = TypeExpression::new(TypeNode::Nullable(Rc::new(ty)), position)
+        }
+
+        ty.node
+      },
+    };
+
+    let mut ty = TypeExpression::new(node, self.span_from(position));
+
+    while self.remaining() > 0 && self.current_lexeme() == "?" {
+      self.next()?;
+
+      ty = TypeExpression::new(
+        TypeNode::Nullable(Rc::new(ty)),
+        self.span_from(position)
+      )
+    }
+
+    Ok(ty)
+  }
+
+
+
+  fn parse_binary(&mut self, min_precedence: u8) -> Result<Expression, ()> {
+    self.nested(|parser| parser.parse_operations(min_precedence))
+  }
+
+  // Precedence climbing, operators binding looser than `min_precedence` are left for the caller
+  fn parse_operations(&mut self, min_precedence: u8) -> Result<Expression, ()> {
+    let mut left = self.parse_unary()?;
+
+    while self.remaining() > 0 && self.current_type() == TokenType::Operator {
+      let (operator, precedence) = match Operator::from_symbol(&self.current_lexeme()) {
+        Some(op_prec) => op_prec,
+        None          => break,
+      };
+
+      if precedence < min_precedence {
+        break
+      }
+
+      let position = self.current_position();
+
+      self.next()?;
+
+      if self.remaining() == 0 {
+        return Err(
+          response!(
+            Wrong("reached EOF in operation"),
+            self.source.file,
+            self.source.snippet(&position)
+          )
+        )
+      }
+
+      let right = match operator.associativity() {
+        Associativity::Left  => self.parse_binary(precedence + 1)?,
+        Associativity::Right => self.parse_binary(precedence)?,
+      };
+
+      let position = self.span_between(&left.pos, &right.pos);
+
+      left = Expression::new(
+        ExpressionNode::Binary(Rc::new(left), operator, Rc::new(right)),
+        position
+      )
+    }
+
+    Ok(left)
+  }
+
+
+
+  fn parse_unary(&mut self) -> Result<Expression, ()> {
+    // `vent fetch(url)` binds like a call, `vent a + b` adds to what `a` resolved to
+    if self.is_prefix("vent") {
+      let position = self.current_position();
+
+      if !self.context.is_async {
+        return Err(
+          response!(
+            Wrong("`vent` can only be used in `asynk` functions"),
+            self.source.file,
+            self.source.snippet(&position)
+          )
+        )
+      }
+
+      self.next()?;
+
+      let operand = self.parse_binary(5)?;
+
+      return Ok(Expression::new(ExpressionNode::Await(Rc::new(operand)), self.span_from(position)))
+    }
+
+    // `gi x + 1` hands out `x + 1`, and makes the function it's in a generator
+    if self.is_prefix("gi") {
+      let position = self.current_position();
+
+      if !self.context.function {
+        return Err(
+          response!(
+            Wrong("`gi` can only be used in functions"),
+            self.source.file,
+            self.source.snippet(&position)
+          )
+        )
+      }
+
+      self.next()?;
+
+      let operand = self.parse_expression()?;
+
+      self.context.yields = true;
+
+      return Ok(Expression::new(ExpressionNode::Yield(Rc::new(operand)), self.span_from(position)))
+    }
+
+    if self.remaining() == 0 || self.current_type() != TokenType::Operator {
+      return self.parse_atom()
+    }
+
+    let lexeme = self.current_lexeme();
+
+    let precedence = match Operator::prefix_precedence(&lexeme) {
+      Some(precedence) => precedence,
+      None             => return Err(
+        response!(
+          Wrong(format!("unexpected operator `{}`", lexeme)),
+          self.source.file,
+          self.source.snippet(&self.current_position())
+        )
+      )
+    };
+
+    let position = self.current_position();
+
+    self.next()?;
+
+    let operand  = self.parse_binary(precedence)?;
+    let position = self.span_between(&position, &operand.pos);
+
+    let node = if lexeme == "-" {
+      ExpressionNode::Neg(Rc::new(operand))
+    } else {
+      ExpressionNode::Not(Rc::new(operand))
+    };
+
+    Ok(Expression::new(node, position))
+  }
+
+
+
+  fn parse_atom(&mut self) -> Result<Expression, ()> {
+    use self::TokenType::*;
+
+    if self.remaining() == 0 {
+      Ok(
+        Expression::new(
+          ExpressionNode::EOF,
+          self.current_position()
+        )
+      )
+    } else {
+      let token_type = self.current_type().clone();
+      let position   = self.current_position();
+
+      let expression = match token_type {
+        Int => Expression::new(
+          ExpressionNode::Int(self.eat()?.parse::<u64>().unwrap()),
+          position
+        ),
+
+        Float => Expression::new(
+          ExpressionNode::Float(self.eat()?.parse::<f64>().unwrap()),
+          position
+        ),
+
+        Str => Expression::new(
+          ExpressionNode::Str(self.eat()?),
+          position
+        ),
+
+        Identifier if self.current_lexeme() == "nil" => {
+          self.next()?;
+
+          Expression::new(ExpressionNode::Nil, position)
+        },
+
+        Identifier => Expression::new(
+          ExpressionNode::Identifier(self.eat()?),
+          position
+        ),
+
+        Bool => Expression::new(
+          ExpressionNode::Bool(self.eat()? == "true"),
+          position
+        ),
+
+        Symbol => match self.current_lexeme().as_str() {
+          "{" => Expression::new(
+            ExpressionNode::Table(self.parse_block_of(("{", "}"), &Self::_parse_definition_comma)?),
+            self.span_from(position)
+          ),
+
+          "[" => {
+            let content = self.parse_block_of(("[", "]"), &Self::_parse_expression_comma)?;
+
+            if let Some(named) = content.iter().find(|element| Self::is_named_arg(element)) {
+              return Err(
+                response!(
+                  Wrong("only arguments can be named, not elements of arrays"),
+                  self.source.file,
+                  self.source.snippet(&named.pos)
+                )
+              )
+            }
+
+            Expression::new(
+              ExpressionNode::Array(content),
+              self.span_from(position)
+            )
+          },
+
+          "(" => {
+            self.next()?;
+            self.next_newline()?;
+
+            if self.current_lexeme() == ")" {
+              self.next()?;
+
+              Expression::new(
+                ExpressionNode::Empty,
+                self.span_from(position)
+              )
             } else {
               let expression = self.parse_expression()?;
 
@@ -508,7 +1302,7 @@ impl<'p> Parser<'p> {
             response!(
               Wrong(format!("unexpected symbol `{}`", symbol)),
               self.source.file,
-              self.current_position()
+              self.source.snippet(&self.current_position())
             )
           )
         },
@@ -517,7 +1311,7 @@ impl<'p> Parser<'p> {
           response!(
             Wrong(format!("unexpected token `{}`", token_type)),
             self.source.file,
-            self.current_position()
+            self.source.snippet(&self.current_position())
           )
         )
       };
@@ -533,6 +1327,11 @@ impl<'p> Parser<'p> {
 
 
   fn parse_postfix(&mut self, expression: Expression) -> Result<Expression, ()> {
+    self.nested(|parser| parser.parse_link(expression))
+  }
+
+  // The index, call or construction following `expression`, and the ones after that
+  fn parse_link(&mut self, expression: Expression) -> Result<Expression, ()> {
     if self.remaining() == 0 {
       return Ok(expression)
     }
@@ -542,7 +1341,9 @@ impl<'p> Parser<'p> {
         "(" => {
           let args = self.parse_block_of(("(", ")"), &Self::_parse_expression_comma)?;
 
-          let position = expression.pos.clone();
+          self.check_named(&args)?;
+
+          let position = expression.pos;
 
           let call = Expression::new(
             ExpressionNode::Call(Rc::new(expression), args),
@@ -555,11 +1356,17 @@ impl<'p> Parser<'p> {
         "[" => {
           self.next()?;
 
-          let expr = self.parse_expression()?;
+          let expr = if self.is_range() {
+            self.parse_range(None, true)?
+          } else {
+            let start = self.parse_binary(0)?;
+
+            if self.is_range() { self.parse_range(Some(start), true)? } else { start }
+          };
 
           self.eat_lexeme("]")?;
 
-          let position = expression.pos.clone();
+          let position = expression.pos;
 
           let index = Expression::new(
             ExpressionNode::Index(Rc::new(expression), Rc::new(expr), false),
@@ -570,98 +1377,100 @@ impl<'p> Parser<'p> {
         },
 
         c @ "." | c @ "\\" => {
-          let position = self.current_position();
           self.next()?;
 
-          let id = Expression::new(
-            ExpressionNode::Identifier(
-              self.eat()?
-            ),
-            position
-          );
-
-          let position = expression.pos.clone();
-
-          let index = Expression::new(
-            ExpressionNode::Index(
-              Rc::new(expression),
-              Rc::new(id),
-              c == "\\"
-            ),
-            self.span_from(position)
-          );
+          let index = self.parse_field(expression, c == "\\")?;
 
           self.parse_postfix(index)
         },
 
-        _ => Ok(expression)
-      },
+        // `a?.b`, `a?.[i]` and `a?.(x)` go on like without the `?` when `a` isn't nil
+        "?." => {
+          let is_method = matches!(expression.node, ExpressionNode::Index(_, _, true));
 
-      _ => Ok(expression)
-    }
-  }
+          self.next()?;
 
+          let position = expression.pos;
+          let optional = Expression::new(ExpressionNode::Optional(Rc::new(expression)), position);
 
+          if self.current_type() == TokenType::Symbol && ["(", "["].contains(&self.current_lexeme().as_str()) {
+            if is_method {
+              return Err(
+                response!(
+                  Wrong("a method is called on what it's indexed from, `?.` can't call it"),
+                  self.source.file,
+                  self.source.snippet(&position)
+                )
+              )
+            }
 
-  fn parse_binary(&mut self, left: Expression) -> Result<Expression, ()> {
-    let left_position = left.pos.clone();
+            self.parse_postfix(optional)
+          } else {
+            let index = self.parse_field(optional, false)?;
 
-    let mut expression_stack = vec!(left);
-    let mut operator_stack   = vec!(Operator::from_str(&self.eat()?).unwrap());
+            self.parse_postfix(index)
+          }
+        },
 
-    expression_stack.push(self.parse_atom()?);
+        // `Player { x: 1 }` builds a record by the names of its fields
+        "{" => match expression.node {
+          ExpressionNode::Identifier(_) => {
+            let fields = self.parse_block_of(("{", "}"), &Self::_parse_definition_comma)?;
+
+            if let Some((_, spread)) = fields.iter().find(|&(_, value)| Self::is_spread_of(value)) {
+              return Err(
+                response!(
+                  Wrong("records are built from their fields by name, they can't be spread into"),
+                  self.source.file,
+                  self.source.snippet(&spread.pos)
+                )
+              )
+            }
 
-    while operator_stack.len() > 0 {
-      while self.current_type() == TokenType::Operator {
-        let position               = self.current_position();
-        let (operator, precedence) = Operator::from_str(&self.eat()?).unwrap();
+            let position = expression.pos;
 
-        if precedence < operator_stack.last().unwrap().1 {
-          let right = expression_stack.pop().unwrap();
-          let left  = expression_stack.pop().unwrap();
+            let construct = Expression::new(
+              ExpressionNode::Construct(Rc::new(expression), fields),
+              self.span_from(position)
+            );
 
-          expression_stack.push(
-            Expression::new(
-              ExpressionNode::Binary(Rc::new(left), operator_stack.pop().unwrap().0, Rc::new(right)),
-              self.current_position(),
-            )
-          );
+            self.parse_postfix(construct)
+          },
 
-          if self.remaining() > 0 {
-            expression_stack.push(self.parse_atom()?);
-            operator_stack.push((operator, precedence))
-          } else {
-            return Err(
-              response!(
-                Wrong("reached EOF in operation"),
-                self.source.file,
-                position
-              )
-            )
-          }
-        } else {
-          expression_stack.push(self.parse_atom()?);
-          operator_stack.push((operator, precedence))
-        }
-      }
+          _ => Ok(expression)
+        },
 
-      let right = expression_stack.pop().unwrap();
-      let left  = expression_stack.pop().unwrap();
+        _ => Ok(expression)
+      },
 
-      expression_stack.push(
-        Expression::new(
-          ExpressionNode::Binary(Rc::new(left), operator_stack.pop().unwrap().0, Rc::new(right)),
-          self.current_position(),
-        )
-      );
+      _ => Ok(expression)
     }
+  }
+
 
-    let expression = expression_stack.pop().unwrap();
+
+  // The field of `expression` named by the identifier coming up
+  fn parse_field(&mut self, expression: Expression, is_method: bool) -> Result<Expression, ()> {
+    let position = self.current_position();
+
+    // a field name is indexed like the string it spells, `a.b` is `a["b"]`
+    let id = Expression::new(
+      ExpressionNode::Str(
+        self.eat_type(&TokenType::Identifier)?
+      ),
+      position
+    );
+
+    let position = expression.pos;
 
     Ok(
       Expression::new(
-        expression.node,
-        self.span_from(left_position)
+        ExpressionNode::Index(
+          Rc::new(expression),
+          Rc::new(id),
+          is_method
+        ),
+        self.span_from(position)
       )
     )
   }
@@ -673,12 +1482,11 @@ impl<'p> Parser<'p> {
       match self.current_lexeme().as_str() {
         "\n" => self.next(),
         _    => {
-          panic!();
           Err(
             response!(
               Wrong(format!("expected new line found: `{}`", self.current_lexeme())),
               self.source.file,
-              self.current_position()
+              self.source.snippet(&self.current_position())
             )
           )
         }
@@ -701,7 +1509,7 @@ impl<'p> Parser<'p> {
 
 
   fn get_indent(&self) -> usize {
-    self.current().slice.0 - 1
+    self.source.location(self.current().pos.0).1 - 1
   }
 
   fn is_dedent(&self) -> bool {
@@ -719,7 +1527,7 @@ impl<'p> Parser<'p> {
         response!(
           Wrong("moving outside token stack"),
           self.source.file,
-          self.current_position()
+          self.source.snippet(&self.current_position())
         )
       )
     }
@@ -730,19 +1538,21 @@ impl<'p> Parser<'p> {
   }
 
   fn current_position(&self) -> Pos {
-    let current = self.current();
-
-    Pos(
-      current.line.clone(),
-      current.slice
-    )
+    self.current().pos
   }
 
+  // Span from `left_position` up to the end of the last consumed token, trailing newlines excluded
   fn span_from(&self, left_position: Pos) -> Pos {
-    let Pos(ref line, ref slice) = left_position;
-    let Pos(_, ref slice2)       = self.current_position();
+    let consumed = &self.tokens[.. self.index.min(self.tokens.len())];
+
+    match consumed.iter().rev().find(|token| token.token_type != TokenType::EOL) {
+      Some(token) => left_position.to(&token.pos),
+      None        => left_position,
+    }
+  }
 
-    Pos(line.clone(), (slice.0, if slice2.1 < line.1.len() { slice2.1 } else { line.1.len() } ))
+  fn span_between(&self, left_position: &Pos, right_position: &Pos) -> Pos {
+    left_position.to(right_position)
   }
 
   fn current(&self) -> Token {
@@ -767,12 +1577,11 @@ impl<'p> Parser<'p> {
 
       Ok(lexeme)
     } else {
-      panic!();
       Err(
         response!(
           Wrong(format!("expected `{}`, found `{}`", lexeme, self.current_lexeme())),
           self.source.file,
-          self.current_position()
+          self.source.snippet(&self.current_position())
         )
       )
     }
@@ -789,7 +1598,7 @@ impl<'p> Parser<'p> {
         response!(
           Wrong(format!("expected `{}`, found `{}`", token_type, self.current_type())),
           self.source.file,
-          self.current_position()
+          self.source.snippet(&self.current_position())
         )
       )
     }
@@ -803,37 +1612,70 @@ impl<'p> Parser<'p> {
     self.current().token_type
   }
 
-  fn expect_type(&self, token_type: TokenType) -> Result<(), ()> {
-    if self.current_type() == token_type {
+  fn peek_type(&self, n: usize) -> Option<TokenType> {
+    self.tokens.get(self.index + n).map(|token| token.token_type.clone())
+  }
+
+  fn peek_lexeme(&self, n: usize) -> Option<String> {
+    self.tokens.get(self.index + n).map(|token| token.lexeme.clone())
+  }
+
+  fn expect_lexeme(&self, lexeme: &str) -> Result<(), ()> {
+    if self.current_lexeme() == lexeme {
       Ok(())
     } else {
       Err(
         response!(
-          Wrong(format!("expected `{}`, found `{}`", token_type, self.current_type())),
+          Wrong(format!("expected `{}`, found `{}`", lexeme, self.current_lexeme())),
           self.source.file
         )
       )
     }
   }
 
-  fn expect_lexeme(&self, lexeme: &str) -> Result<(), ()> {
-    if self.current_lexeme() == lexeme {
-      Ok(())
-    } else {
-      Err(
+
+
+  // Runs `parse` without reporting anything, rewinding when it fails
+  fn speculate<T>(&mut self, parse: fn(&mut Self) -> Result<T, ()>) -> Option<T> {
+    let backup_index = self.index;
+
+    let (result, _) = error::capture(|| parse(self));
+
+    if result.is_err() {
+      self.index = backup_index
+    }
+
+    result.ok()
+  }
+
+
+
+  // Parses a level deeper, giving up at `NESTING_MAX` levels instead of overflowing the stack
+  fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ()>) -> Result<T, ()> {
+    if self.depth >= NESTING_MAX {
+      return Err(
         response!(
-          Wrong(format!("expected `{}`, found `{}`", lexeme, self.current_lexeme())),
-          self.source.file
+          Wrong(format!("this nests more than {} levels deep", NESTING_MAX)),
+          self.source.file,
+          self.source.snippet(&self.current_position())
         )
       )
     }
+
+    self.depth += 1;
+
+    let result = parse(self);
+
+    self.depth -= 1;
+
+    result
   }
 
 
 
   // A helper method for parsing sequences defined by provided static methods,
   // for as long as given static method returns Some(B)
-  fn parse_block_of<B>(&mut self, delimeters: (&str, &str), parse_with: &Fn(&mut Self) -> Result<Option<B>, ()>) -> Result<Vec<B>, ()> {
+  fn parse_block_of<B>(&mut self, delimeters: (&str, &str), parse_with: &dyn Fn(&mut Self) -> Result<Option<B>, ()>) -> Result<Vec<B>, ()> {
     self.eat_lexeme(delimeters.0)?;
 
     let mut block_tokens = Vec::new();
@@ -859,6 +1701,8 @@ impl<'p> Parser<'p> {
 
     if !block_tokens.is_empty() {
       let mut parser = Parser::new(block_tokens, self.source);
+
+      parser.depth = self.depth;
       let mut block  = Vec::new();
 
       while let Some(element) = parse_with(&mut parser)? {
@@ -873,7 +1717,7 @@ impl<'p> Parser<'p> {
 
 
 
-  fn _parse_statement(self: &mut Self) -> Result<Option<Statement>, ()> {
+  fn _parse_statement(&mut self) -> Result<Option<Statement>, ()> {
     if self.remaining() > 0 {
       Ok(Some(self.parse_statement()?))
     } else {
@@ -883,7 +1727,7 @@ impl<'p> Parser<'p> {
 
 
 
-  fn _parse_expression(self: &mut Self) -> Result<Option<Expression>, ()> {
+  fn _parse_expression(&mut self) -> Result<Option<Expression>, ()> {
     let expression = self.parse_expression()?;
 
     match expression.node {
@@ -894,7 +1738,7 @@ impl<'p> Parser<'p> {
 
 
 
-  fn _parse_name_comma(self: &mut Self) -> Result<Option<String>, ()> {
+  fn _parse_name_comma(&mut self) -> Result<Option<String>, ()> {
     if self.remaining() == 0 {
       Ok(None)
     } else {
@@ -915,12 +1759,18 @@ impl<'p> Parser<'p> {
 
 
   // Static method for parsing sequence `expr* ,* \n*` - for things like [1, 2, 3, 4,]
-  fn _parse_expression_comma(self: &mut Self) -> Result<Option<Expression>, ()> {
+  fn _parse_expression_comma(&mut self) -> Result<Option<Expression>, ()> {
     if self.remaining() > 0 && self.current_lexeme() == "\n" {
       self.next()?
     }
 
-    let expression = Self::_parse_expression(self);
+    let expression = if self.is_spread() {
+      self.parse_spread().map(Some)
+    } else if self.is_named() {
+      self.parse_named().map(Some)
+    } else {
+      Self::_parse_expression(self)
+    };
 
     if self.remaining() > 0 && self.current_lexeme() == "\n" {
       self.next()?
@@ -940,7 +1790,7 @@ impl<'p> Parser<'p> {
 
 
   // Static method for parsing sequence `expr* ,* \n*` - for things like [1, 2, 3, 4,]
-  fn _parse_expression_no_comma(self: &mut Self) -> Result<Option<Expression>, ()> {
+  fn _parse_expression_no_comma(&mut self) -> Result<Option<Expression>, ()> {
     if self.remaining() > 0 && self.current_lexeme() == "\n" {
       self.next()?
     }
@@ -956,7 +1806,7 @@ impl<'p> Parser<'p> {
 
 
 
-  fn _parse_param(self: &mut Self) -> Result<Option<(String)>, ()> {
+  fn _parse_param(&mut self) -> Result<Option<String>, ()> {
     if self.remaining() > 0 && self.current_lexeme() == "\n" {
       self.next()?
     }
@@ -973,7 +1823,7 @@ impl<'p> Parser<'p> {
           response!(
             Wrong(format!("expected space or newline, found `{}`", self.current_lexeme())),
             self.source.file,
-            self.current_position()
+            self.source.snippet(&self.current_position())
           )
         )
       } else {
@@ -990,7 +1840,50 @@ impl<'p> Parser<'p> {
 
 
 
-  fn _parse_definition_comma(self: &mut Self) -> Result<Option<(String, Expression)>, ()> {
+  fn _parse_definition_comma(&mut self) -> Result<Option<(String, Expression)>, ()> {
+    if self.remaining() > 0 && self.current_lexeme() == "\n" {
+      self.next()?
+    }
+
+    if self.remaining() == 0 {
+      return Ok(None)
+    }
+
+    // `...base` adds the entries of `base`
+    let param = if self.is_spread() {
+      Some((String::new(), self.parse_spread()?))
+    } else {
+      let name = self.eat_type(&TokenType::Identifier)?;
+
+      self.eat_lexeme(":")?;
+
+      Some((name, self.parse_expression()?))
+    };
+
+    if self.remaining() > 0 {
+      if ![",", "\n"].contains(&self.current_lexeme().as_str()) {
+        return Err(
+          response!(
+            Wrong(format!("expected `,` or newline, found `{}`", self.current_lexeme())),
+            self.source.file,
+            self.source.snippet(&self.current_position())
+          )
+        )
+      } else {
+        self.next()?;
+      }
+
+      if self.remaining() > 0 && self.current_lexeme() == "\n" {
+        self.next()?
+      }
+    }
+
+    Ok(param)
+  }
+
+
+
+  fn _parse_param_comma(&mut self) -> Result<Option<Param>, ()> {
     if self.remaining() > 0 && self.current_lexeme() == "\n" {
       self.next()?
     }
@@ -999,17 +1892,165 @@ impl<'p> Parser<'p> {
       return Ok(None)
     }
 
+    let rest = self.is_spread();
+
+    if rest {
+      self.next()?
+    }
+
     let position = self.current_position();
+    let name     = self.eat_type(&TokenType::Identifier)?;
+
+    let ty = if self.remaining() > 0 && self.current_lexeme() == ":" {
+      self.next()?;
+
+      Some(self.parse_type()?)
+    } else {
+      None
+    };
+
+    let default = if self.remaining() > 0 && self.current_lexeme() == "=" {
+      self.next()?;
+
+      Some(self.parse_expression()?)
+    } else {
+      None
+    };
+
+    if self.remaining() > 0 {
+      self.eat_lexeme(",")?;
+
+      if self.remaining() > 0 && self.current_lexeme() == "\n" {
+        self.next()?
+      }
+    }
+
+    Ok(
+      Some(
+        Param {
+          default,
+          rest,
+          ..Param::new(name, ty, position)
+        }
+      )
+    )
+  }
+
+
+
+  fn _parse_type_comma(&mut self) -> Result<Option<TypeExpression>, ()> {
+    if self.remaining() > 0 && self.current_lexeme() == "\n" {
+      self.next()?
+    }
+
+    if self.remaining() == 0 {
+      return Ok(None)
+    }
+
+    let ty = self.parse_type()?;
+
+    if self.remaining() > 0 {
+      self.eat_lexeme(",")?;
+
+      if self.remaining() > 0 && self.current_lexeme() == "\n" {
+        self.next()?
+      }
+    }
+
+    Ok(Some(ty))
+  }
+
+
+
+  // `name: type` in record types, separated like the entries of tables
+  fn _parse_field_type_comma(&mut self) -> Result<Option<(String, TypeExpression)>, ()> {
+    if self.remaining() > 0 && self.current_lexeme() == "\n" {
+      self.next()?
+    }
+
+    if self.remaining() == 0 {
+      return Ok(None)
+    }
 
     let name = self.eat_type(&TokenType::Identifier)?;
 
     self.eat_lexeme(":")?;
 
-    let mut value = self.parse_expression()?;
+    let field = Some((name, self.parse_type()?));
+
+    if self.remaining() > 0 {
+      if ![",", "\n"].contains(&self.current_lexeme().as_str()) {
+        return Err(
+          response!(
+            Wrong(format!("expected `,` or newline, found `{}`", self.current_lexeme())),
+            self.source.file,
+            self.source.snippet(&self.current_position())
+          )
+        )
+      } else {
+        self.next()?;
+      }
+
+      if self.remaining() > 0 && self.current_lexeme() == "\n" {
+        self.next()?
+      }
+    }
+
+    Ok(field)
+  }
+
+
+
+  // `Circle(radius: float)` or `Empty` in enums
+  fn _parse_variant_comma(&mut self) -> Result<Option<Variant>, ()> {
+    if self.remaining() > 0 && self.current_lexeme() == "\n" {
+      self.next()?
+    }
+
+    if self.remaining() == 0 {
+      return Ok(None)
+    }
+
+    let position = self.current_position();
+    let name     = self.eat_type(&TokenType::Identifier)?;
+
+    let fields = if self.remaining() > 0 && self.current_lexeme() == "(" {
+      self.parse_block_of(("(", ")"), &Self::_parse_param_comma)?
+    } else {
+      Vec::new()
+    };
+
+    if let Some(field) = fields.iter().find(|field| field.rest) {
+      return Err(
+        response!(
+          Wrong("fields of variants can't gather the arguments left"),
+          self.source.file,
+          self.source.snippet(&field.pos)
+        )
+      )
+    }
+
+    if let Some(field) = fields.iter().find(|field| field.default.is_some()) {
+      return Err(
+        response!(
+          Wrong("fields of variants can't have defaults"),
+          self.source.file,
+          self.source.snippet(&field.pos)
+        )
+      )
+    }
 
-    value.pos = position;
+    if let Some(field) = fields.iter().find(|field| field.name == TAG) {
+      return Err(
+        response!(
+          Wrong(format!("`{}` is where variants keep their name, it can't be a field", TAG)),
+          self.source.file,
+          self.source.snippet(&field.pos)
+        )
+      )
+    }
 
-    let param = Some((name, value));
+    let variant = Variant::new(name, fields, self.span_from(position));
 
     if self.remaining() > 0 {
       if ![",", "\n"].contains(&self.current_lexeme().as_str()) {
@@ -1017,7 +2058,7 @@ impl<'p> Parser<'p> {
           response!(
             Wrong(format!("expected `,` or newline, found `{}`", self.current_lexeme())),
             self.source.file,
-            self.current_position()
+            self.source.snippet(&self.current_position())
           )
         )
       } else {
@@ -1029,7 +2070,82 @@ impl<'p> Parser<'p> {
       }
     }
 
-    Ok(param)
+    Ok(Some(variant))
+  }
+
+
+
+  // The names a pattern binds the fields of a variant to
+  fn _parse_binding_comma(&mut self) -> Result<Option<(String, Pos)>, ()> {
+    if self.remaining() == 0 {
+      return Ok(None)
+    }
+
+    let position = self.current_position();
+    let name     = self.eat_type(&TokenType::Identifier)?;
+
+    if self.remaining() > 0 {
+      self.eat_lexeme(",")?;
+    }
+
+    Ok(Some((name, position)))
+  }
+
+
+
+  // `name: type = default` in records, the type and the default may each be left out
+  fn _parse_field_comma(&mut self) -> Result<Option<Param>, ()> {
+    if self.remaining() > 0 && self.current_lexeme() == "\n" {
+      self.next()?
+    }
+
+    if self.remaining() == 0 {
+      return Ok(None)
+    }
+
+    let position = self.current_position();
+    let name     = self.eat_type(&TokenType::Identifier)?;
+
+    let ty = if self.remaining() > 0 && self.current_lexeme() == ":" {
+      self.next()?;
+
+      Some(self.parse_type()?)
+    } else {
+      None
+    };
+
+    let default = if self.remaining() > 0 && self.current_lexeme() == "=" {
+      self.next()?;
+
+      Some(self.parse_expression()?)
+    } else {
+      None
+    };
+
+    let field = Param {
+      default,
+      ..Param::new(name, ty, position)
+    };
+
+    if self.remaining() > 0 {
+      if ![",", "\n"].contains(&self.current_lexeme().as_str()) {
+        return Err(
+          response!(
+            Wrong(format!("expected `,` or newline, found `{}`", self.current_lexeme())),
+            self.source.file,
+            self.source.snippet(&self.current_position())
+          )
+        )
+      } else {
+        self.next()?;
+      }
+
+      if self.remaining() > 0 && self.current_lexeme() == "\n" {
+        self.next()?
+      }
+    }
+
+    Ok(Some(field))
   }
 
 
@@ -1040,22 +2156,22 @@ impl<'p> Parser<'p> {
 
     let node = match expression.node {
       Binary(ref left, ref op, ref right) => {
-        let node = match (&Self::fold_expression(&*left)?.node, op, &Self::fold_expression(&*right)?.node) {
-          (&Int(ref a),   &Add, &Int(ref b))   => Int(a + b),
-          (&Float(ref a), &Add, &Float(ref b)) => Float(a + b),
-          (&Int(ref a),   &Sub, &Int(ref b))   => Int(a - b),
-          (&Float(ref a), &Sub, &Float(ref b)) => Float(a - b),
-          (&Int(ref a),   &Mul, &Int(ref b))   => Int(a * b),
-          (&Float(ref a), &Mul, &Float(ref b)) => Float(a * b),
-          (&Int(ref a),   &Div, &Int(ref b))   => Int(a / b),
-          (&Float(ref a), &Div, &Float(ref b)) => Float(a / b),
+        let node = match (&Self::fold_expression(left)?.node, op, &Self::fold_expression(right)?.node) {
+          (Int(a),   Add, Int(b))   => Int(a + b),
+          (Float(a), Add, Float(b)) => Float(a + b),
+          (Int(a),   Sub, Int(b))   => Int(a - b),
+          (Float(a), Sub, Float(b)) => Float(a - b),
+          (Int(a),   Mul, Int(b))   => Int(a * b),
+          (Float(a), Mul, Float(b)) => Float(a * b),
+          (Int(a),   Div, Int(b))   => Int(a / b),
+          (Float(a), Div, Float(b)) => Float(a / b),
 
           _ => expression.node.clone()
         };
 
         Expression::new(
           node,
-          expression.pos.clone()
+          expression.pos
         )
       },
 
@@ -1065,3 +2181,123 @@ impl<'p> Parser<'p> {
     Ok(node)
   }
 }
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // `code` parsed as a single expression, with every operation in parentheses
+  fn grouped(code: &str) -> String {
+    let ast = crate::parse_str("<test>", code).result.expect("it should parse");
+
+    match ast[0].node {
+      StatementNode::Expression(ref expression) => show(expression),
+      ref node                                  => panic!("expected an expression, found {:?}", node),
+    }
+  }
+
+  fn show(expression: &Expression) -> String {
+    match expression.node {
+      ExpressionNode::Binary(ref left, ref op, ref right) => format!("({} {} {})", show(left), op, show(right)),
+      ExpressionNode::Neg(ref operand)                    => format!("(-{})", show(operand)),
+      ExpressionNode::Not(ref operand)                    => format!("(not {})", show(operand)),
+      ExpressionNode::Call(ref called, ref args)          => format!("{}({})", show(called), args.iter().map(show).collect::<Vec<String>>().join(", ")),
+      ExpressionNode::Identifier(ref name)                => name.clone(),
+      ExpressionNode::Int(n)                              => n.to_string(),
+      ref node                                            => format!("{:?}", node),
+    }
+  }
+
+  #[test]
+  fn binds_by_precedence() {
+    assert_eq!(grouped("a + b * c"), "(a + (b * c))");
+    assert_eq!(grouped("a * b + c"), "((a * b) + c)");
+    assert_eq!(grouped("a + b == c * d"), "((a + b) == (c * d))");
+    assert_eq!(grouped("a == b or c"), "((a == b) or c)");
+    assert_eq!(grouped("a * b ^ c"), "(a * (b ^ c))");
+  }
+
+  #[test]
+  fn associates() {
+    assert_eq!(grouped("a - b - c"), "((a - b) - c)");
+    assert_eq!(grouped("a ^ b ^ c"), "(a ^ (b ^ c))");
+    assert_eq!(grouped("x |> f |> g"), "((x |> f) |> g)");
+    assert_eq!(grouped("g <| f <| x"), "(g <| (f <| x))");
+  }
+
+  #[test]
+  fn binds_prefix_operators() {
+    assert_eq!(grouped("-a ^ b"), "(-(a ^ b))");
+    assert_eq!(grouped("-a * b"), "((-a) * b)");
+    assert_eq!(grouped("not a == b"), "(not (a == b))");
+  }
+
+  #[test]
+  fn groups_by_parentheses() {
+    assert_eq!(grouped("(a + b) * c"), "((a + b) * c)");
+    assert_eq!(grouped("f(a + 1, b) * 2"), "(f((a + 1), b) * 2)");
+  }
+
+  #[test]
+  fn reports_a_missing_operand() {
+    assert!(!crate::parse_str("<test>", "a + * b").is_ok());
+  }
+
+  #[test]
+  fn needs_a_catch_or_finally_after_try() {
+    let output = crate::parse_str("<test>", "prøv:\n  kast 1\nprint(2)\n");
+
+    assert_eq!(output.diagnostics.first().map(|diagnostic| diagnostic.message.as_str()), Some("`prøv` needs a `fang` or an `endelig` after it"));
+    assert!(crate::parse_str("<test>", "prøv:\n  kast 1\nendelig:\n  print(2)\n").is_ok())
+  }
+
+  #[test]
+  fn awaits_only_in_async_functions() {
+    let message = |code: &str| crate::parse_str("<test>", code).diagnostics.first().map(|diagnostic| diagnostic.message.clone());
+
+    assert_eq!(message("asynk øl f(url) =\n  øl vent fetch(url)\n"), None);
+    assert_eq!(message("øl f(url) =\n  øl vent fetch(url)\n"), Some("`vent` can only be used in `asynk` functions".to_string()));
+    assert_eq!(message("asynk øl f(url) =\n  øl g() =\n    øl vent fetch(url)\n  øl g\n"), Some("`vent` can only be used in `asynk` functions".to_string()))
+  }
+
+  #[test]
+  fn yields_only_in_functions() {
+    assert_eq!(crate::parse_str("<test>", "gi 1\n").diagnostics.first().map(|diagnostic| diagnostic.message.as_str()), Some("`gi` can only be used in functions"));
+    assert!(crate::parse_str("<test>", "øl f() =\n  gi 1\n").is_ok())
+  }
+
+  #[test]
+  fn gathers_only_in_the_last_param() {
+    let message = |code: &str| crate::parse_str("<test>", code).diagnostics.first().map(|diagnostic| diagnostic.message.clone());
+
+    assert_eq!(message("øl log(level, ...msgs) =\n  øl msgs\n"), None);
+    assert_eq!(message("øl log(...msgs, level) =\n  øl msgs\n"), Some("`...msgs` gathers the arguments left, it has to be the last parameter".to_string()));
+    assert_eq!(message("øl log(...msgs = []) =\n  øl msgs\n"), Some("`...msgs` can't have a default, it's empty when no arguments are left".to_string()))
+  }
+
+  #[test]
+  fn binds_coalescing_like_or() {
+    assert_eq!(grouped("a ?? b or c"), "((a ?? b) or c)");
+    assert_eq!(grouped("a ?? b ?? c + 1"), "((a ?? b) ?? (c + 1))")
+  }
+
+  #[test]
+  fn refuses_nesting_past_the_limit() {
+    let message = |code: String| crate::parse_str("<test>", &code).diagnostics.first().map(|diagnostic| diagnostic.message.clone());
+
+    let too_deep = Some(format!("this nests more than {} levels deep", NESTING_MAX));
+
+    assert_eq!(message("(".repeat(20000)), too_deep);
+    assert_eq!(message(format!("øl x = {}{}\n", "[".repeat(20000), "]".repeat(20000))), too_deep);
+    assert_eq!(message(format!("øl x = {}1\n", "-".repeat(20000))), too_deep);
+    assert_eq!(message(format!("øl x = a{}\n", ".b".repeat(20000))), too_deep);
+
+    // what's within the limit still goes through every backend
+    let code = format!("øl x = {}1{}\nx\n", "[".repeat(NESTING_MAX - 1), "]".repeat(NESTING_MAX - 1));
+
+    assert!(crate::compile_str("<test>", &code).result.is_some());
+    assert!(crate::run_str("<test>", &code, &mut crate::øl::vm::Globals::new(), Default::default()).result.is_some())
+  }
+}
@@ -0,0 +1,201 @@
+use super::*;
+use super::super::error::Response::Wrong;
+
+// Checks the fields used against the records `ast` declares, in `Player { .. }` and on values known to be built that way
+pub fn check_records(ast: &[Statement], source: &Source) -> Result<(), ()> {
+  let mut found = Found {
+    records:  false,
+    assigned: Vec::new(),
+  };
+
+  found.body(ast)?;
+
+  // records from elsewhere, like an earlier REPL input, can't be checked here
+  if !found.records {
+    return Ok(())
+  }
+
+  let mut records = Records {
+    assigned: found.assigned,
+    scopes:   Vec::new(),
+    source,
+  };
+
+  records.function(&[], ast)
+}
+
+// Whether `ast` has records, and the names assigned to after they're defined
+struct Found<'a> {
+  records:  bool,
+  assigned: Vec<&'a str>,
+}
+
+impl<'a> Visit<'a> for Found<'a> {
+  fn statement(&mut self, statement: &'a Statement) -> Result<(), ()> {
+    match statement.node {
+      StatementNode::Record(..) => self.records = true,
+
+      StatementNode::Assignment(Expression { node: ExpressionNode::Identifier(ref name), .. }, _) |
+      StatementNode::Compound(Expression { node: ExpressionNode::Identifier(ref name), .. }, ..) => self.assigned.push(name),
+
+      _ => (),
+    }
+
+    walk_statement(self, statement)
+  }
+}
+
+
+
+// What a name in a function is, as far as fields go
+#[derive(Clone, Copy)]
+enum Name<'a> {
+  Record(&'a [Param]),
+  Built(&'a str), // a variable holding a record built by the name of it
+  Other,
+}
+
+struct Records<'a> {
+  assigned: Vec<&'a str>,                  // may hold something else later on, so what they hold isn't known
+  scopes:   Vec<Vec<(&'a str, Name<'a>)>>, // the names each function being walked defines
+  source:   &'a Source,
+}
+
+impl<'a> Records<'a> {
+  fn function(&mut self, params: &'a [Param], body: &'a [Statement]) -> Result<(), ()> {
+    let mut names = params.iter().map(|param| (param.name.as_str(), Name::Other)).collect::<Vec<(&str, Name)>>();
+
+    visit::definitions(body, &mut |defined| {
+      let name = match defined {
+        Defined::Statement(_, &Statement { node: StatementNode::Record(_, ref fields), .. })         => Name::Record(fields),
+        Defined::Statement(_, &Statement { node: StatementNode::Variable(_, _, ref value, _), .. }) => Self::built_from(value),
+        _                                                                                          => Name::Other,
+      };
+
+      names.push((defined.name(), name))
+    });
+
+    self.scopes.push(names);
+
+    let result = self.body(body);
+
+    self.scopes.pop();
+
+    result
+  }
+
+  // `Player { .. }` and `Player(..)` build a `Player` when that's what the name is
+  fn built_from(value: &'a Expression) -> Name<'a> {
+    match value.node {
+      ExpressionNode::Construct(ref record, _) | ExpressionNode::Call(ref record, _) => match record.node {
+        ExpressionNode::Identifier(ref name) => Name::Built(name),
+        _                                    => Name::Other,
+      },
+
+      _ => Name::Other,
+    }
+  }
+
+  // What `name` is in the innermost function defining it, a name defined more than once there may be either
+  fn lookup(&self, name: &str) -> Name<'a> {
+    for scope in self.scopes.iter().rev() {
+      let mut names = scope.iter().filter(|&&(other, _)| other == name);
+
+      if let Some(&(_, found)) = names.next() {
+        return if names.next().is_some() { Name::Other } else { found }
+      }
+    }
+
+    Name::Other
+  }
+
+  fn field(&self, record: &str, fields: &[Param], name: &str, pos: Pos) -> Result<(), ()> {
+    if fields.iter().any(|field| field.name == name) {
+      Ok(())
+    } else {
+      Err(self.wrong(format!("`{}` has no field `{}`", record, name), pos))
+    }
+  }
+
+  fn wrong(&self, message: String, pos: Pos) {
+    response!(Wrong(message), self.source.file, self.source.snippet(&pos))
+  }
+}
+
+impl<'a> Visit<'a> for Records<'a> {
+  fn statement(&mut self, statement: &'a Statement) -> Result<(), ()> {
+    match statement.node {
+      StatementNode::Function(ref name, ref params, _, ref body, ..) => {
+        self.expression(name)?;
+        walk_defaults(self, params)?;
+
+        self.function(params, body)
+      },
+
+      _ => walk_statement(self, statement),
+    }
+  }
+
+  fn expression(&mut self, expression: &'a Expression) -> Result<(), ()> {
+    match expression.node {
+      ExpressionNode::Construct(ref record, ref given) => if let ExpressionNode::Identifier(ref name) = record.node {
+        if let Name::Record(fields) = self.lookup(name) {
+          for (key, value) in given.iter() {
+            self.field(name, fields, key, value.pos)?
+          }
+        }
+      },
+
+      // `p.field`, methods are looked up elsewhere
+      ExpressionNode::Index(ref indexed, ref index, false) => {
+        if let (ExpressionNode::Identifier(ref name), ExpressionNode::Str(ref key)) = (&indexed.node, &index.node) {
+          let record = match self.lookup(name) {
+            Name::Built(record) if !self.assigned.contains(&name.as_str()) => record,
+            _                                                             => return walk_expression(self, expression),
+          };
+
+          if let Name::Record(fields) = self.lookup(record) {
+            self.field(record, fields, key, index.pos)?
+          }
+        }
+      },
+
+      _ => (),
+    }
+
+    walk_expression(self, expression)
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  const PLAYER: &str = "record Player = { name: str, score: int = 0 }\n";
+
+  fn message(code: &str) -> Option<String> {
+    let output = crate::parse_str("<test>", &format!("{}{}", PLAYER, code));
+
+    output.diagnostics.first().map(|diagnostic| diagnostic.message.clone())
+  }
+
+  #[test]
+  fn accepts_known_fields() {
+    assert_eq!(message("øl p = Player { name: \"a\" }\np.score += 1\nprint(p.name)\n"), None)
+  }
+
+  #[test]
+  fn reports_unknown_fields() {
+    assert_eq!(message("øl p = Player { nme: \"a\" }\n"), Some("`Player` has no field `nme`".to_string()));
+    assert_eq!(message("øl p = Player { name: \"a\" }\nprint(p.scroe)\n"), Some("`Player` has no field `scroe`".to_string()));
+    assert_eq!(message("øl p = Player(\"a\")\nøl f() =\n  p.nme = \"b\"\n"), Some("`Player` has no field `nme`".to_string()))
+  }
+
+  #[test]
+  fn leaves_values_it_cant_be_sure_of() {
+    // reassigned or shadowed, `p` may hold anything
+    assert_eq!(message("øl p = Player { name: \"a\" }\np = { other: 1 }\nprint(p.other)\n"), None);
+    assert_eq!(message("øl p = Player { name: \"a\" }\nøl f(p) =\n  øl p.other\n"), None);
+    assert_eq!(message("øl f() =\n  øl Player = nil\n  øl p = Player { other: 1 }\n"), None)
+  }
+}
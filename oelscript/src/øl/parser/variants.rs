@@ -0,0 +1,182 @@
+use super::*;
+use super::super::error::Response::Wrong;
+
+// Checks the variants used against the enums `ast` declares, so misspelled ones fail before running
+pub fn check_variants(ast: &[Statement], source: &Source) -> Result<(), ()> {
+  let mut enums = Enums(Vec::new());
+
+  enums.body(ast)?;
+
+  let enums = enums.0;
+
+  // enums from elsewhere, like an earlier REPL input, can't be checked here
+  if enums.is_empty() {
+    return Ok(())
+  }
+
+  Variants { enums, source }.body(ast)
+}
+
+// Every enum in `body`, in functions too
+struct Enums<'v>(Vec<(&'v str, &'v [Variant])>);
+
+impl<'v> Visit<'v> for Enums<'v> {
+  fn statement(&mut self, statement: &'v Statement) -> Result<(), ()> {
+    if let StatementNode::Enum(ref name, ref variants) = statement.node {
+      self.0.push((name, variants))
+    }
+
+    walk_statement(self, statement)
+  }
+}
+
+
+
+struct Variants<'v> {
+  enums:  Vec<(&'v str, &'v [Variant])>,
+  source: &'v Source,
+}
+
+impl<'v> Variants<'v> {
+  // The arms of a match are all of the enum the first one naming one is of
+  fn arms(&self, arms: &[Arm]) -> Result<(), ()> {
+    let of_enum = arms.iter().filter_map(|arm| match arm.pattern {
+      Pattern::Variant(Some(ref name), ..)   => Some(name.as_str()),
+      Pattern::Variant(None, ref variant, _) => self.enum_with(variant),
+      Pattern::Wildcard                      => None,
+    }).next();
+
+    // no arm telling which enum is matched leaves names that aren't a variant of any
+    let of_enum = match of_enum {
+      Some(name) => name,
+      None       => return match arms.iter().find(|arm| arm.pattern != Pattern::Wildcard) {
+        Some(&Arm { pattern: Pattern::Variant(_, ref name, _), pos, .. }) => Err(self.wrong(format!("no enum has a variant `{}`", name), pos)),
+        _                                                                 => Ok(()),
+      },
+    };
+
+    for arm in arms.iter() {
+      if let Pattern::Variant(ref named, ref name, ref bindings) = arm.pattern {
+        if let Some(ref named) = *named {
+          if named != of_enum {
+            return Err(self.wrong(format!("expected a variant of `{}`, found one of `{}`", of_enum, named), arm.pos))
+          }
+        }
+
+        let variant = match self.variant(of_enum, name, arm.pos)? {
+          Some(variant) => variant,
+          None          => continue,
+        };
+
+        if bindings.len() != variant.fields.len() {
+          return Err(self.wrong(format!("`{}` has {} fields, found {} names for them", name, variant.fields.len(), bindings.len()), arm.pos))
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // `of_enum.name`, when `of_enum` is a plain name
+  fn variant_index(expression: &Expression) -> Option<(&str, &str)> {
+    if let ExpressionNode::Index(ref indexed, ref index, false) = expression.node {
+      if let (ExpressionNode::Identifier(of_enum), ExpressionNode::Str(name)) = (&indexed.node, &index.node) {
+        return Some((of_enum, name))
+      }
+    }
+
+    None
+  }
+
+  // The variant `name` of `of_enum`, or None when `of_enum` isn't a known enum
+  fn variant(&self, of_enum: &str, name: &str, pos: Pos) -> Result<Option<&'v Variant>, ()> {
+    let variants = match self.enums.iter().find(|&&(other, _)| other == of_enum) {
+      Some(&(_, variants)) => variants,
+      None                 => return Ok(None),
+    };
+
+    match variants.iter().find(|variant| variant.name == name) {
+      Some(variant) => Ok(Some(variant)),
+      None          => Err(self.wrong(format!("`{}` has no variant `{}`", of_enum, name), pos)),
+    }
+  }
+
+  fn enum_with(&self, variant: &str) -> Option<&'v str> {
+    self.enums.iter().find(|&&(_, variants)| variants.iter().any(|other| other.name == variant)).map(|&(name, _)| name)
+  }
+
+  fn wrong(&self, message: String, pos: Pos) {
+    response!(Wrong(message), self.source.file, self.source.snippet(&pos))
+  }
+}
+
+impl<'a, 'v> Visit<'a> for Variants<'v> {
+  fn statement(&mut self, statement: &'a Statement) -> Result<(), ()> {
+    if let StatementNode::Match(_, ref arms) = statement.node {
+      self.arms(arms)?
+    }
+
+    walk_statement(self, statement)
+  }
+
+  fn expression(&mut self, expression: &'a Expression) -> Result<(), ()> {
+    match expression.node {
+      // `Shape.Circle(r)` has to pass every field
+      ExpressionNode::Call(ref called, ref args) => if let Some((of_enum, name)) = Self::variant_index(called) {
+        if let Some(variant) = self.variant(of_enum, name, called.pos)? {
+          if variant.fields.is_empty() {
+            return Err(self.wrong(format!("`{}.{}` holds no fields, it's used without calling it", of_enum, name), expression.pos))
+          }
+
+          if args.len() != variant.fields.len() {
+            return Err(self.wrong(format!("expected {} arguments, found {}", variant.fields.len(), args.len()), expression.pos))
+          }
+        }
+      },
+
+      ExpressionNode::Index(..) => if let Some((of_enum, name)) = Self::variant_index(expression) {
+        self.variant(of_enum, name, expression.pos)?;
+      },
+
+      _ => (),
+    }
+
+    walk_expression(self, expression)
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  const SHAPE: &str = "enum Shape = { Circle(r: int), Empty }\n";
+
+  fn message(code: &str) -> Option<String> {
+    let output = crate::parse_str("<test>", &format!("{}{}", SHAPE, code));
+
+    output.diagnostics.first().map(|diagnostic| diagnostic.message.clone())
+  }
+
+  #[test]
+  fn accepts_known_variants() {
+    assert_eq!(message("øl s = Shape.Circle(1)\nmatch s:\n  Circle(r): print(r)\n  Shape.Empty: print(0)\n"), None)
+  }
+
+  #[test]
+  fn reports_misspelled_variants() {
+    assert_eq!(message("øl s = Shape.Circel(1)\n"), Some("`Shape` has no variant `Circel`".to_string()));
+    assert_eq!(message("match Shape.Empty:\n  Circle(r): print(r)\n  Emtpy: print(0)\n"), Some("`Shape` has no variant `Emtpy`".to_string()))
+  }
+
+  #[test]
+  fn reports_names_of_no_variant() {
+    assert_eq!(message("match Shape.Empty:\n  Emtpy: print(0)\n"), Some("no enum has a variant `Emtpy`".to_string()));
+    assert_eq!(message("match Shape.Empty:\n  Emtpy: print(0)\n  _: print(1)\n"), Some("no enum has a variant `Emtpy`".to_string()))
+  }
+
+  #[test]
+  fn reports_wrong_fields() {
+    assert_eq!(message("øl s = Shape.Circle()\n"), Some("expected 1 arguments, found 0".to_string()));
+    assert_eq!(message("match Shape.Empty:\n  Circle: print(0)\n"), Some("`Circle` has 1 fields, found 0 names for them".to_string()))
+  }
+}
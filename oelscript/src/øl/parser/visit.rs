@@ -0,0 +1,446 @@
+use super::*;
+
+use std::rc::Rc;
+
+// Walks every node of a syntax tree, a pass overrides the nodes it looks at and calls the `walk_*` function of one to go on into it
+pub trait Visit<'a> {
+  fn body(&mut self, body: &'a [Statement]) -> Result<(), ()> {
+    walk_body(self, body)
+  }
+
+  fn statement(&mut self, statement: &'a Statement) -> Result<(), ()> {
+    walk_statement(self, statement)
+  }
+
+  fn expression(&mut self, expression: &'a Expression) -> Result<(), ()> {
+    walk_expression(self, expression)
+  }
+}
+
+pub fn walk_body<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, body: &'a [Statement]) -> Result<(), ()> {
+  for statement in body.iter() {
+    visitor.statement(statement)?
+  }
+
+  Ok(())
+}
+
+pub fn walk_statement<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, statement: &'a Statement) -> Result<(), ()> {
+  use self::StatementNode::*;
+
+  match statement.node {
+    Expression(ref expression) | Variable(_, _, ref expression, _) | Return(Some(ref expression)) | Throw(ref expression) => visitor.expression(expression),
+
+    Assignment(ref left, ref right) | Compound(ref left, _, ref right) => {
+      visitor.expression(left)?;
+      visitor.expression(right)
+    },
+
+    Function(ref name, ref params, _, ref body, ..) => {
+      visitor.expression(name)?;
+      walk_defaults(visitor, params)?;
+      visitor.body(body)
+    },
+
+    Record(_, ref fields) => walk_defaults(visitor, fields),
+
+    Enum(_, ref variants) => {
+      for variant in variants.iter() {
+        walk_defaults(visitor, &variant.fields)?
+      }
+
+      Ok(())
+    },
+
+    If(ref condition, ref body, ref else_branch) => {
+      visitor.expression(condition)?;
+      visitor.body(body)?;
+
+      match *else_branch {
+        Some((ref body, _)) => visitor.body(body),
+        None                => Ok(()),
+      }
+    },
+
+    Match(ref subject, ref arms) => {
+      visitor.expression(subject)?;
+
+      for arm in arms.iter() {
+        visitor.body(&arm.body)?
+      }
+
+      Ok(())
+    },
+
+    Try(ref body, ref catch, ref finally) => {
+      visitor.body(body)?;
+
+      if let Some(ref catch) = *catch {
+        visitor.body(&catch.body)?
+      }
+
+      match *finally {
+        Some((ref body, _)) => visitor.body(body),
+        None                => Ok(()),
+      }
+    },
+
+    For(_, ref iterable, ref body) => {
+      visitor.expression(iterable)?;
+      visitor.body(body)
+    },
+
+    Return(None) | TypeAlias(..) | Import(_) | Skip | Break => Ok(()),
+  }
+}
+
+// The defaults of params or fields
+pub fn walk_defaults<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, params: &'a [Param]) -> Result<(), ()> {
+  for default in params.iter().filter_map(|param| param.default.as_ref()) {
+    visitor.expression(default)?
+  }
+
+  Ok(())
+}
+
+pub fn walk_expression<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, expression: &'a Expression) -> Result<(), ()> {
+  use self::ExpressionNode::*;
+
+  match expression.node {
+    Call(ref called, ref args) => {
+      visitor.expression(called)?;
+
+      for arg in args.iter() {
+        visitor.expression(arg)?
+      }
+
+      Ok(())
+    },
+
+    Index(ref left, ref right, _) | Binary(ref left, _, ref right) => {
+      visitor.expression(left)?;
+      visitor.expression(right)
+    },
+
+    Neg(ref operand) | Not(ref operand) | Await(ref operand) | Yield(ref operand) | Spread(ref operand) | Named(_, ref operand) | Optional(ref operand) => visitor.expression(operand),
+
+    Array(ref content) => {
+      for element in content.iter() {
+        visitor.expression(element)?
+      }
+
+      Ok(())
+    },
+
+    Table(ref content) => {
+      for (_, value) in content.iter() {
+        visitor.expression(value)?
+      }
+
+      Ok(())
+    },
+
+    Construct(ref record, ref content) => {
+      visitor.expression(record)?;
+
+      for (_, value) in content.iter() {
+        visitor.expression(value)?
+      }
+
+      Ok(())
+    },
+
+    Range(ref start, ref end, _) => {
+      for bound in start.iter().chain(end.iter()) {
+        visitor.expression(bound)?
+      }
+
+      Ok(())
+    },
+
+    Int(_) | Float(_) | Str(_) | Bool(_) | Nil | Identifier(_) | Empty | EOF => Ok(()),
+  }
+}
+
+
+
+// Like `Visit`, for passes changing the tree as they go
+pub trait VisitMut {
+  fn body(&mut self, body: &mut [Statement]) -> Result<(), ()> {
+    walk_body_mut(self, body)
+  }
+
+  fn statement(&mut self, statement: &mut Statement) -> Result<(), ()> {
+    walk_statement_mut(self, statement)
+  }
+
+  fn expression(&mut self, expression: &mut Expression) -> Result<(), ()> {
+    walk_expression_mut(self, expression)
+  }
+}
+
+pub fn walk_body_mut<V: VisitMut + ?Sized>(visitor: &mut V, body: &mut [Statement]) -> Result<(), ()> {
+  for statement in body.iter_mut() {
+    visitor.statement(statement)?
+  }
+
+  Ok(())
+}
+
+pub fn walk_statement_mut<V: VisitMut + ?Sized>(visitor: &mut V, statement: &mut Statement) -> Result<(), ()> {
+  use self::StatementNode::*;
+
+  match statement.node {
+    Expression(ref mut expression) | Variable(_, _, ref mut expression, _) | Return(Some(ref mut expression)) | Throw(ref mut expression) => visitor.expression(expression),
+
+    Assignment(ref mut left, ref mut right) | Compound(ref mut left, _, ref mut right) => {
+      visitor.expression(left)?;
+      visitor.expression(right)
+    },
+
+    Function(ref mut name, ref mut params, _, ref mut body, ..) => {
+      visitor.expression(name)?;
+      walk_defaults_mut(visitor, params)?;
+      visitor.body(body)
+    },
+
+    Record(_, ref mut fields) => walk_defaults_mut(visitor, fields),
+
+    Enum(_, ref mut variants) => {
+      for variant in variants.iter_mut() {
+        walk_defaults_mut(visitor, &mut variant.fields)?
+      }
+
+      Ok(())
+    },
+
+    If(ref mut condition, ref mut body, ref mut else_branch) => {
+      visitor.expression(condition)?;
+      visitor.body(body)?;
+
+      match *else_branch {
+        Some((ref mut body, _)) => visitor.body(body),
+        None                    => Ok(()),
+      }
+    },
+
+    Match(ref mut subject, ref mut arms) => {
+      visitor.expression(subject)?;
+
+      for arm in arms.iter_mut() {
+        visitor.body(&mut arm.body)?
+      }
+
+      Ok(())
+    },
+
+    Try(ref mut body, ref mut catch, ref mut finally) => {
+      visitor.body(body)?;
+
+      if let Some(ref mut catch) = *catch {
+        visitor.body(&mut catch.body)?
+      }
+
+      match *finally {
+        Some((ref mut body, _)) => visitor.body(body),
+        None                    => Ok(()),
+      }
+    },
+
+    For(_, ref mut iterable, ref mut body) => {
+      visitor.expression(iterable)?;
+      visitor.body(body)
+    },
+
+    Return(None) | TypeAlias(..) | Import(_) | Skip | Break => Ok(()),
+  }
+}
+
+pub fn walk_defaults_mut<V: VisitMut + ?Sized>(visitor: &mut V, params: &mut [Param]) -> Result<(), ()> {
+  for default in params.iter_mut().filter_map(|param| param.default.as_mut()) {
+    visitor.expression(default)?
+  }
+
+  Ok(())
+}
+
+pub fn walk_expression_mut<V: VisitMut + ?Sized>(visitor: &mut V, expression: &mut Expression) -> Result<(), ()> {
+  use self::ExpressionNode::*;
+
+  match expression.node {
+    Call(ref mut called, ref mut args) => {
+      visitor.expression(Rc::make_mut(called))?;
+
+      for arg in args.iter_mut() {
+        visitor.expression(arg)?
+      }
+
+      Ok(())
+    },
+
+    Index(ref mut left, ref mut right, _) | Binary(ref mut left, _, ref mut right) => {
+      visitor.expression(Rc::make_mut(left))?;
+      visitor.expression(Rc::make_mut(right))
+    },
+
+    Neg(ref mut operand) | Not(ref mut operand) | Await(ref mut operand) | Yield(ref mut operand) | Spread(ref mut operand) | Named(_, ref mut operand) | Optional(ref mut operand) => {
+      visitor.expression(Rc::make_mut(operand))
+    },
+
+    Array(ref mut content) => {
+      for element in content.iter_mut() {
+        visitor.expression(element)?
+      }
+
+      Ok(())
+    },
+
+    Table(ref mut content) => {
+      for (_, value) in content.iter_mut() {
+        visitor.expression(value)?
+      }
+
+      Ok(())
+    },
+
+    Construct(ref mut record, ref mut content) => {
+      visitor.expression(Rc::make_mut(record))?;
+
+      for (_, value) in content.iter_mut() {
+        visitor.expression(value)?
+      }
+
+      Ok(())
+    },
+
+    Range(ref mut start, ref mut end, _) => {
+      for bound in start.iter_mut().chain(end.iter_mut()) {
+        visitor.expression(Rc::make_mut(bound))?
+      }
+
+      Ok(())
+    },
+
+    Int(_) | Float(_) | Str(_) | Bool(_) | Nil | Identifier(_) | Empty | EOF => Ok(()),
+  }
+}
+
+
+
+// What a body defines in the scope of the function it's in, blocks in it share that scope and functions in it get their own
+pub enum Defined<'a> {
+  Statement(&'a str, &'a Statement), // a variable, a function named by an identifier, a record or an enum
+  Loop(&'a str, &'a Statement),      // the name a `for` binds
+  Binding(&'a str, Pos),             // a field bound by the pattern of an arm
+  Caught(&'a str, &'a Catch),        // the name of a `fang`
+}
+
+impl<'a> Defined<'a> {
+  pub fn name(&self) -> &'a str {
+    match *self {
+      Defined::Statement(name, _) | Defined::Loop(name, _) | Defined::Binding(name, _) | Defined::Caught(name, _) => name,
+    }
+  }
+}
+
+pub fn definitions<'a, F: FnMut(Defined<'a>)>(body: &'a [Statement], define: &mut F) {
+  use self::StatementNode::*;
+
+  for statement in body.iter() {
+    match statement.node {
+      Variable(ref name, ..) | Record(ref name, _) | Enum(ref name, _) => define(Defined::Statement(name, statement)),
+
+      Function(ref name, ..) => if let ExpressionNode::Identifier(ref name) = name.node {
+        define(Defined::Statement(name, statement))
+      },
+
+      If(_, ref body, ref else_branch) => {
+        definitions(body, define);
+
+        if let Some((ref body, _)) = *else_branch {
+          definitions(body, define)
+        }
+      },
+
+      Match(_, ref arms) => for arm in arms.iter() {
+        if let Pattern::Variant(_, _, ref bindings) = arm.pattern {
+          for &(ref name, pos) in bindings.iter() {
+            define(Defined::Binding(name, pos))
+          }
+        }
+
+        definitions(&arm.body, define)
+      },
+
+      Try(ref body, ref catch, ref finally) => {
+        definitions(body, define);
+
+        if let Some(ref catch) = *catch {
+          if let Some(ref name) = catch.name {
+            define(Defined::Caught(name, catch))
+          }
+
+          definitions(&catch.body, define)
+        }
+
+        if let Some((ref body, _)) = *finally {
+          definitions(body, define)
+        }
+      },
+
+      For(ref name, _, ref body) => {
+        define(Defined::Loop(name, statement));
+
+        definitions(body, define)
+      },
+
+      Expression(_) | Assignment(..) | Compound(..) | Return(_) | TypeAlias(..) | Import(_) | Throw(_) | Skip | Break => (),
+    }
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // The identifiers reached, in the order they're walked
+  struct Names(Vec<String>);
+
+  impl<'a> Visit<'a> for Names {
+    fn expression(&mut self, expression: &'a Expression) -> Result<(), ()> {
+      if let ExpressionNode::Identifier(ref name) = expression.node {
+        self.0.push(name.clone())
+      }
+
+      walk_expression(self, expression)
+    }
+  }
+
+  fn ast(code: &str) -> Vec<Statement> {
+    crate::parse_str("<test>", code).result.unwrap()
+  }
+
+  #[test]
+  fn walks_into_every_node() {
+    let ast = ast("øl f(a, b = c) =\n  øl a\nprøv:\n  f(1, b: d ?? e)\nfang err:\n  kast err\nfor x in xs:\n  øl p = P { y: -z }\n");
+
+    let mut names = Names(Vec::new());
+
+    names.body(&ast).unwrap();
+
+    assert_eq!(names.0, ["f", "c", "a", "f", "d", "e", "err", "xs", "P", "z"])
+  }
+
+  #[test]
+  fn defines_what_blocks_bind_but_not_functions() {
+    let ast = ast("øl x = 1\nøl f(a) =\n  øl inner = a\n  øl inner\nfor i in [1]:\n  øl y = i\nmatch x:\n  Some(v): print(v)\n  _: print(0)\n");
+
+    let mut names = Vec::new();
+
+    definitions(&ast, &mut |defined| names.push(defined.name()));
+
+    assert_eq!(names, ["x", "f", "i", "y", "v"])
+  }
+}
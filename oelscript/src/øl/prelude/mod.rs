@@ -0,0 +1,9 @@
+#[allow(clippy::module_inception)]
+pub mod prelude;
+pub mod native;
+
+use super::parser::*;
+use super::vm::*;
+
+pub use self::prelude::*;
+pub use self::native::*;
@@ -0,0 +1,204 @@
+use super::*;
+
+// Native implementations of `PRELUDE`, behaving like its JavaScript runtime
+pub fn install(globals: &mut Globals) {
+  globals.native("print", |_, values| {
+    let values = values.iter().map(|value| format!("{}", value)).collect::<Vec<String>>();
+
+    println!("{}", values.join(" "));
+
+    Ok(Value::Nil)
+  });
+
+  globals.register("len", |value: Value| match value {
+    Value::Str(ref s)         => Ok(s.chars().count() as i64),
+    Value::Array(ref content) => Ok(content.borrow().len() as i64),
+    Value::Table(ref content) => Ok(content.borrow().len() as i64),
+    Value::Record(ref record) => Ok(record.fields.borrow().len() as i64),
+    _                         => Err(format!("can't take the length of {}", value.type_name())),
+  });
+
+  globals.native("map", |vm, args| {
+    let (array, f) = array_and_function("map", args)?;
+
+    let mut result = Vec::new();
+
+    for element in array {
+      vm.check_deadline()?;
+
+      result.push(vm.call(f.clone(), vec!(element))?)
+    }
+
+    Ok(result.into_value())
+  });
+
+  globals.native("filter", |vm, args| {
+    let (array, f) = array_and_function("filter", args)?;
+
+    let mut result = Vec::new();
+
+    for element in array {
+      vm.check_deadline()?;
+
+      if vm.call(f.clone(), vec!(element.clone()))?.truthy() {
+        result.push(element)
+      }
+    }
+
+    Ok(result.into_value())
+  });
+
+  globals.native("reduce", |vm, args| {
+    let (array, f) = array_and_function("reduce", args)?;
+
+    let mut elements = array.into_iter();
+
+    let mut accumulated = match args.get(2) {
+      Some(initial) => initial.clone(),
+      None          => match elements.next() {
+        Some(first) => first,
+        None        => return Err("can't reduce an empty array without an initial value".into()),
+      },
+    };
+
+    for element in elements {
+      vm.check_deadline()?;
+
+      accumulated = vm.call(f.clone(), vec!(accumulated, element))?
+    }
+
+    Ok(accumulated)
+  });
+
+  globals.register("split", |string: String, separator: String| {
+    if separator.is_empty() {
+      string.chars().map(|c| c.to_string()).collect::<Vec<String>>()
+    } else {
+      string.split(separator.as_str()).map(str::to_string).collect::<Vec<String>>()
+    }
+  });
+
+  globals.register("join", |array: Vec<Value>, separator: Option<String>| {
+    // nil is left out like `undefined` is by JS
+    let parts = array.iter().map(|value| match *value {
+      Value::Nil => String::new(),
+      ref value  => format!("{}", value),
+    }).collect::<Vec<String>>();
+
+    parts.join(&separator.unwrap_or_default())
+  });
+
+  globals.register("trim", |string: String| string.trim().to_string());
+
+  globals.register("floor", |x: f64| x.floor() as i64);
+  globals.register("ceil",  |x: f64| x.ceil() as i64);
+  globals.register("round", |x: f64| (x + 0.5).floor() as i64); // halves go up like `Math.round`
+  globals.register("sqrt",  |x: f64| x.sqrt());
+
+  globals.register("abs", |x: Value| match x {
+    Value::Int(n)   => Ok(n.checked_abs().map_or(Value::Float((n as f64).abs()), Value::Int)),
+    Value::Float(n) => Ok(Value::Float(n.abs())),
+    _               => Err(format!("expected a number, found {}", x.type_name())),
+  });
+
+  globals.native("min", |_, args| extreme("min", args, |a, b| a < b));
+  globals.native("max", |_, args| extreme("max", args, |a, b| a > b));
+
+  globals.register("keys", |table: Value| match table {
+    Value::Table(ref content) => Ok(content.borrow().iter().map(|(key, _)| key.clone()).collect::<Vec<String>>()),
+    Value::Record(ref record) => Ok(record.field_names().to_vec()),
+    _                         => Err(format!("expected table, found {}", table.type_name())),
+  });
+
+  globals.register("values", |table: Value| match table {
+    Value::Table(ref content) => Ok(content.borrow().iter().map(|(_, value)| value.clone()).collect::<Vec<Value>>()),
+    Value::Record(ref record) => Ok(record.fields.borrow().clone()),
+    _                         => Err(format!("expected table, found {}", table.type_name())),
+  });
+
+  globals.register("type", |value: Value| value.type_name());
+}
+
+
+
+fn array_and_function(name: &str, args: &[Value]) -> Result<(Vec<Value>, Value), RuntimeError> {
+  let array = match args.first() {
+    Some(Value::Array(content)) => content.borrow().clone(),
+    other                       => return Err(format!("argument 1 of `{}`: expected array, found {}", name, other.map_or("nothing", Value::type_name)).into()),
+  };
+
+  match args.get(1) {
+    Some(f @ &Value::Function(_)) | Some(f @ &Value::Native(_)) => Ok((array, f.clone())),
+    other => Err(format!("argument 2 of `{}`: expected function, found {}", name, other.map_or("nothing", Value::type_name)).into()),
+  }
+}
+
+// The number `wins` over every other one, keeping ints as ints
+fn extreme<F: Fn(f64, f64) -> bool>(name: &str, args: &[Value], wins: F) -> Result<Value, RuntimeError> {
+  let mut result: Option<(f64, Value)> = None;
+
+  for (i, arg) in args.iter().enumerate() {
+    let n = match *arg {
+      Value::Int(n)   => n as f64,
+      Value::Float(n) => n,
+      _               => return Err(format!("argument {} of `{}`: expected a number, found {}", i + 1, name, arg.type_name()).into()),
+    };
+
+    let better = match result {
+      Some((best, _)) => wins(n, best),
+      None            => true,
+    };
+
+    if better {
+      result = Some((n, arg.clone()))
+    }
+  }
+
+  match result {
+    Some((_, value)) => Ok(value),
+    None             => Err(format!("`{}` needs at least one number", name).into()),
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(code: &str, limits: Limits) -> crate::Output<Value> {
+    crate::run_str("<test>", code, &mut Globals::new(), limits)
+  }
+
+  #[test]
+  fn joins_nil_as_nothing() {
+    let output = run("øl t = {}\njoin([1, t.x, \"to\"], \"-\")\n", Limits::default());
+
+    assert_eq!(output.result.map(|value| value.to_string()), Some("1--to".to_string()))
+  }
+
+  #[test]
+  fn charges_what_it_builds() {
+    let limits = Limits {
+      memory: Some(256 * 1024),
+      ..Limits::default()
+    };
+
+    let calls = [
+      "join(xs, \",\")",
+      "split(\"a,b,c\", \",\")",
+      "map(xs, id)",
+      "filter(xs, id)",
+      "keys(t)",
+      "values(t)",
+    ];
+
+    for call in calls.iter() {
+      let code   = format!("øl id(x) =\n  øl x\nøl xs = [1, 2, 3, 4, 5, 6, 7, 8]\nøl t = {{ a: 1, b: 2, c: 3 }}\nfor i in 0..20000:\n  øl ys = {}\n", call);
+      let output = run(&code, limits);
+
+      assert_eq!(output.diagnostics.first().map(|diagnostic| diagnostic.message.as_str()), Some("ran out of memory, the limit is 262144 bytes"), "{}", call);
+      assert!(run(&code, Limits::default()).is_ok(), "{}", call)
+    }
+  }
+}
@@ -0,0 +1,355 @@
+use super::*;
+
+// A function every program can call without defining it
+pub struct Builtin {
+  pub name:   &'static str,
+  pub params: &'static [&'static str],
+  pub doc:    &'static str,
+  pub js:     &'static str, // its definition in the JavaScript runtime
+}
+
+pub const PRELUDE: &[Builtin] = &[
+  Builtin {
+    name:   "print",
+    params: &["...values"],
+    doc:    "Prints the values separated by spaces",
+    js:     "function print(...values) { console.log(...values) }",
+  },
+
+  Builtin {
+    name:   "len",
+    params: &["value"],
+    doc:    "Length of a string, array or table",
+    js:     "function len(value) { return typeof value === \"string\" ? [...value].length : Array.isArray(value) ? value.length : Object.keys(value).length }",
+  },
+
+  Builtin {
+    name:   "map",
+    params: &["array", "f"],
+    doc:    "A new array of `f` applied to every element",
+    js:     "function map(array, f) { return array.map(x => f(x)) }",
+  },
+
+  Builtin {
+    name:   "filter",
+    params: &["array", "f"],
+    doc:    "A new array of the elements `f` is truthy for",
+    js:     "function filter(array, f) { return array.filter(x => f(x)) }",
+  },
+
+  Builtin {
+    name:   "reduce",
+    params: &["array", "f", "initial"],
+    doc:    "Folds the array with `f(accumulated, element)`, starting from `initial` or the first element",
+    js:     "function reduce(array, f, initial) { return initial === undefined ? array.reduce((a, x) => f(a, x)) : array.reduce((a, x) => f(a, x), initial) }",
+  },
+
+  Builtin {
+    name:   "split",
+    params: &["string", "separator"],
+    doc:    "The parts of a string between separators, its characters for an empty separator",
+    js:     "function split(string, separator) { return string.split(separator) }",
+  },
+
+  Builtin {
+    name:   "join",
+    params: &["array", "separator"],
+    doc:    "The elements of an array as one string, with an optional separator in between",
+    js:     "function join(array, separator) { return array.join(separator === undefined ? \"\" : separator) }",
+  },
+
+  Builtin {
+    name:   "trim",
+    params: &["string"],
+    doc:    "A string without whitespace around it",
+    js:     "function trim(string) { return string.trim() }",
+  },
+
+  Builtin {
+    name:   "floor",
+    params: &["x"],
+    doc:    "Rounds down to an int",
+    js:     "function floor(x) { return Math.floor(x) }",
+  },
+
+  Builtin {
+    name:   "ceil",
+    params: &["x"],
+    doc:    "Rounds up to an int",
+    js:     "function ceil(x) { return Math.ceil(x) }",
+  },
+
+  Builtin {
+    name:   "round",
+    params: &["x"],
+    doc:    "Rounds to the nearest int, halves up",
+    js:     "function round(x) { return Math.round(x) }",
+  },
+
+  Builtin {
+    name:   "abs",
+    params: &["x"],
+    doc:    "Absolute value",
+    js:     "function abs(x) { return Math.abs(x) }",
+  },
+
+  Builtin {
+    name:   "sqrt",
+    params: &["x"],
+    doc:    "Square root",
+    js:     "function sqrt(x) { return Math.sqrt(x) }",
+  },
+
+  Builtin {
+    name:   "min",
+    params: &["...numbers"],
+    doc:    "The smallest of the numbers",
+    js:     "function min(...numbers) { return Math.min(...numbers) }",
+  },
+
+  Builtin {
+    name:   "max",
+    params: &["...numbers"],
+    doc:    "The largest of the numbers",
+    js:     "function max(...numbers) { return Math.max(...numbers) }",
+  },
+
+  Builtin {
+    name:   "keys",
+    params: &["table"],
+    doc:    "The keys of a table, in the order they were added",
+    js:     "function keys(table) { return Object.keys(table) }",
+  },
+
+  Builtin {
+    name:   "values",
+    params: &["table"],
+    doc:    "The values of a table, in the order they were added",
+    js:     "function values(table) { return Object.values(table) }",
+  },
+
+  Builtin {
+    name:   "type",
+    params: &["value"],
+    doc:    "Name of the type of a value: nil, bool, int, float, string, array, table or function",
+    js:     "function type(value) { return value === null || value === undefined ? \"nil\" : typeof value === \"boolean\" ? \"bool\" : typeof value === \"number\" ? (Number.isInteger(value) ? \"int\" : \"float\") : typeof value === \"string\" ? \"string\" : typeof value === \"function\" ? \"function\" : Array.isArray(value) ? \"array\" : \"table\" }",
+  },
+];
+
+// Helpers the compiled JavaScript calls on its own, `$` keeps them apart from names of øl programs
+const HELPERS: &[(&str, &str)] = &[
+  ("range$", "function range$(start, end) { const result = []; for (let i = start; i < end; i++) result.push(i); return result }"),
+  ("concat$", "function concat$(a, b) { return Array.isArray(a) ? a.concat(b) : \"\" + a + b }"),
+  ("concat_at$", "function concat_at$(object, key, value) { return object[key] = concat$(object[key], value) }"),
+];
+
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+  PRELUDE.iter().find(|builtin| builtin.name == name)
+}
+
+
+
+// The runtime helpers `ast` calls, leaving out names the program defines itself at the top level
+pub fn runtime_for(ast: &[Statement]) -> String {
+  let mut used = Vec::new();
+
+  for statement in ast.iter() {
+    uses_in_statement(statement, &mut used)
+  }
+
+  let defined = ast.iter().filter_map(|statement| match statement.node {
+    StatementNode::Variable(ref name, ..) |
+    StatementNode::Record(ref name, _)    |
+    StatementNode::Enum(ref name, _)      => Some(name.as_str()),
+    StatementNode::Function(ref name, ..) => match name.node {
+      ExpressionNode::Identifier(ref name) => Some(name.as_str()),
+      _                                    => None,
+    },
+    _ => None,
+  }).collect::<Vec<&str>>();
+
+  let mut result = String::new();
+
+  for builtin in PRELUDE.iter() {
+    if used.contains(&builtin.name) && !defined.contains(&builtin.name) {
+      result.push_str(builtin.js);
+      result.push('\n')
+    }
+  }
+
+  for &(name, js) in HELPERS.iter() {
+    if used.contains(&name) {
+      result.push_str(js);
+      result.push('\n')
+    }
+  }
+
+  result
+}
+
+fn uses_in_statement(statement: &Statement, used: &mut Vec<&'static str>) {
+  use self::StatementNode::*;
+
+  match statement.node {
+    Expression(ref expression)        |
+    Variable(_, _, ref expression, _) |
+    Return(Some(ref expression))      |
+    Throw(ref expression)             => uses_in_expression(expression, used),
+
+    Assignment(ref left, ref right) | Compound(ref left, _, ref right) => {
+      uses_in_expression(left, used);
+      uses_in_expression(right, used);
+
+      // `++=` is a call of `concat$`, through `concat_at$` on indexes
+      if let Compound(_, Operator::Concat, _) = statement.node {
+        let helpers: &[&'static str] = match left.node {
+          ExpressionNode::Index(..) => &["concat$", "concat_at$"],
+          _                         => &["concat$"],
+        };
+
+        for &helper in helpers.iter() {
+          if !used.contains(&helper) {
+            used.push(helper)
+          }
+        }
+      }
+    },
+
+    Function(ref name, ref params, _, ref body, ..) => {
+      uses_in_expression(name, used);
+
+      for default in params.iter().filter_map(|param| param.default.as_ref()) {
+        uses_in_expression(default, used)
+      }
+
+      for statement in body.iter() {
+        uses_in_statement(statement, used)
+      }
+    },
+
+    Record(_, ref fields) => for default in fields.iter().filter_map(|field| field.default.as_ref()) {
+      uses_in_expression(default, used)
+    },
+
+    Match(ref subject, ref arms) => {
+      uses_in_expression(subject, used);
+
+      for statement in arms.iter().flat_map(|arm| arm.body.iter()) {
+        uses_in_statement(statement, used)
+      }
+    },
+
+    If(ref condition, ref body, ref else_branch) => {
+      uses_in_expression(condition, used);
+
+      for statement in body.iter() {
+        uses_in_statement(statement, used)
+      }
+
+      if let Some((ref body, _)) = *else_branch {
+        for statement in body.iter() {
+          uses_in_statement(statement, used)
+        }
+      }
+    },
+
+    Try(ref body, ref catch, ref finally) => {
+      let catch   = catch.iter().flat_map(|catch| catch.body.iter());
+      let finally = finally.iter().flat_map(|(body, _)| body.iter());
+
+      for statement in body.iter().chain(catch).chain(finally) {
+        uses_in_statement(statement, used)
+      }
+    },
+
+    // counting loops don't make the range
+    For(_, ref iterable, ref body) => {
+      match iterable.node {
+        ExpressionNode::Range(..) => uses_in_bounds(iterable, used),
+        _                         => uses_in_expression(iterable, used),
+      }
+
+      for statement in body.iter() {
+        uses_in_statement(statement, used)
+      }
+    },
+
+    _ => (),
+  }
+}
+
+fn uses_in_expression(expression: &Expression, used: &mut Vec<&'static str>) {
+  use self::ExpressionNode::*;
+
+  match expression.node {
+    Identifier(ref name) => if let Some(builtin) = builtin(name) {
+      if !used.contains(&builtin.name) {
+        used.push(builtin.name)
+      }
+    },
+
+    // `++` is a call of `concat$`
+    Binary(ref left, ref op, ref right) => {
+      if *op == Operator::Concat && !used.contains(&"concat$") {
+        used.push("concat$")
+      }
+
+      uses_in_expression(left, used);
+      uses_in_expression(right, used)
+    },
+
+    // slices are `slice` calls
+    Index(ref indexed, ref index, _) => {
+      uses_in_expression(indexed, used);
+
+      match index.node {
+        Range(..) => uses_in_bounds(index, used),
+        _         => uses_in_expression(index, used),
+      }
+    },
+
+    Range(..) => {
+      if !used.contains(&"range$") {
+        used.push("range$")
+      }
+
+      uses_in_bounds(expression, used)
+    },
+
+    Neg(ref operand) | Not(ref operand) | Await(ref operand) | Yield(ref operand) | Spread(ref operand) | Optional(ref operand) => uses_in_expression(operand, used),
+
+    Array(ref content) => for element in content.iter() {
+      uses_in_expression(element, used)
+    },
+
+    Table(ref content) => for (_, value) in content.iter() {
+      uses_in_expression(value, used)
+    },
+
+    Construct(ref record, ref fields) => {
+      uses_in_expression(record, used);
+
+      for (_, value) in fields.iter() {
+        uses_in_expression(value, used)
+      }
+    },
+
+    Call(ref called, ref args) => {
+      uses_in_expression(called, used);
+
+      for arg in args.iter() {
+        uses_in_expression(arg, used)
+      }
+    },
+
+    _ => (),
+  }
+}
+
+fn uses_in_bounds(range: &Expression, used: &mut Vec<&'static str>) {
+  if let ExpressionNode::Range(ref start, ref end, _) = range.node {
+    for bound in start.iter().chain(end.iter()) {
+      uses_in_expression(bound, used)
+    }
+  }
+}
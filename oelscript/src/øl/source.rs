@@ -4,8 +4,11 @@ use std::io::prelude::*;
 use std::fmt;
 
 use colored::Colorize;
+use unicode_width::UnicodeWidthChar;
 
-#[derive(Debug)]
+use super::lexer::Pos;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct FilePath(pub String);
 
 impl fmt::Display for FilePath {
@@ -16,10 +19,12 @@ impl fmt::Display for FilePath {
 
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Source {
-  pub file:  FilePath,
-  pub lines: Vec<String>,
+  pub file:    FilePath,
+  pub content: String,
+
+  line_starts: Vec<usize>,
 }
 
 impl Source {
@@ -29,16 +34,367 @@ impl Source {
 
     source.read_to_string(&mut content).unwrap();
 
-    Source {
-      file:  FilePath(path),
-      lines: content.lines().map(|x| x.to_string()).collect()
-    }
+    Self::from(path.as_str(), content)
   }
 
-  pub fn from(path: &str, lines: Vec<String>) -> Self {
+  pub fn from(path: &str, content: String) -> Self {
+    let mut line_starts = vec!(0);
+
+    for (offset, c) in content.char_indices() {
+      if c == '\n' {
+        line_starts.push(offset + 1)
+      }
+    }
+
     Source {
       file: FilePath(path.into()),
-      lines,
+      content,
+      line_starts,
+    }
+  }
+
+  pub fn line_count(&self) -> usize {
+    self.line_starts.len()
+  }
+
+  // Text of the 1-based line `number`, without its line ending
+  pub fn line(&self, number: usize) -> &str {
+    let start = match self.line_starts.get(number.saturating_sub(1)) {
+      Some(start) => *start,
+      None        => return "",
+    };
+
+    let end = self.line_starts.get(number).cloned().unwrap_or(self.content.len());
+
+    self.content[start .. end].trim_end_matches(['\n', '\r'])
+  }
+
+  // 1-based line and column of a byte offset, columns count chars
+  pub fn location(&self, offset: usize) -> (usize, usize) {
+    let offset = offset.min(self.content.len());
+    let line   = match self.line_starts.binary_search(&offset) {
+      Ok(line)  => line,
+      Err(line) => line - 1,
+    };
+
+    let column = self.content[self.line_starts[line] .. offset].chars().count() + 1;
+
+    (line + 1, column)
+  }
+
+  // Byte offset of a 1-based line and column, clamped to the line
+  pub fn offset(&self, line: usize, column: usize) -> usize {
+    let start = match self.line_starts.get(line.saturating_sub(1)) {
+      Some(start) => *start,
+      None        => return self.content.len(),
+    };
+
+    let text = self.line(line);
+
+    start + text.char_indices().nth(column.saturating_sub(1)).map_or(text.len(), |(offset, _)| offset)
+  }
+
+  // Location of the last character covered by `pos`, the start for empty spans
+  pub fn end_location(&self, pos: &Pos) -> (usize, usize) {
+    match self.content[.. pos.1.min(self.content.len())].chars().next_back() {
+      Some(c) if pos.1 > pos.0 => self.location(pos.1 - c.len_utf8()),
+      _                        => self.location(pos.0),
+    }
+  }
+
+  pub fn slice(&self, pos: &Pos) -> &str {
+    &self.content[pos.0.min(self.content.len()) .. pos.1.min(self.content.len())]
+  }
+
+  // Terminal cells taken up by the first `column - 1` chars of `line`
+  pub fn display_column(line: &str, column: usize) -> usize {
+    line.chars().take(column.saturating_sub(1)).map(char_width).sum()
+  }
+
+  pub fn snippet(&self, pos: &Pos) -> Snippet<'_> {
+    Snippet {
+      source: self,
+      labels: vec!(Label { pos: *pos, message: None, primary: true }),
+    }
+  }
+}
+
+
+
+// Tabs are drawn as four spaces, combining marks take no space and wide characters two cells
+fn char_width(c: char) -> usize {
+  if c == '\t' {
+    4
+  } else {
+    c.width().unwrap_or(0)
+  }
+}
+
+
+
+struct Label {
+  pos:     Pos,
+  message: Option<String>,
+  primary: bool,
+}
+
+// Renders the lines covered by a primary span and any number of secondary labels
+pub struct Snippet<'s> {
+  source: &'s Source,
+  labels: Vec<Label>,
+}
+
+impl<'s> Snippet<'s> {
+  pub fn message(mut self, message: &str) -> Self {
+    self.labels[0].message = Some(message.to_string());
+    self
+  }
+
+  pub fn label(mut self, pos: &Pos, message: &str) -> Self {
+    self.labels.push(Label { pos: *pos, message: Some(message.to_string()), primary: false });
+    self
+  }
+
+  pub fn pos(&self) -> Pos {
+    self.labels[0].pos
+  }
+
+  // Secondary labels with their messages
+  pub fn labels(&self) -> Vec<(Pos, String)> {
+    self.labels[1 ..].iter().map(|label| (label.pos, label.message.clone().unwrap_or_default())).collect()
+  }
+
+  fn mark(&self, label: &Label, text: &str, with_message: bool) -> String {
+    let text = match label.message {
+      Some(ref message) if with_message => format!("{} {}", text, message),
+      _                                 => text.to_string(),
+    };
+
+    if label.primary {
+      format!("{}", text.red().bold())
+    } else {
+      format!("{}", text.blue().bold())
+    }
+  }
+}
+
+impl<'s> fmt::Display for Snippet<'s> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let spans = self.labels.iter().map(|label| {
+      let start = self.source.location(label.pos.0);
+      let end   = self.source.end_location(&label.pos);
+
+      (label, start, end)
+    }).collect::<Vec<_>>();
+
+    let multiline = spans.iter().any(|&(_, start, end)| start.0 != end.0);
+
+    // long regions only show their first two and last line
+    let mut numbers = Vec::new();
+
+    for &(_, start, end) in spans.iter() {
+      for number in start.0 ..= end.0 {
+        if number <= start.0 + 1 || number == end.0 {
+          numbers.push(number)
+        }
+      }
     }
+
+    numbers.sort();
+    numbers.dedup();
+
+    let linepad = format!("{:5} │", " ").blue().bold();
+
+    write!(f, "\n{}", linepad)?;
+
+    let mut previous: Option<usize> = None;
+
+    for number in numbers {
+      if previous.is_some_and(|previous| number > previous + 1) {
+        write!(f, "\n{}", format!("{:>5} │", "...").blue().bold())?;
+      }
+
+      previous = Some(number);
+
+      let gutter = if !multiline {
+        ""
+      } else if spans.iter().any(|&(_, start, end)| start.0 < number && number <= end.0) {
+        "│ "
+      } else {
+        "  "
+      };
+
+      let line = self.source.line(number);
+
+      write!(f, "\n{}{}{}", format!("{:5} │ ", number).blue().bold(), gutter.red().bold(), line.replace('\t', "    "))?;
+
+      for &(label, start, end) in spans.iter() {
+        let marker = if label.primary { "^" } else { "-" };
+
+        let start_column = Source::display_column(line, start.1);
+        let end_column   = Source::display_column(line, end.1);
+
+        if start.0 == number && end.0 == number {
+          let pad   = if multiline { "  " } else { "" };
+          let width = Source::display_column(line, end.1 + 1) - start_column;
+          let marks = marker.repeat(width.max(1));
+
+          write!(f, "\n{} {}{: <count$}{}", linepad, pad, "", self.mark(label, &marks, true), count=start_column)?;
+        } else if start.0 == number {
+          let marks = format!("╭{}{}", "─".repeat(start_column + 1), marker);
+
+          write!(f, "\n{} {}", linepad, self.mark(label, &marks, false))?;
+        } else if end.0 == number {
+          let marks = format!("╰{}{}", "─".repeat(end_column + 1), marker);
+
+          write!(f, "\n{} {}", linepad, self.mark(label, &marks, true))?;
+        }
+      }
+    }
+
+    write!(f, "\n{}", linepad)
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn source(content: &str) -> Source {
+    Source::from("<test>", content.to_string())
+  }
+
+  // Span of the first `text` in `source`
+  fn span(source: &Source, text: &str) -> Pos {
+    let start = source.content.find(text).unwrap();
+
+    Pos(start, start + text.len())
+  }
+
+  // The marks drawn under `pos`, after the gutter
+  fn carets(source: &Source, pos: Pos) -> String {
+    colored::control::set_override(false);
+
+    let drawn = format!("{}", source.snippet(&pos));
+    let line  = drawn.lines().find(|line| line.contains('^')).unwrap();
+
+    line["      │ ".len() ..].to_string()
+  }
+
+  fn drawn(snippet: Snippet<'_>) -> String {
+    colored::control::set_override(false);
+
+    format!("{}", snippet)
+  }
+
+  #[test]
+  fn locates_in_chars() {
+    let source = source("øl æble = 1\nå = 日本");
+
+    assert_eq!(source.location(span(&source, "æble").0), (1, 4));
+    assert_eq!(source.location(span(&source, "=").0), (1, 9));
+    assert_eq!(source.location(span(&source, "本").0), (2, 6));
+    assert_eq!(source.offset(2, 6), span(&source, "本").0);
+  }
+
+  #[test]
+  fn locates_tokens_after_danish_identifiers() {
+    let code   = "søde_øl = bær + 1";
+    let source = source(code);
+    let tokens = crate::tokenize_str("<test>", code).result.unwrap();
+
+    let lexemes = tokens.iter().map(|token| token.lexeme.as_str()).collect::<Vec<&str>>();
+
+    assert_eq!(lexemes, vec!("søde_øl", "=", "bær", "+", "1"));
+    assert_eq!(source.location(tokens[2].pos.0), (1, 11));
+    assert_eq!(source.slice(&tokens[2].pos), "bær");
+  }
+
+  #[test]
+  fn counts_display_columns() {
+    assert_eq!(Source::display_column("æble", 3), 2);
+    assert_eq!(Source::display_column("日本 = 1", 2), 2);
+    assert_eq!(Source::display_column("日本 = 1", 4), 5);
+    assert_eq!(Source::display_column("\tx", 2), 4);
+    assert_eq!(Source::display_column("e\u{301}x", 3), 1);
+  }
+
+  #[test]
+  fn puts_carets_under_danish_identifiers() {
+    let source = source("øl æble = blåbær\n");
+
+    assert_eq!(carets(&source, span(&source, "æble")), "   ^^^^");
+    assert_eq!(carets(&source, span(&source, "blåbær")), "          ^^^^^^");
+  }
+
+  #[test]
+  fn puts_carets_under_wide_characters() {
+    let source = source("øl 日本 = \"東京\"\n");
+
+    assert_eq!(carets(&source, span(&source, "本")), "     ^^");
+    assert_eq!(carets(&source, span(&source, "\"東京\"")), "          ^^^^^^");
+  }
+
+
+  #[test]
+  fn draws_spans_over_lines() {
+    let source = source("øl f(x) =\n  øl x\n    + 1\n");
+    let pos    = Pos(span(&source, "f(x)").0, span(&source, "1").1);
+
+    assert_eq!(drawn(source.snippet(&pos)), "
+      │
+    1 │   øl f(x) =
+      │ ╭────^
+    2 │ │   øl x
+    3 │ │     + 1
+      │ ╰───────^
+      │")
+  }
+
+  #[test]
+  fn draws_spans_from_danish_columns() {
+    let source = source("øl søde = bær ++\n  \"æble\"\n");
+    let pos    = Pos(span(&source, "bær").0, span(&source, "\"æble\"").1);
+
+    assert_eq!(drawn(source.snippet(&pos).message("not a string")), "
+      │
+    1 │   øl søde = bær ++
+      │ ╭───────────^
+    2 │ │   \"æble\"
+      │ ╰────────^ not a string
+      │")
+  }
+
+  #[test]
+  fn leaves_out_the_middle_of_long_spans() {
+    let source = source("a = [\n1,\n2,\n3,\n4]\n");
+    let pos    = Pos(span(&source, "[").0, span(&source, "]").1);
+
+    assert_eq!(drawn(source.snippet(&pos)), "
+      │
+    1 │   a = [
+      │ ╭─────^
+    2 │ │ 1,
+  ... │
+    5 │ │ 4]
+      │ ╰──^
+      │")
+  }
+
+  #[test]
+  fn draws_labels_on_other_lines() {
+    let source  = source("øl f(x) =\n  øl x\n    + 1\n");
+    let snippet = source.snippet(&span(&source, "1")).message("here").label(&span(&source, "f(x)"), "defined here");
+
+    assert_eq!(drawn(snippet), "
+      │
+    1 │ øl f(x) =
+      │    ---- defined here
+  ... │
+    3 │     + 1
+      │       ^ here
+      │")
   }
 }
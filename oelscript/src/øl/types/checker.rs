@@ -0,0 +1,1535 @@
+use super::*;
+use super::super::error::Response::Wrong;
+
+use std::rc::Rc;
+use std::collections::HashMap;
+
+// A type generic over `vars`, fresh ones replace them wherever the name is used
+#[derive(Debug, Clone)]
+pub struct Scheme {
+  pub vars: Vec<usize>,
+  pub ty:   Type,
+}
+
+impl Scheme {
+  pub fn mono(ty: Type) -> Self {
+    Scheme {
+      vars: Vec::new(),
+      ty,
+    }
+  }
+}
+
+// The names of a function being checked, scoped to the whole function like JS `var`
+struct Scope {
+  names:   HashMap<String, Scheme>,
+  ret:     Type,
+  returns: bool,
+}
+
+impl Scope {
+  fn new(ret: Type) -> Self {
+    Scope {
+      names:   HashMap::new(),
+      ret,
+      returns: false,
+    }
+  }
+}
+
+// Hindley-Milner inference over the AST, reporting every mismatch it finds
+pub struct Checker<'c> {
+  pub source: &'c Source,
+  bindings:   Vec<Option<Type>>, // what each variable turned out to be
+  numeric:    Vec<bool>,         // variables only numbers may fill, from being used in arithmetic
+  scopes:     Vec<Scope>,
+  aliases:    HashMap<String, TypeExpression>,
+  records:    HashMap<String, (Type, Vec<bool>)>, // the type of each record and which fields have defaults
+  enums:      HashMap<String, Vec<(String, Vec<Type>)>>, // the fields of each variant
+  expanding:  Vec<String>, // aliases being turned into types, to catch recursive ones
+  failed:     bool,
+}
+
+impl<'c> Checker<'c> {
+  pub fn new(source: &'c Source) -> Self {
+    Checker {
+      source,
+      bindings:  Vec::new(),
+      numeric:   Vec::new(),
+      scopes:    Vec::new(),
+      aliases:   HashMap::new(),
+      records:   HashMap::new(),
+      enums:     HashMap::new(),
+      expanding: Vec::new(),
+      failed:    false,
+    }
+  }
+
+  pub fn check(&mut self, ast: &[Statement]) -> Result<(), ()> {
+    self.scopes.push(Scope::new(Type::Nil));
+
+    self.check_body(ast);
+
+    // numbers nothing pinned down are floats, like in JS
+    for var in 0 .. self.bindings.len() {
+      if self.numeric[var] && self.bindings[var].is_none() {
+        self.bindings[var] = Some(Type::Float)
+      }
+    }
+
+    if self.failed {
+      Err(())
+    } else {
+      Ok(())
+    }
+  }
+
+
+
+  // Whether anything was reported, the types that didn't check out are `Any`
+  pub fn failed(&self) -> bool {
+    self.failed
+  }
+
+  // What was found out about a top level name, once checked
+  pub fn global(&self, name: &str) -> Option<Scheme> {
+    let scheme = self.scopes.first()?.names.get(name)?;
+    let ty     = self.zonk(&scheme.ty);
+
+    let vars = ty.vars().into_iter().filter(|var| scheme.vars.contains(var)).collect();
+
+    Some(
+      Scheme {
+        vars,
+        ty,
+      }
+    )
+  }
+
+
+
+  fn check_body(&mut self, body: &[Statement]) {
+    // functions can be called and types used before they're defined
+    self.hoist(body);
+
+    for statement in body.iter() {
+      self.check_statement(statement)
+    }
+  }
+
+  fn hoist(&mut self, body: &[Statement]) {
+    for statement in body.iter() {
+      match statement.node {
+        StatementNode::Function(Expression { node: ExpressionNode::Identifier(ref name), .. }, ..) => {
+          let ty = self.fresh();
+
+          self.declare(name, Scheme::mono(ty))
+        },
+
+        StatementNode::TypeAlias(ref name, ref ty) => {
+          self.aliases.insert(name.clone(), ty.clone());
+        },
+
+        StatementNode::If(_, ref body, ref else_branch) => {
+          self.hoist(body);
+
+          if let Some((ref body, _)) = *else_branch {
+            self.hoist(body)
+          }
+        },
+
+        StatementNode::Try(ref body, ref catch, ref finally) => {
+          self.hoist(body);
+
+          if let Some(ref catch) = *catch {
+            self.hoist(&catch.body)
+          }
+
+          if let Some((ref body, _)) = *finally {
+            self.hoist(body)
+          }
+        },
+
+        StatementNode::For(_, _, ref body) => self.hoist(body),
+
+        _ => (),
+      }
+    }
+
+    // once the aliases their fields may use are known
+    self.hoist_types(body)
+  }
+
+  // Records and enums with their constructors, the defaults of records are checked where they are
+  fn hoist_types(&mut self, body: &[Statement]) {
+    for statement in body.iter() {
+      if let StatementNode::Enum(ref name, ref variants) = statement.node {
+        // enums are told apart by name, so they can hold themselves
+        self.enums.insert(name.clone(), Vec::new());
+
+        let ty = Type::Enum(name.clone());
+
+        let mut constructors = Vec::new();
+        let mut fields       = Vec::new();
+
+        for variant in variants.iter() {
+          let types = variant.fields.iter().map(|field| match field.ty {
+            Some(ref ty) => self.annotation(ty),
+            None         => self.fresh(),
+          }).collect::<Vec<Type>>();
+
+          let constructor = if types.is_empty() { ty.clone() } else { Type::function(types.clone(), ty.clone()) };
+
+          constructors.push((variant.name.clone(), constructor));
+          fields.push((variant.name.clone(), types));
+        }
+
+        self.enums.insert(name.clone(), fields);
+        self.declare(name, Scheme::mono(Type::Record(constructors, None)))
+      }
+
+      if let StatementNode::Record(ref name, ref fields) = statement.node {
+        // there are no recursive types, a record holding itself holds anything there
+        self.records.insert(name.clone(), (Type::Any, Vec::new()));
+
+        let types = fields.iter().map(|field| match field.ty {
+          Some(ref ty) => self.annotation(ty),
+          None         => self.fresh(),
+        }).collect::<Vec<Type>>();
+
+        let record   = Type::Record(fields.iter().map(|field| field.name.clone()).zip(types.iter().cloned()).collect(), None);
+        let defaults = fields.iter().map(|field| field.default.is_some()).collect::<Vec<bool>>();
+
+        let constructor = Type::Function(
+          Signature {
+            required: defaults.iter().rposition(|default| !default).map_or(0, |i| i + 1),
+            params:   types,
+            rest:     None,
+            ret:      Rc::new(record.clone()),
+          }
+        );
+
+        self.records.insert(name.clone(), (record, defaults));
+        self.declare(name, Scheme::mono(constructor))
+      }
+    }
+  }
+
+  fn check_statement(&mut self, statement: &Statement) {
+    use self::StatementNode::*;
+
+    match statement.node {
+      Expression(ref expression) => {
+        self.infer(expression);
+      },
+
+      Variable(ref name, ref annotation, ref right, _) => {
+        let mut ty = self.infer(right);
+
+        if let Some(ref annotation) = *annotation {
+          let declared = self.annotation(annotation);
+
+          self.expect(&ty, &declared, right.pos);
+
+          ty = declared
+        }
+
+        self.declare(name, Scheme::mono(ty))
+      },
+
+      Assignment(ref left, ref right) => {
+        let ty = self.infer(right);
+
+        self.check_assignment(left, ty, right.pos)
+      },
+
+      Compound(ref left, ref op, ref right) => {
+        let ty = self.infer_binary(left, op, right, statement.pos);
+
+        self.check_assignment(left, ty, right.pos)
+      },
+
+      Function(ref name, ref params, ref ret, ref body, _, is_generator) => {
+        let ty = self.infer_function(params, ret, body, is_generator, statement.pos);
+
+        match name.node {
+          ExpressionNode::Identifier(ref id) => {
+            // taken out so it doesn't keep its own variables from being generalized
+            if let Some(hoisted) = self.scopes.last_mut().unwrap().names.remove(id) {
+              if hoisted.vars.is_empty() {
+                self.expect(&ty, &hoisted.ty, name.pos)
+              }
+            }
+
+            let scheme = self.generalize(&ty);
+
+            self.declare(id, scheme)
+          },
+
+          _ => self.check_assignment(name, ty, statement.pos),
+        }
+      },
+
+      If(ref condition, ref body, ref else_branch) => {
+        self.infer(condition);
+
+        for statement in body.iter() {
+          self.check_statement(statement)
+        }
+
+        if let Some((ref body, _)) = *else_branch {
+          for statement in body.iter() {
+            self.check_statement(statement)
+          }
+        }
+      },
+
+      Return(ref value) => {
+        // returning from the top level is reported by the code generators
+        if self.scopes.len() == 1 {
+          return
+        }
+
+        let (ty, pos) = match *value {
+          Some(ref value) => (self.infer(value), value.pos),
+          None            => (Type::Nil, statement.pos),
+        };
+
+        let ret = {
+          let scope = self.scopes.last_mut().unwrap();
+
+          scope.returns = true;
+          scope.ret.clone()
+        };
+
+        self.expect(&ty, &ret, pos)
+      },
+
+      Record(ref name, ref fields) => {
+        let types = match self.records.get(name) {
+          Some(&(Type::Record(ref types, _), _)) => types.clone(),
+          _                                      => return,
+        };
+
+        for (field, (_, ty)) in fields.iter().zip(types.iter()) {
+          if let Some(ref default) = field.default {
+            let default_ty = self.infer(default);
+
+            self.expect(&default_ty, ty, default.pos)
+          }
+        }
+      },
+
+      Match(ref subject, ref arms) => self.check_match(subject, arms),
+
+      // anything can be thrown, so what's caught can be anything
+      Try(ref body, ref catch, ref finally) => {
+        for statement in body.iter() {
+          self.check_statement(statement)
+        }
+
+        if let Some(ref catch) = *catch {
+          if let Some(ref name) = catch.name {
+            self.declare(name, Scheme::mono(Type::Any))
+          }
+
+          for statement in catch.body.iter() {
+            self.check_statement(statement)
+          }
+        }
+
+        if let Some((ref body, _)) = *finally {
+          for statement in body.iter() {
+            self.check_statement(statement)
+          }
+        }
+      },
+
+      Throw(ref value) => {
+        self.infer(value);
+      },
+
+      // generators aren't typed, so what they hand out can be anything
+      For(ref name, ref iterable, ref body) => {
+        let iterable_ty = self.infer(iterable);
+
+        let element = match self.resolve(&iterable_ty) {
+          Type::Array(element) => (*element).clone(),
+          Type::Str            => Type::Str,
+          Type::Var(_)         |
+          Type::Any            => Type::Any,
+          ty                   => {
+            let message = format!("can't loop over {}", self.show(&ty));
+
+            self.error(message, iterable.pos)
+          },
+        };
+
+        self.declare(name, Scheme::mono(element));
+
+        for statement in body.iter() {
+          self.check_statement(statement)
+        }
+      },
+
+      Enum(..) | TypeAlias(..) | Import(_) | Skip | Break => (),
+    }
+  }
+
+  // The arms bind the fields of the variants of one enum, and have to cover all of them
+  fn check_match(&mut self, subject: &Expression, arms: &[Arm]) {
+    let subject_ty = self.infer(subject);
+
+    let of_enum = match self.resolve(&subject_ty) {
+      Type::Enum(name) => Some(name),
+
+      _ => arms.iter().filter_map(|arm| match arm.pattern {
+        Pattern::Variant(Some(ref name), ..)   => Some(name.clone()),
+        Pattern::Variant(None, ref variant, _) => self.enum_with(variant),
+        Pattern::Wildcard                      => None,
+      }).next(),
+    };
+
+    let variants = match of_enum {
+      Some(ref name) => {
+        self.expect(&subject_ty, &Type::Enum(name.clone()), subject.pos);
+
+        self.enums.get(name).cloned()
+      },
+
+      None => None,
+    };
+
+    for arm in arms.iter() {
+      if let Pattern::Variant(_, ref name, ref bindings) = arm.pattern {
+        let fields = variants.as_ref().and_then(|variants| variants.iter().find(|&(variant, _)| variant == name)).map(|(_, fields)| fields.clone());
+
+        for (i, (binding, _)) in bindings.iter().enumerate() {
+          let ty = fields.as_ref().and_then(|fields| fields.get(i).cloned()).unwrap_or(Type::Any);
+
+          self.declare(binding, Scheme::mono(ty))
+        }
+      }
+
+      for statement in arm.body.iter() {
+        self.check_statement(statement)
+      }
+    }
+
+    if let (Some(name), Some(variants)) = (of_enum, variants) {
+      if arms.iter().any(|arm| arm.pattern == Pattern::Wildcard) {
+        return
+      }
+
+      let missing = variants.iter().map(|(variant, _)| variant.clone()).filter(|variant| {
+        arms.iter().all(|arm| match arm.pattern {
+          Pattern::Variant(_, ref name, _) => name != variant,
+          Pattern::Wildcard                => true,
+        })
+      }).collect::<Vec<String>>();
+
+      if !missing.is_empty() {
+        let missing = missing.iter().map(|variant| format!("`{}`", variant)).collect::<Vec<String>>();
+
+        self.error(format!("the match on `{}` misses {}", name, missing.join(", ")), subject.pos);
+      }
+    }
+  }
+
+  // The enum declaring `variant`, the first one in order of name when there are more
+  fn enum_with(&self, variant: &str) -> Option<String> {
+    let mut names = self.enums.iter()
+      .filter(|&(_, variants)| variants.iter().any(|(name, _)| name == variant))
+      .map(|(name, _)| name.clone())
+      .collect::<Vec<String>>();
+
+    names.sort();
+    names.into_iter().next()
+  }
+
+  fn check_assignment(&mut self, left: &Expression, ty: Type, pos: Pos) {
+    match left.node {
+      // the fields of a table are the ones it was made with
+      ExpressionNode::Index(ref indexed, ref index, _) => if let ExpressionNode::Str(ref name) = index.node {
+        let indexed_ty = self.infer(indexed);
+
+        let field = self.field(&indexed_ty, name, left.pos);
+
+        self.expect(&ty, &field, pos)
+      } else {
+        let indexed = self.infer(left);
+
+        self.expect(&ty, &indexed, pos)
+      },
+
+      _ => {
+        let variable = self.infer(left);
+
+        self.expect(&ty, &variable, pos)
+      },
+    }
+  }
+
+  fn infer_function(&mut self, params: &[Param], ret: &Option<TypeExpression>, body: &[Statement], is_generator: bool, pos: Pos) -> Type {
+    let ret = match *ret {
+      Some(ref ret) => self.annotation(ret),
+      None          => self.fresh(),
+    };
+
+    let mut types = params.iter().map(|param| match param.ty {
+      Some(ref ty)       => self.annotation(ty),
+      None if param.rest => Type::Array(Rc::new(self.fresh())),
+      None               => self.fresh(),
+    }).collect::<Vec<Type>>();
+
+    self.scopes.push(Scope::new(ret.clone()));
+
+    for (param, ty) in params.iter().zip(types.iter()) {
+      self.declare(&param.name, Scheme::mono(ty.clone()));
+
+      // defaults see the params before them
+      if let Some(ref default) = param.default {
+        let default_ty = self.infer(default);
+
+        self.expect(&default_ty, ty, default.pos)
+      }
+    }
+
+    self.check_body(body);
+
+    let returns = self.scopes.pop().unwrap().returns;
+
+    // the arguments a rest parameter gathers are each of the type of its elements
+    let rest = match params.last() {
+      Some(param) if param.rest => {
+        let ty = types.pop().unwrap();
+
+        let element = match self.resolve(&ty) {
+          Type::Array(element) => (*element).clone(),
+          Type::Any            => Type::Any,
+          ty                   => {
+            let message = format!("`...{}` gathers the arguments left into an array, found {}", param.name, self.show(&ty));
+
+            self.error(message, param.pos)
+          },
+        };
+
+        Some(Rc::new(element))
+      },
+
+      _ => None,
+    };
+
+    // nil gets the default, so it can be passed for params with one
+    let types = params.iter().zip(types).map(|(param, ty)| match param.default {
+      Some(_) => Type::Nullable(Rc::new(ty)),
+      None    => ty,
+    }).collect::<Vec<Type>>();
+
+    let required = params.iter().rposition(|param| !param.rest && param.default.is_none()).map_or(0, |i| i + 1);
+
+    // calling a generator function gives the generator, which isn't typed
+    let ret = if is_generator {
+      Type::Any
+    } else {
+      // falling off the end returns nil
+      if !returns {
+        self.expect(&Type::Nil, &ret, pos)
+      }
+
+      ret
+    };
+
+    Type::Function(
+      Signature {
+        required,
+        params:   types,
+        rest,
+        ret:      Rc::new(ret),
+      }
+    )
+  }
+
+
+
+  fn infer(&mut self, expression: &Expression) -> Type {
+    use self::ExpressionNode::*;
+
+    let pos = expression.pos;
+
+    match expression.node {
+      Int(n)   => if n > i64::MAX as u64 { Type::Float } else { Type::Int },
+      Float(_) => Type::Float,
+      Str(_)   => Type::Str,
+      Bool(_)  => Type::Bool,
+
+      Nil | Empty | EOF => Type::Nil,
+
+      Identifier(ref name) => match self.lookup(name) {
+        Some(scheme) => self.instantiate(&scheme),
+        None         => match self.builtin(name) {
+          Some(ty) => ty,
+          None     => self.error(format!("no such value `{}`", name), pos),
+        },
+      },
+
+      Neg(ref operand) => {
+        let ty = self.infer(operand);
+
+        if self.number(&ty) {
+          ty
+        } else {
+          let message = format!("can't negate {}", self.show(&ty));
+
+          self.error(message, pos)
+        }
+      },
+
+      Not(ref operand) => {
+        self.infer(operand);
+
+        Type::Bool
+      },
+
+      // promises aren't typed, an `asynk` function is checked as if it returned what it resolves to
+      Await(ref operand) => self.infer(operand),
+
+      // a `for` loop resumes the generator with nothing
+      Yield(ref operand) => {
+        self.infer(operand);
+
+        Type::Nil
+      },
+
+      Range(ref start, ref end, _) => {
+        let element = self.bounds(start, end);
+
+        Type::Array(Rc::new(element))
+      },
+
+      Binary(ref left, ref op, ref right) => self.infer_binary(left, op, right, pos),
+
+      Array(ref content) => {
+        let element = self.fresh();
+
+        for value in content.iter() {
+          let ty = self.infer(value);
+
+          self.expect(&ty, &element, value.pos)
+        }
+
+        Type::Array(Rc::new(element))
+      },
+
+      Table(ref content) => {
+        let mut fields: Vec<(String, Type)> = Vec::new();
+
+        // spreading what isn't known to have only the fields it has so far makes the fields unknown too
+        let mut known = true;
+
+        for (key, value) in content.iter() {
+          let added = match value.node {
+            Spread(ref spread) => {
+              let spread_ty = self.infer(spread);
+
+              match self.resolve(&spread_ty) {
+                Type::Record(spread_fields, None) => spread_fields,
+                Type::Nil                         => Vec::new(),
+
+                Type::Record(..) | Type::Nullable(_) | Type::Var(_) | Type::Any => {
+                  known = false;
+
+                  Vec::new()
+                },
+
+                ty => {
+                  let message = format!("can't spread {} into a table", self.show(&ty));
+
+                  self.error(message, value.pos);
+
+                  Vec::new()
+                },
+              }
+            },
+
+            _ => vec!((key.clone(), self.infer(value))),
+          };
+
+          for (key, ty) in added {
+            match fields.iter().position(|(name, _)| *name == key) {
+              Some(i) => fields[i].1 = ty,
+              None    => fields.push((key, ty)),
+            }
+          }
+        }
+
+        if known { Type::Record(fields, None) } else { Type::Any }
+      },
+
+      // put in place by `arrange_arguments` right after parsing
+      Named(..) => unreachable!(),
+
+      // one of what's spread, they're only in arguments, arrays and tables
+      Spread(ref spread) => {
+        let ty = self.infer(spread);
+
+        match self.resolve(&ty) {
+          Type::Array(element) => (*element).clone(),
+          Type::Str            => Type::Str,
+          Type::Var(_)         |
+          Type::Any            => Type::Any,
+          ty                   => {
+            let message = format!("can't spread {}", self.show(&ty));
+
+            self.error(message, spread.pos)
+          },
+        }
+      },
+
+      Call(ref called, ref args) => {
+        let mut arg_types = Vec::new();
+
+        // `a\b(c)` passes `a` along as the first argument, untyped as methods stored in `a` would make its type infinite
+        let function = match called.node {
+          Index(ref indexed, ref index, true) => {
+            let receiver = self.infer_link(indexed);
+            let function = self.infer_index(&receiver, index, called.pos);
+
+            arg_types.push((Type::Any, indexed.pos));
+
+            function
+          },
+
+          _ => self.infer_link(called),
+        };
+
+        // how many arguments are before the first spread, how many there are after it isn't known
+        let known = args.iter().position(|arg| matches!(arg.node, Spread(_))).map(|i| arg_types.len() + i);
+
+        for arg in args.iter() {
+          arg_types.push((self.infer(arg), arg.pos))
+        }
+
+        let ty = self.call(&function, arg_types, known, called.pos, pos);
+
+        self.chain(called, ty)
+      },
+
+      Index(ref indexed, ref index, _) => {
+        let indexed_ty = self.infer_link(indexed);
+        let ty         = self.infer_index(&indexed_ty, index, pos);
+
+        self.chain(indexed, ty)
+      },
+
+      // what the chain goes on from when it isn't nil
+      Optional(ref value) => {
+        let ty = self.infer_link(value);
+
+        self.held(&ty)
+      },
+
+      Construct(ref record, ref fields) => self.infer_construct(record, fields, pos),
+    }
+  }
+
+  // `Player { x: 1 }`, every field without a default has to be given
+  fn infer_construct(&mut self, record: &Expression, given: &[(String, Expression)], pos: Pos) -> Type {
+    let name = match record.node {
+      ExpressionNode::Identifier(ref name) => name,
+      _                                    => unreachable!(),
+    };
+
+    let (ty, defaults) = match self.records.get(name) {
+      Some((ty, defaults)) => (ty.clone(), defaults.clone()),
+      None                 => return self.error(format!("`{}` isn't a record", name), record.pos),
+    };
+
+    let fields = match ty {
+      Type::Record(ref fields, _) => fields.clone(),
+      _                           => unreachable!(),
+    };
+
+    for (key, value) in given.iter() {
+      let value_ty = self.infer(value);
+
+      match fields.iter().find(|&(field, _)| field == key) {
+        Some((_, field_ty)) => self.expect(&value_ty, field_ty, value.pos),
+        None                => {
+          self.error(format!("`{}` has no field `{}`", name, key), value.pos);
+        },
+      }
+    }
+
+    for ((field, _), default) in fields.iter().zip(defaults.iter()) {
+      if !default && given.iter().all(|(key, _)| key != field) {
+        self.error(format!("missing field `{}` of `{}`", field, name), pos);
+      }
+    }
+
+    ty
+  }
+
+  // What a chain goes on from, a `?.` before it has made sure it isn't nil
+  fn infer_link(&mut self, link: &Expression) -> Type {
+    let ty = self.infer(link);
+
+    if link.is_optional() { self.held(&ty) } else { ty }
+  }
+
+  // A chain with a `?.` in it is nil when what's before the `?.` is
+  fn chain(&mut self, link: &Expression, ty: Type) -> Type {
+    match self.resolve(&ty) {
+      Type::Nullable(_)       => ty,
+      _ if link.is_optional() => Type::Nullable(Rc::new(ty)),
+      _                       => ty,
+    }
+  }
+
+  fn held(&mut self, ty: &Type) -> Type {
+    match self.resolve(ty) {
+      Type::Nullable(held) => (*held).clone(),
+      _                    => ty.clone(),
+    }
+  }
+
+  fn infer_index(&mut self, indexed: &Type, index: &Expression, pos: Pos) -> Type {
+    if let ExpressionNode::Str(ref name) = index.node {
+      return self.field(indexed, name, pos)
+    }
+
+    // a slice is of the type of what it's taken from
+    if let ExpressionNode::Range(ref start, ref end, _) = index.node {
+      self.bounds(start, end);
+
+      return match self.resolve(indexed) {
+        Type::Array(_) | Type::Str | Type::Var(_) | Type::Any => indexed.clone(),
+
+        ty => {
+          let message = format!("can't slice {}", self.show(&ty));
+
+          self.error(message, pos)
+        },
+      }
+    }
+
+    let index_ty = self.infer(index);
+
+    match self.resolve(indexed) {
+      Type::Array(element) => {
+        self.expect(&index_ty, &Type::Int, index.pos);
+
+        (*element).clone()
+      },
+
+      Type::Str => {
+        self.expect(&index_ty, &Type::Int, index.pos);
+
+        Type::Str
+      },
+
+      Type::Record(..) => {
+        self.expect(&index_ty, &Type::Str, index.pos);
+
+        Type::Any
+      },
+
+      Type::Var(_) if self.resolve(&index_ty) == Type::Int => {
+        let element = self.fresh();
+
+        self.expect(indexed, &Type::Array(Rc::new(element.clone())), pos);
+
+        element
+      },
+
+      Type::Var(_) | Type::Any => Type::Any,
+
+      indexed => {
+        let message = format!("can't index {} with {}", self.show(&indexed), self.show(&index_ty));
+
+        self.error(message, pos)
+      },
+    }
+  }
+
+  // The ends of a range are numbers, what's in it is of the type of the start
+  fn bounds(&mut self, start: &Option<Rc<Expression>>, end: &Option<Rc<Expression>>) -> Type {
+    let mut types = Vec::new();
+
+    for bound in start.iter().chain(end.iter()) {
+      let ty = self.infer(bound);
+
+      if !self.number(&ty) {
+        let message = format!("expected a number, found {}", self.show(&ty));
+
+        self.error(message, bound.pos);
+      }
+
+      types.push(ty)
+    }
+
+    if types.len() == 2 {
+      let _ = self.unify(&types[0], &types[1]);
+    }
+
+    types.into_iter().next().unwrap_or(Type::Int)
+  }
+
+  // The type of `indexed.name`, records not known to be closed get the field added
+  fn field(&mut self, indexed: &Type, name: &str, pos: Pos) -> Type {
+    match self.resolve(indexed) {
+      Type::Array(_) | Type::Str | Type::Var(_) if name == "length" => Type::Int,
+
+      Type::Enum(_) if name == TAG => Type::Str,
+
+      Type::Any => Type::Any,
+
+      Type::Record(fields, rest) => {
+        if let Some((_, ty)) = fields.iter().find(|&(field, _)| field == name) {
+          return ty.clone()
+        }
+
+        match rest {
+          Some(rest) => {
+            let ty   = self.fresh();
+            let more = self.fresh_var();
+
+            self.bind(rest, Type::Record(vec!((name.to_string(), ty.clone())), Some(more))).unwrap();
+
+            ty
+          },
+
+          None => {
+            let message = format!("{} has no field `{}`", self.show(indexed), name);
+
+            self.error(message, pos)
+          },
+        }
+      },
+
+      Type::Var(_) => {
+        let ty   = self.fresh();
+        let rest = self.fresh_var();
+
+        self.expect(indexed, &Type::Record(vec!((name.to_string(), ty.clone())), Some(rest)), pos);
+
+        ty
+      },
+
+      indexed => {
+        let message = format!("can't index {} with `{}`", self.show(&indexed), name);
+
+        self.error(message, pos)
+      },
+    }
+  }
+
+  // The arguments from `known` on are spread, so where each of them goes isn't known
+  fn call(&mut self, function: &Type, args: Vec<(Type, Pos)>, known: Option<usize>, called: Pos, pos: Pos) -> Type {
+    match self.resolve(function) {
+      Type::Any => Type::Any,
+
+      Type::Var(_) => {
+        let ret = self.fresh();
+
+        let signature = match known {
+          Some(known) => {
+            let element = self.fresh();
+
+            for &(ref ty, pos) in args[known ..].iter() {
+              self.expect(ty, &element, pos)
+            }
+
+            Type::Function(
+              Signature {
+                required: known,
+                params:   args[.. known].iter().map(|(ty, _)| ty.clone()).collect(),
+                rest:     Some(Rc::new(element)),
+                ret:      Rc::new(ret.clone()),
+              }
+            )
+          },
+
+          None => Type::function(args.into_iter().map(|(ty, _)| ty).collect(), ret.clone()),
+        };
+
+        self.expect(&signature, function, called);
+
+        ret
+      },
+
+      Type::Function(signature) => {
+        let too_many = signature.rest.is_none() && args.len() > signature.params.len();
+
+        if known.is_none() && (args.len() < signature.required || too_many) {
+          let expected = if signature.required == signature.params.len() {
+            format!("{}", signature.required)
+          } else {
+            format!("{} to {}", signature.required, signature.params.len())
+          };
+
+          let message = format!("expected {} arguments, found {}", expected, args.len());
+
+          self.error(message, pos);
+        }
+
+        let known = known.unwrap_or(args.len());
+
+        for (i, (ty, pos)) in args.into_iter().enumerate() {
+          if i >= known {
+            let left = signature.params.iter().skip(known).chain(signature.rest.iter().map(|rest| &**rest)).cloned().collect::<Vec<Type>>();
+
+            for param in left.iter() {
+              self.expect(&ty, param, pos)
+            }
+
+            continue
+          }
+
+          let param = match signature.params.get(i) {
+            Some(param) => param.clone(),
+            None        => match signature.rest {
+              Some(ref rest) => (**rest).clone(),
+              None           => break,
+            },
+          };
+
+          self.expect(&ty, &param, pos)
+        }
+
+        (*signature.ret).clone()
+      },
+
+      function => {
+        let message = format!("can't call {}", self.show(&function));
+
+        self.error(message, called)
+      },
+    }
+  }
+
+  fn infer_binary(&mut self, left: &Expression, op: &Operator, right: &Expression, pos: Pos) -> Type {
+    use self::Operator::*;
+
+    if let PipeR | PipeL = *op {
+      let (function, value) = if *op == PipeR { (right, left) } else { (left, right) };
+
+      let function_ty = self.infer(function);
+      let value_ty    = self.infer(value);
+
+      return self.call(&function_ty, vec!((value_ty, value.pos)), None, function.pos, pos)
+    }
+
+    let left_ty  = self.infer(left);
+    let right_ty = self.infer(right);
+
+    let ty = match *op {
+      Eq | NEq => Some(Type::Bool),
+
+      // either side may be the result, so they only have to agree when they can
+      And | Or => Some(if self.unify(&left_ty, &right_ty).is_ok() { left_ty.clone() } else { Type::Any }),
+
+      // the right side stands in for what the left holds when it's nil
+      Coalesce => {
+        let held = self.held(&left_ty);
+
+        Some(if self.unify(&held, &right_ty).is_ok() { right_ty.clone() } else { Type::Any })
+      },
+
+      Add | Sub | Mul | Div | Mod | Pow => if self.number(&left_ty) && self.number(&right_ty) {
+        let _ = self.unify(&left_ty, &right_ty);
+
+        Some(match (self.resolve(&left_ty), self.resolve(&right_ty)) {
+          _ if *op == Div        => Type::Float,
+          (Type::Int, Type::Int) => Type::Int,
+          (Type::Any, _)         => Type::Any,
+          (_, Type::Any)         => Type::Any,
+          (Type::Float, _)       => Type::Float,
+          (_, Type::Float)       => Type::Float,
+          (ty, _)                => ty,
+        })
+      } else {
+        None
+      },
+
+      Lt | Gt | LtEq | GtEq => match (self.resolve(&left_ty), self.resolve(&right_ty)) {
+        (ref a, ref b) if a.is_number() && b.is_number() => Some(Type::Bool),
+
+        (Type::Str, _) | (_, Type::Str) | (Type::Var(_), _) | (_, Type::Var(_)) | (Type::Any, _) | (_, Type::Any) => {
+          if self.unify(&left_ty, &right_ty).is_ok() { Some(Type::Bool) } else { None }
+        },
+
+        _ => None,
+      },
+
+      Concat => match (self.resolve(&left_ty), self.resolve(&right_ty)) {
+        (Type::Str, _) | (_, Type::Str) => Some(Type::Str),
+
+        (Type::Array(_), _) | (_, Type::Array(_)) => {
+          if self.unify(&left_ty, &right_ty).is_ok() { Some(left_ty.clone()) } else { None }
+        },
+
+        // strings or arrays, it can't be told yet
+        (Type::Any, _) | (_, Type::Any) | (Type::Var(_), _) | (_, Type::Var(_)) => Some(Type::Any),
+
+        _ => None,
+      },
+
+      PipeR | PipeL => unreachable!(),
+    };
+
+    match ty {
+      Some(ty) => ty,
+      None     => {
+        let message = format!("can't use `{}` on {} and {}", op, self.show(&left_ty), self.show(&right_ty));
+
+        self.error(message, pos)
+      },
+    }
+  }
+
+
+
+  // The type an annotation stands for
+  fn annotation(&mut self, annotation: &TypeExpression) -> Type {
+    match annotation.node {
+      TypeNode::Name(ref name) => match name.as_str() {
+        "int"   => Type::Int,
+        "float" => Type::Float,
+        "str"   => Type::Str,
+        "bool"  => Type::Bool,
+        "nil"   => Type::Nil,
+        "any"   => Type::Any,
+
+        name if self.records.contains_key(name) => self.records[name].0.clone(),
+        name if self.enums.contains_key(name)   => Type::Enum(name.to_string()),
+
+        _ => match self.aliases.get(name).cloned() {
+          Some(_) if self.expanding.contains(name) => {
+            let message = format!("type `{}` is defined in terms of itself", name);
+
+            self.error(message, annotation.pos)
+          },
+
+          Some(aliased) => {
+            self.expanding.push(name.clone());
+
+            let ty = self.annotation(&aliased);
+
+            self.expanding.pop();
+
+            ty
+          },
+
+          None => self.error(format!("no such type `{}`", name), annotation.pos),
+        },
+      },
+
+      TypeNode::Array(ref element) => Type::Array(Rc::new(self.annotation(element))),
+
+      TypeNode::Record(ref fields) => Type::Record(
+        fields.iter().map(|(name, ty)| (name.clone(), self.annotation(ty))).collect(),
+        None
+      ),
+
+      TypeNode::Function(ref params, ref ret) => {
+        let params = params.iter().map(|ty| self.annotation(ty)).collect();
+        let ret    = self.annotation(ret);
+
+        Type::function(params, ret)
+      },
+
+      TypeNode::Nullable(ref ty) => Type::Nullable(Rc::new(self.annotation(ty))),
+    }
+  }
+
+
+
+  fn builtin(&mut self, name: &str) -> Option<Type> {
+    let a = self.fresh();
+    let b = self.fresh();
+
+    let number = self.fresh();
+    self.number(&number);
+
+    let array = |t: &Type| Type::Array(Rc::new(t.clone()));
+    let f     = Type::function;
+
+    let ty = match name {
+      "print" => Type::Function(
+        Signature {
+          params:   Vec::new(),
+          required: 0,
+          rest:     Some(Rc::new(Type::Any)),
+          ret:      Rc::new(Type::Nil),
+        }
+      ),
+
+      "len"    => f(vec!(Type::Any), Type::Int),
+      "map"    => f(vec!(array(&a), f(vec!(a.clone()), b.clone())), array(&b)),
+      "filter" => f(vec!(array(&a), f(vec!(a.clone()), b)), array(&a)),
+
+      "reduce" => Type::Function(
+        Signature {
+          params:   vec!(array(&a), f(vec!(a.clone(), a.clone()), a.clone()), a.clone()),
+          required: 2,
+          rest:     None,
+          ret:      Rc::new(a),
+        }
+      ),
+
+      "split" => f(vec!(Type::Str, Type::Str), array(&Type::Str)),
+
+      "join" => Type::Function(
+        Signature {
+          params:   vec!(array(&a), Type::Str),
+          required: 1,
+          rest:     None,
+          ret:      Rc::new(Type::Str),
+        }
+      ),
+
+      "trim"                     => f(vec!(Type::Str), Type::Str),
+      "floor" | "ceil" | "round" => f(vec!(Type::Float), Type::Int),
+      "sqrt"                     => f(vec!(Type::Float), Type::Float),
+      "abs"                      => f(vec!(number.clone()), number),
+
+      "min" | "max" => Type::Function(
+        Signature {
+          params:   Vec::new(),
+          required: 0,
+          rest:     Some(Rc::new(number.clone())),
+          ret:      Rc::new(number),
+        }
+      ),
+
+      "keys" | "values" => {
+        let record = Type::Record(Vec::new(), Some(self.fresh_var()));
+        let result = if name == "keys" { Type::Str } else { Type::Any };
+
+        f(vec!(record), array(&result))
+      },
+
+      "type" => f(vec!(Type::Any), Type::Str),
+
+      _ => return None,
+    };
+
+    Some(ty)
+  }
+
+
+
+  fn fresh_var(&mut self) -> usize {
+    self.bindings.push(None);
+    self.numeric.push(false);
+
+    self.bindings.len() - 1
+  }
+
+  fn fresh(&mut self) -> Type {
+    Type::Var(self.fresh_var())
+  }
+
+  // Whether `ty` can be a number, making it one if it's still open
+  fn number(&mut self, ty: &Type) -> bool {
+    match self.resolve(ty) {
+      Type::Var(var)                      => { self.numeric[var] = true; true },
+      Type::Int | Type::Float | Type::Any => true,
+      _                                   => false,
+    }
+  }
+
+  fn declare(&mut self, name: &str, scheme: Scheme) {
+    self.scopes.last_mut().unwrap().names.insert(name.to_string(), scheme);
+  }
+
+  fn lookup(&self, name: &str) -> Option<Scheme> {
+    self.scopes.iter().rev().filter_map(|scope| scope.names.get(name)).next().cloned()
+  }
+
+  // Generic over the variables nothing else in scope refers to
+  fn generalize(&self, ty: &Type) -> Scheme {
+    let ty = self.zonk(ty);
+
+    let mut bound = Vec::new();
+
+    for scope in self.scopes.iter() {
+      bound.extend(self.zonk(&scope.ret).vars());
+
+      for scheme in scope.names.values() {
+        bound.extend(self.zonk(&scheme.ty).vars())
+      }
+    }
+
+    let vars = ty.vars().into_iter().filter(|var| !bound.contains(var)).collect();
+
+    Scheme {
+      vars,
+      ty,
+    }
+  }
+
+  fn instantiate(&mut self, scheme: &Scheme) -> Type {
+    let mut map = HashMap::new();
+
+    for &var in scheme.vars.iter() {
+      let fresh = self.fresh_var();
+
+      self.numeric[fresh] = self.numeric[var];
+
+      map.insert(var, fresh);
+    }
+
+    self.zonk(&scheme.ty).substitute(&map)
+  }
+
+
+
+  // Follows bound variables at the top of `ty`, merging the fields of records
+  fn resolve(&self, ty: &Type) -> Type {
+    match *ty {
+      Type::Var(var) => match self.bindings[var] {
+        Some(ref bound) => self.resolve(bound),
+        None            => ty.clone(),
+      },
+
+      Type::Record(ref fields, rest) => {
+        let mut fields = fields.clone();
+        let mut rest   = rest;
+
+        while let Some(var) = rest {
+          match self.bindings[var] {
+            Some(Type::Record(ref more, more_rest)) => {
+              fields.extend(more.iter().cloned());
+              rest = more_rest
+            },
+
+            Some(Type::Var(other)) => rest = Some(other),
+            _                      => break,
+          }
+        }
+
+        Type::Record(fields, rest)
+      },
+
+      _ => ty.clone(),
+    }
+  }
+
+  // `ty` with every bound variable replaced
+  fn zonk(&self, ty: &Type) -> Type {
+    match self.resolve(ty) {
+      Type::Array(element) => Type::Array(Rc::new(self.zonk(&element))),
+
+      Type::Record(fields, rest) => Type::Record(
+        fields.iter().map(|(name, ty)| (name.clone(), self.zonk(ty))).collect(),
+        rest
+      ),
+
+      Type::Function(signature) => Type::Function(
+        Signature {
+          params:   signature.params.iter().map(|ty| self.zonk(ty)).collect(),
+          required: signature.required,
+          rest:     signature.rest.as_ref().map(|ty| Rc::new(self.zonk(ty))),
+          ret:      Rc::new(self.zonk(&signature.ret)),
+        }
+      ),
+
+      Type::Nullable(ty) => Type::Nullable(Rc::new(self.zonk(&ty))),
+
+      ty => ty,
+    }
+  }
+
+  fn show(&self, ty: &Type) -> String {
+    self.describe(ty, &Type::rename(&[&self.zonk(ty)])[0])
+  }
+
+  // Open variables used in arithmetic read better as what they must become
+  fn describe(&self, ty: &Type, renamed: &Type) -> String {
+    match self.resolve(ty) {
+      Type::Var(var) if self.numeric[var] => String::from("a number"),
+      _                                   => format!("{}", renamed),
+    }
+  }
+
+  // Unifies `found` with `expected`, reporting at `pos` when they can't be
+  fn expect(&mut self, found: &Type, expected: &Type, pos: Pos) {
+    if self.unify(found, expected).is_err() {
+      let renamed = Type::rename(&[&self.zonk(expected), &self.zonk(found)]);
+      let message = format!("expected {}, found {}", self.describe(expected, &renamed[0]), self.describe(found, &renamed[1]));
+
+      self.error(message, pos);
+    }
+  }
+
+  // Leaves the bindings as they were when failing
+  fn unify(&mut self, a: &Type, b: &Type) -> Result<(), ()> {
+    let bindings = self.bindings.clone();
+    let numeric  = self.numeric.clone();
+
+    let result = self.unify_types(a, b);
+
+    if result.is_err() {
+      self.bindings = bindings;
+      self.numeric  = numeric;
+    }
+
+    result
+  }
+
+  fn unify_types(&mut self, a: &Type, b: &Type) -> Result<(), ()> {
+    match (self.resolve(a), self.resolve(b)) {
+      (Type::Any, _) | (_, Type::Any) => Ok(()),
+
+      (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+
+      (Type::Var(var), ty) | (ty, Type::Var(var)) => self.bind(var, ty),
+
+      // ints are used as floats freely, like in JS
+      (ref a, ref b) if a.is_number() && b.is_number() => Ok(()),
+
+      (Type::Str, Type::Str) | (Type::Bool, Type::Bool) | (Type::Nil, Type::Nil) => Ok(()),
+
+      (Type::Enum(ref a), Type::Enum(ref b)) if a == b => Ok(()),
+
+      (Type::Array(a), Type::Array(b)) => self.unify_types(&a, &b),
+
+      (Type::Nullable(a), Type::Nullable(b)) => self.unify_types(&a, &b),
+
+      // `a` is what was found and `b` what it goes into, `T` goes into `T?` but not the other way around
+      (Type::Nil, Type::Nullable(_)) => Ok(()),
+
+      (a, Type::Nullable(b)) => self.unify_types(&a, &b),
+
+      // extra arguments are ignored and missing ones are nil, so only the common ones have to agree.
+      // what's passed goes the other way, a function taking `T?` can be used as one taking `T`
+      (Type::Function(a), Type::Function(b)) => {
+        for (a, b) in a.params.iter().zip(b.params.iter()) {
+          self.unify_types(b, a)?
+        }
+
+        if let (Some(a), Some(b)) = (a.rest, b.rest) {
+          self.unify_types(&b, &a)?
+        }
+
+        self.unify_types(&a.ret, &b.ret)
+      },
+
+      (Type::Record(a, a_rest), Type::Record(b, b_rest)) => {
+        let mut only_a = Vec::new();
+
+        for (name, ty) in a.iter() {
+          match b.iter().find(|&(other, _)| other == name) {
+            Some((_, other)) => self.unify_types(ty, other)?,
+            None             => only_a.push((name.clone(), ty.clone())),
+          }
+        }
+
+        let only_b = b.into_iter().filter(|(name, _)| a.iter().all(|(other, _)| other != name)).collect::<Vec<(String, Type)>>();
+
+        match (a_rest, b_rest) {
+          (Some(a_rest), Some(b_rest)) => {
+            if a_rest == b_rest {
+              return if only_a.is_empty() && only_b.is_empty() { Ok(()) } else { Err(()) }
+            }
+
+            let rest = self.fresh_var();
+
+            self.bind(a_rest, Type::Record(only_b, Some(rest)))?;
+            self.bind(b_rest, Type::Record(only_a, Some(rest)))
+          },
+
+          (Some(a_rest), None) if only_a.is_empty() => self.bind(a_rest, Type::Record(only_b, None)),
+          (None, Some(b_rest)) if only_b.is_empty() => self.bind(b_rest, Type::Record(only_a, None)),
+          (None, None) if only_a.is_empty() && only_b.is_empty() => Ok(()),
+
+          _ => Err(()),
+        }
+      },
+
+      _ => Err(()),
+    }
+  }
+
+  fn bind(&mut self, var: usize, ty: Type) -> Result<(), ()> {
+    if let Type::Var(other) = ty {
+      if self.numeric[var] {
+        self.numeric[other] = true
+      }
+    } else if self.numeric[var] && !ty.is_number() {
+      return Err(())
+    }
+
+    // no infinite types
+    if self.zonk(&ty).vars().contains(&var) {
+      return Err(())
+    }
+
+    self.bindings[var] = Some(ty);
+
+    Ok(())
+  }
+
+  // `Any` keeps the error from causing more of them
+  fn error(&mut self, message: String, pos: Pos) -> Type {
+    self.failed = true;
+
+    response!(Wrong(message), self.source.file, self.source.snippet(&pos));
+
+    Type::Any
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn messages(code: &str) -> Vec<String> {
+    let source = Source::from("<test>", code.to_string());
+    let ast    = crate::parse_str("<test>", code).result.expect("it should parse");
+
+    let (_, diagnostics) = crate::øl::error::capture(|| Checker::new(&source).check(&ast));
+
+    diagnostics.into_iter().map(|diagnostic| diagnostic.message).collect()
+  }
+
+  #[test]
+  fn reports_mismatches() {
+    assert_eq!(messages("øl x = \"a\" - 1\n").len(), 1);
+    assert_eq!(messages("øl f = 1\nf(2)\n").len(), 1)
+  }
+
+  #[test]
+  fn generalizes_functions() {
+    assert_eq!(messages("øl id(x) =\n  øl x\nøl a = id(1) + 1\nøl b = id(\"s\") ++ \"t\"\n"), Vec::<String>::new())
+  }
+
+  #[test]
+  fn reports_unknown_names() {
+    assert_eq!(messages("øl x = y + 1\n"), vec!("no such value `y`"));
+    assert_eq!(messages("z = 2\n"), vec!("no such value `z`"))
+  }
+
+  #[test]
+  fn knows_the_prelude_and_functions_defined_later() {
+    assert_eq!(messages("øl a = f(1)\nøl f(x) =\n  øl x\nprint(len(\"øl\"), a)\n"), Vec::<String>::new())
+  }
+
+  #[test]
+  fn reports_writes_to_missing_fields() {
+    assert_eq!(messages("øl p = { x: 1 }\np.y = 2\n"), vec!("{ x: int } has no field `y`"));
+    assert_eq!(messages("øl p = { x: 1 }\np.x = 2\n"), Vec::<String>::new());
+    assert_eq!(messages("øl p = { x: 1 }\np.x = \"two\"\n").len(), 1)
+  }
+
+  #[test]
+  fn lets_values_into_nullables_only() {
+    assert_eq!(messages("øl f(a: int?): int? =\n  øl a\nf(1)\nf(nil)\n"), Vec::<String>::new());
+    assert_eq!(messages("øl f(a: int?): int =\n  øl a\n"), vec!("expected int, found int?"));
+    assert_eq!(messages("øl x: int = nil\n").len(), 1)
+  }
+}
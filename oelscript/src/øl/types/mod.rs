@@ -0,0 +1,10 @@
+#[allow(clippy::module_inception)]
+pub mod types;
+pub mod checker;
+
+use super::parser::*;
+use super::source::*;
+use super::lexer::*;
+
+pub use self::types::*;
+pub use self::checker::*;
@@ -0,0 +1,185 @@
+use std::rc::Rc;
+use std::fmt;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+  Int,
+  Float,
+  Str,
+  Bool,
+  Nil,
+  Any, // goes along with everything, for what can't be known statically
+  Var(usize),
+  Array(Rc<Type>),
+  Record(Vec<(String, Type)>, Option<usize>), // Option: variable standing for the fields not known yet
+  Function(Signature),
+  Nullable(Rc<Type>),
+  Enum(String), // told apart by name, unlike records
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+  pub params:   Vec<Type>,
+  pub required: usize, // the params after these may be left out
+  pub rest:     Option<Rc<Type>>,
+  pub ret:      Rc<Type>,
+}
+
+impl Type {
+  pub fn function(params: Vec<Type>, ret: Type) -> Self {
+    Type::Function(
+      Signature {
+        required: params.len(),
+        params,
+        rest:     None,
+        ret:      Rc::new(ret),
+      }
+    )
+  }
+
+  pub fn is_number(&self) -> bool {
+    matches!(*self, Type::Int | Type::Float)
+  }
+
+  // Every variable in `self`, in the order they appear
+  pub fn vars(&self) -> Vec<usize> {
+    let mut vars = Vec::new();
+
+    self.collect_vars(&mut vars);
+
+    vars
+  }
+
+  fn collect_vars(&self, vars: &mut Vec<usize>) {
+    let add = |var: usize, vars: &mut Vec<usize>| if !vars.contains(&var) {
+      vars.push(var)
+    };
+
+    match *self {
+      Type::Var(var)        => add(var, vars),
+      Type::Array(ref t)    => t.collect_vars(vars),
+      Type::Nullable(ref t) => t.collect_vars(vars),
+
+      Type::Record(ref fields, rest) => {
+        for (_, t) in fields.iter() {
+          t.collect_vars(vars)
+        }
+
+        if let Some(rest) = rest {
+          add(rest, vars)
+        }
+      },
+
+      Type::Function(ref signature) => {
+        for t in signature.params.iter() {
+          t.collect_vars(vars)
+        }
+
+        if let Some(ref rest) = signature.rest {
+          rest.collect_vars(vars)
+        }
+
+        signature.ret.collect_vars(vars)
+      },
+
+      _ => (),
+    }
+  }
+
+  // Replaces variables, `self` should have its bound variables resolved already
+  pub fn substitute(&self, map: &HashMap<usize, usize>) -> Type {
+    let var = |var: usize| *map.get(&var).unwrap_or(&var);
+
+    match *self {
+      Type::Var(v)          => Type::Var(var(v)),
+      Type::Array(ref t)    => Type::Array(Rc::new(t.substitute(map))),
+      Type::Nullable(ref t) => Type::Nullable(Rc::new(t.substitute(map))),
+
+      Type::Record(ref fields, rest) => Type::Record(
+        fields.iter().map(|(name, t)| (name.clone(), t.substitute(map))).collect(),
+        rest.map(var)
+      ),
+
+      Type::Function(ref signature) => Type::Function(
+        Signature {
+          params:   signature.params.iter().map(|t| t.substitute(map)).collect(),
+          required: signature.required,
+          rest:     signature.rest.as_ref().map(|t| Rc::new(t.substitute(map))),
+          ret:      Rc::new(signature.ret.substitute(map)),
+        }
+      ),
+
+      ref t => t.clone(),
+    }
+  }
+
+  // Numbers the variables of `types` from 0 so they read as `a`, `b` and so on
+  pub fn rename(types: &[&Type]) -> Vec<Type> {
+    let mut map = HashMap::new();
+
+    for t in types.iter() {
+      for var in t.vars() {
+        let next = map.len();
+
+        map.entry(var).or_insert(next);
+      }
+    }
+
+    types.iter().map(|t| t.substitute(&map)).collect()
+  }
+}
+
+impl fmt::Display for Type {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Type::Int   => write!(f, "int"),
+      Type::Float => write!(f, "float"),
+      Type::Str   => write!(f, "str"),
+      Type::Bool  => write!(f, "bool"),
+      Type::Nil   => write!(f, "nil"),
+      Type::Any   => write!(f, "any"),
+
+      Type::Var(var) => if var < 26 {
+        write!(f, "{}", (b'a' + var as u8) as char)
+      } else {
+        write!(f, "t{}", var)
+      },
+
+      Type::Array(ref t) => write!(f, "[{}]", t),
+
+      Type::Record(ref fields, rest) => {
+        let mut entries = fields.iter().map(|(name, t)| format!("{}: {}", name, t)).collect::<Vec<String>>();
+
+        if rest.is_some() {
+          entries.push(String::from(".."))
+        }
+
+        if entries.is_empty() {
+          write!(f, "{{}}")
+        } else {
+          write!(f, "{{ {} }}", entries.join(", "))
+        }
+      },
+
+      Type::Function(ref signature) => {
+        let mut params = signature.params.iter().enumerate().map(|(i, t)| {
+          if i < signature.required { format!("{}", t) } else { format!("{}?", t) }
+        }).collect::<Vec<String>>();
+
+        if let Some(ref rest) = signature.rest {
+          params.push(format!("...{}", rest))
+        }
+
+        write!(f, "({}) -> {}", params.join(", "), signature.ret)
+      },
+
+      Type::Nullable(ref t) => match **t {
+        Type::Function(_) => write!(f, "({})?", t),
+        _                 => write!(f, "{}?", t),
+      },
+
+      Type::Enum(ref name) => write!(f, "{}", name),
+    }
+  }
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+  Constant(u16),     // push constant
+  Nil,
+  True,
+  False,
+  Pop,
+  Dup2,              // [a, b] -> [a, b, a, b]
+
+  GetLocal(u8),      // slots are relative to the frame, parameters come first
+  SetLocal(u8),      // assignments leave their value on the stack
+  GetUpvalue(u8),
+  SetUpvalue(u8),
+  GetGlobal(u16),    // operands of globals are name constants
+  SetGlobal(u16),
+  DefineGlobal(u16), // pops the value
+
+  Array(u16),        // pops its elements
+  Table(u16),        // pops key and value pairs
+  Spread,            // [array or table, value] -> it with the elements or entries of `value` added
+  GetIndex,          // [indexed, index] -> value
+  SetIndex,          // [indexed, index, value] -> value
+  Method,            // [receiver, index] -> [function, receiver]
+  Instance,          // makes a record of the arguments of the running constructor
+  Construct,         // [constructor, table] -> result, passing the fields of the table by name
+  Field(u8),         // [table] -> value of entry `n`, in the order they were added
+  Range,             // [start, end] -> array of the numbers from `start` up to `end`, leaving it out
+  Slice,             // [indexed, start, end] -> copy of the part between them like JS `slice`, nil ends are the ends of it
+
+  Neg,
+  Not,
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Mod,
+  Pow,
+  Concat,
+  Eq,
+  NEq,
+  Lt,
+  Gt,
+  LtEq,
+  GtEq,
+
+  Jump(usize),        // absolute instruction index
+  JumpIfFalse(usize), // leaves the condition on the stack
+  JumpIfNil(usize),   // leaves the value on the stack
+
+  Try(usize),         // errors until `EndTry` go to the target, with what was raised on the stack
+  Finally(usize),     // errors until `EndTry` go to the target, which ends with `Rethrow`
+  EndTry,
+  Throw,              // [value] -> raises it
+  Rethrow,            // raises the error the innermost `Finally` caught again
+
+  Iterate,            // [iterable] -> generator, arrays and strings get one stepping through their elements
+  Next,               // [generator] -> [value, whether there was one], resuming a generator function
+  Yield,              // [value] -> suspends the generator function, which is resumed with nil on the stack
+
+  Call(u8),          // [function, args ..] -> result
+  Apply,             // [function, array of args] -> result
+  Closure(u16),      // wraps function prototype `n` of the chunk
+  Return,
+}
+
+// Compiled code of a single function
+#[derive(Debug, Default)]
+pub struct Chunk {
+  pub code:      Vec<Instruction>,
+  pub constants: Vec<Value>,
+  pub functions: Vec<Rc<Prototype>>,
+  pub lines:     Vec<usize>, // source line of every instruction
+  pub spans:     Vec<Pos>,   // and the range it came from, for errors
+  pub source:    Option<Rc<Source>>, // what it was compiled from, None only for chunks made by hand
+}
+
+impl Chunk {
+  pub fn write(&mut self, instruction: Instruction, line: usize, pos: Pos) -> usize {
+    self.code.push(instruction);
+    self.lines.push(line);
+    self.spans.push(pos);
+
+    self.code.len() - 1
+  }
+}
+
+// A function as compiled, closures are made from it at runtime
+#[derive(Debug, Default)]
+pub struct Prototype {
+  pub name:      String,
+  pub params:    Vec<String>,
+  pub locals:    Vec<String>,     // names of every slot, the parameters come first
+  pub upvalues:  Vec<(bool, u8)>, // captured from the enclosing function: (is a local slot of it, index)
+  pub record:    bool,            // the constructor of a record, its params being the fields
+  pub generator: bool,            // calling it makes a generator instead of running it
+  pub rest:      bool,            // the last param gathers the arguments left into an array
+  pub chunk:     Chunk,
+}
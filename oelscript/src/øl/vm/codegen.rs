@@ -0,0 +1,1427 @@
+use super::*;
+use super::super::error::Response::Wrong;
+
+use std::mem;
+use std::rc::Rc;
+
+// A function being compiled, locals are scoped to the whole function like JS `var`
+struct FunctionState {
+  prototype: Prototype,
+  locals:    Vec<String>,
+  handlers:  Vec<Option<Vec<Statement>>>, // the `prøv` blocks compiling, with their `endelig` bodies
+  loops:     Vec<Loop>,
+  chain:     Vec<usize>, // jumps of the `?.`s in the chain of indexes and calls compiling, to past the end of it
+}
+
+// A loop being compiled, its jumps are patched once where they go is known
+struct Loop {
+  breaks:   Vec<usize>,
+  skips:    Vec<usize>,
+  handlers: usize, // how many `prøv` blocks it's in
+}
+
+// Compiles an AST into bytecode, the top level becomes a function of its own
+pub struct CodeGen {
+  pub source: Rc<Source>, // shared with every chunk compiled, errors at runtime point into it
+  functions:  Vec<FunctionState>,
+}
+
+impl CodeGen {
+  pub fn new(source: &Source) -> Self {
+    CodeGen {
+      source:    Rc::new(source.clone()),
+      functions: Vec::new(),
+    }
+  }
+
+  // The result returns the value of a trailing expression statement
+  pub fn compile(&mut self, ast: &[Statement]) -> Result<Rc<Prototype>, ()> {
+    self.begin_function("<script>", &[]);
+
+    for (i, statement) in ast.iter().enumerate() {
+      match statement.node {
+        StatementNode::Expression(ref expression) if i == ast.len() - 1 => {
+          self.compile_expression(expression)?;
+          self.emit(Instruction::Return, statement.pos);
+
+          return Ok(Rc::new(self.end_function()))
+        },
+
+        _ => self.compile_statement(statement)?,
+      }
+    }
+
+    let end = Pos(self.source.content.len(), self.source.content.len());
+
+    self.emit(Instruction::Nil, end);
+    self.emit(Instruction::Return, end);
+
+    Ok(Rc::new(self.end_function()))
+  }
+
+
+
+  fn compile_statement(&mut self, statement: &Statement) -> Result<(), ()> {
+    use self::StatementNode::*;
+
+    let pos = statement.pos;
+
+    match statement.node {
+      Expression(ref expression) => {
+        self.compile_expression(expression)?;
+        self.emit(Instruction::Pop, pos);
+      },
+
+      Variable(ref name, _, ref right, _) => {
+        self.compile_expression(right)?;
+
+        if self.is_top_level() {
+          let name = self.name(name, pos)?;
+
+          self.emit(Instruction::DefineGlobal(name), pos);
+        } else {
+          let slot = self.declare_local(name, pos)?;
+
+          self.emit(Instruction::SetLocal(slot), pos);
+          self.emit(Instruction::Pop, pos);
+        }
+      },
+
+      Assignment(ref left, ref right) => {
+        self.compile_assignment(left, right)?;
+        self.emit(Instruction::Pop, pos);
+      },
+
+      Compound(ref left, ref op, ref right) => {
+        self.compile_compound(left, op, right, pos)?;
+        self.emit(Instruction::Pop, pos);
+      },
+
+      // there's no event loop to wait on
+      Function(_, _, _, _, true, _) => return Err(response!(Wrong("`asynk` functions have no event loop to wait on here, they only run as JavaScript"), self.source.file, self.source.snippet(&pos))),
+
+      Function(ref name, ref params, _, ref body, _, is_generator) => match name.node {
+        ExpressionNode::Identifier(ref id) => if self.is_top_level() {
+          self.compile_function(id, params, body, is_generator, pos)?;
+
+          let name = self.name(id, name.pos)?;
+
+          self.emit(Instruction::DefineGlobal(name), pos);
+        } else {
+          // declared first so the body can call itself
+          let slot = self.declare_local(id, name.pos)?;
+
+          self.compile_function(id, params, body, is_generator, pos)?;
+
+          self.emit(Instruction::SetLocal(slot), pos);
+          self.emit(Instruction::Pop, pos);
+        },
+
+        ExpressionNode::Index(ref indexed, ref index, _) => {
+          let function_name = match index.node {
+            ExpressionNode::Str(ref name) => name.clone(),
+            _                             => String::from("anonymous"),
+          };
+
+          self.compile_expression(indexed)?;
+          self.compile_expression(index)?;
+          self.compile_function(&function_name, params, body, is_generator, pos)?;
+
+          self.emit(Instruction::SetIndex, pos);
+          self.emit(Instruction::Pop, pos);
+        },
+
+        _ => return Err(response!(Wrong("can't define a function here"), self.source.file, self.source.snippet(&name.pos))),
+      },
+
+      If(ref condition, ref body, ref else_branch) => {
+        self.compile_expression(condition)?;
+
+        let to_else = self.emit(Instruction::JumpIfFalse(0), pos);
+        self.emit(Instruction::Pop, pos);
+
+        for statement in body.iter() {
+          self.compile_statement(statement)?
+        }
+
+        let to_end = self.emit(Instruction::Jump(0), pos);
+
+        self.patch(to_else);
+        self.emit(Instruction::Pop, pos);
+
+        if let Some((ref body, _)) = *else_branch {
+          for statement in body.iter() {
+            self.compile_statement(statement)?
+          }
+        }
+
+        self.patch(to_end)
+      },
+
+      Return(ref value) => {
+        if self.is_top_level() {
+          return Err(response!(Wrong("can't return from the top level"), self.source.file, self.source.snippet(&pos)))
+        }
+
+        match *value {
+          Some(ref value) => self.compile_expression(value)?,
+          None            => { self.emit(Instruction::Nil, pos); },
+        }
+
+        // the `endelig` bodies it's leaving run first, the value waits on the stack
+        self.leave_handlers(0, pos)?;
+
+        self.emit(Instruction::Return, pos);
+      },
+
+      For(ref name, ref iterable, ref body) => self.compile_for(name, iterable, body, pos)?,
+
+      Break | Skip => {
+        let handlers = match self.current().loops.last() {
+          Some(looping) => looping.handlers,
+          None          => {
+            let message = if statement.node == Break { "`break` outside of a loop" } else { "`skip` outside of a loop" };
+
+            return Err(response!(Wrong(message), self.source.file, self.source.snippet(&pos)))
+          },
+        };
+
+        self.leave_handlers(handlers, pos)?;
+
+        let jump    = self.emit(Instruction::Jump(0), pos);
+        let looping = self.current().loops.last_mut().unwrap();
+
+        if statement.node == Break {
+          looping.breaks.push(jump)
+        } else {
+          looping.skips.push(jump)
+        }
+      },
+
+      Record(ref name, ref fields) => if self.is_top_level() {
+        self.compile_record(name, fields, pos)?;
+
+        let name = self.name(name, pos)?;
+
+        self.emit(Instruction::DefineGlobal(name), pos);
+      } else {
+        let slot = self.declare_local(name, pos)?;
+
+        self.compile_record(name, fields, pos)?;
+
+        self.emit(Instruction::SetLocal(slot), pos);
+        self.emit(Instruction::Pop, pos);
+      },
+
+      Enum(ref name, ref variants) => if self.is_top_level() {
+        self.compile_enum(variants, pos)?;
+
+        let name = self.name(name, pos)?;
+
+        self.emit(Instruction::DefineGlobal(name), pos);
+      } else {
+        let slot = self.declare_local(name, pos)?;
+
+        self.compile_enum(variants, pos)?;
+
+        self.emit(Instruction::SetLocal(slot), pos);
+        self.emit(Instruction::Pop, pos);
+      },
+
+      Match(ref subject, ref arms) => self.compile_match(subject, arms, pos)?,
+
+      Try(ref body, ref catch, ref finally) => self.compile_try(body, catch, finally, pos)?,
+
+      Throw(ref value) => {
+        self.compile_expression(value)?;
+
+        self.emit(Instruction::Throw, pos);
+      },
+
+      TypeAlias(..) => (),
+
+      Import(_) => return Err(response!(Wrong("imports can't be compiled to bytecode yet"), self.source.file, self.source.snippet(&pos))),
+    }
+
+    Ok(())
+  }
+
+  // Leaves the assigned value on the stack
+  fn compile_assignment(&mut self, left: &Expression, right: &Expression) -> Result<(), ()> {
+    match left.node {
+      ExpressionNode::Identifier(ref name) => {
+        self.compile_expression(right)?;
+
+        let instruction = match self.resolve(name, left.pos)? {
+          Resolved::Local(slot)   => Instruction::SetLocal(slot),
+          Resolved::Upvalue(slot) => Instruction::SetUpvalue(slot),
+          Resolved::Global(name)  => Instruction::SetGlobal(name),
+        };
+
+        self.emit(instruction, left.pos);
+      },
+
+      ExpressionNode::Index(ref indexed, ref index, _) => {
+        self.compile_expression(indexed)?;
+        self.compile_expression(index)?;
+        self.compile_expression(right)?;
+
+        self.emit(Instruction::SetIndex, left.pos);
+      },
+
+      _ => return Err(response!(Wrong("can't assign to this"), self.source.file, self.source.snippet(&left.pos))),
+    }
+
+    Ok(())
+  }
+
+  // Like an assignment of `left op right`, but what's indexed and the index are kept on the stack to set it with
+  fn compile_compound(&mut self, left: &Expression, op: &Operator, right: &Expression, pos: Pos) -> Result<(), ()> {
+    match left.node {
+      ExpressionNode::Identifier(_) => {
+        let value = Expression::new(
+          ExpressionNode::Binary(Rc::new(left.clone()), op.clone(), Rc::new(right.clone())),
+          pos
+        );
+
+        self.compile_assignment(left, &value)
+      },
+
+      ExpressionNode::Index(ref indexed, ref index, _) => {
+        self.compile_expression(indexed)?;
+        self.compile_expression(index)?;
+
+        self.emit(Instruction::Dup2, left.pos);
+        self.emit(Instruction::GetIndex, left.pos);
+
+        self.compile_expression(right)?;
+
+        let instruction = Self::operation(op);
+
+        self.emit(instruction, pos);
+        self.emit(Instruction::SetIndex, left.pos);
+
+        Ok(())
+      },
+
+      _ => Err(response!(Wrong("can't assign to this"), self.source.file, self.source.snippet(&left.pos))),
+    }
+  }
+
+  fn compile_function(&mut self, name: &str, params: &[Param], body: &[Statement], is_generator: bool, pos: Pos) -> Result<(), ()> {
+    // params are the first locals
+    if params.len() > u8::MAX as usize {
+      return Err(response!(Wrong("too many params in one function"), self.source.file, self.source.snippet(&pos)))
+    }
+
+    let names = params.iter().map(|param| param.name.clone()).collect::<Vec<String>>();
+
+    self.begin_function(name, &names);
+    self.current().prototype.generator = is_generator;
+    self.current().prototype.rest      = params.last().is_some_and(|param| param.rest);
+
+    self.compile_defaults(params)?;
+
+    for statement in body.iter() {
+      self.compile_statement(statement)?
+    }
+
+    let end = Pos(pos.1, pos.1);
+
+    self.emit(Instruction::Nil, end);
+    self.emit(Instruction::Return, end);
+
+    self.end_closure(pos)
+  }
+
+  // The constructor of a record, a function taking the fields and filling in the defaults of nil ones
+  fn compile_record(&mut self, name: &str, fields: &[Param], pos: Pos) -> Result<(), ()> {
+    if fields.len() > u8::MAX as usize {
+      return Err(response!(Wrong("too many fields in one record"), self.source.file, self.source.snippet(&pos)))
+    }
+
+    let params = fields.iter().map(|field| field.name.clone()).collect::<Vec<String>>();
+
+    self.begin_function(name, &params);
+    self.current().prototype.record = true;
+
+    self.compile_defaults(fields)?;
+
+    self.emit(Instruction::Instance, pos);
+    self.emit(Instruction::Return, pos);
+
+    self.end_closure(pos)
+  }
+
+  // Params that are nil get their defaults, in order so a default can use the params before it
+  fn compile_defaults(&mut self, params: &[Param]) -> Result<(), ()> {
+    for (slot, param) in params.iter().enumerate() {
+      if let Some(ref default) = param.default {
+        self.emit(Instruction::GetLocal(slot as u8), param.pos);
+        self.emit(Instruction::Nil, param.pos);
+        self.emit(Instruction::Eq, param.pos);
+
+        let to_end = self.emit(Instruction::JumpIfFalse(0), param.pos);
+        self.emit(Instruction::Pop, param.pos);
+
+        self.compile_expression(default)?;
+
+        self.emit(Instruction::SetLocal(slot as u8), param.pos);
+        self.emit(Instruction::Pop, param.pos);
+
+        let to_next = self.emit(Instruction::Jump(0), param.pos);
+
+        self.patch(to_end);
+        self.emit(Instruction::Pop, param.pos);
+
+        self.patch(to_next)
+      }
+    }
+
+    Ok(())
+  }
+
+  // A table of the constructors of the variants, and of the variants without fields themselves
+  fn compile_enum(&mut self, variants: &[Variant], pos: Pos) -> Result<(), ()> {
+    for variant in variants.iter() {
+      self.emit_constant(Value::Str(variant.name.as_str().into()), variant.pos)?;
+
+      if variant.fields.is_empty() {
+        self.emit_constant(Value::Str(TAG.into()), variant.pos)?;
+        self.emit_constant(Value::Str(variant.name.as_str().into()), variant.pos)?;
+        self.emit(Instruction::Table(1), variant.pos);
+
+        continue
+      }
+
+      if variant.fields.len() > u8::MAX as usize - 1 {
+        return Err(response!(Wrong("too many fields in one variant"), self.source.file, self.source.snippet(&variant.pos)))
+      }
+
+      let params = variant.fields.iter().map(|field| field.name.clone()).collect::<Vec<String>>();
+
+      self.begin_function(&variant.name, &params);
+
+      self.emit_constant(Value::Str(TAG.into()), variant.pos)?;
+      self.emit_constant(Value::Str(variant.name.as_str().into()), variant.pos)?;
+
+      for (slot, field) in variant.fields.iter().enumerate() {
+        self.emit_constant(Value::Str(field.name.as_str().into()), field.pos)?;
+        self.emit(Instruction::GetLocal(slot as u8), field.pos);
+      }
+
+      self.emit(Instruction::Table(params.len() as u16 + 1), variant.pos);
+      self.emit(Instruction::Return, variant.pos);
+
+      self.end_closure(variant.pos)?
+    }
+
+    let len = self.count(variants.len(), "variants in an enum", pos)?;
+
+    self.emit(Instruction::Table(len), pos);
+
+    Ok(())
+  }
+
+  // Tries the arms in order, the fields of a variant come after its tag
+  fn compile_match(&mut self, subject: &Expression, arms: &[Arm], pos: Pos) -> Result<(), ()> {
+    // a slot no name can refer to keeps the subject around
+    let slot = self.declare_local(&format!("<match {}>", pos.0), pos)?;
+
+    self.compile_expression(subject)?;
+
+    self.emit(Instruction::SetLocal(slot), pos);
+    self.emit(Instruction::Pop, pos);
+
+    let mut to_end = Vec::new();
+
+    for arm in arms.iter() {
+      let to_next = match arm.pattern {
+        Pattern::Variant(_, ref name, ref bindings) => {
+          self.emit(Instruction::GetLocal(slot), arm.pos);
+          self.emit_constant(Value::Str(TAG.into()), arm.pos)?;
+          self.emit(Instruction::GetIndex, arm.pos);
+          self.emit_constant(Value::Str(name.as_str().into()), arm.pos)?;
+          self.emit(Instruction::Eq, arm.pos);
+
+          let to_next = self.emit(Instruction::JumpIfFalse(0), arm.pos);
+          self.emit(Instruction::Pop, arm.pos);
+
+          if bindings.len() > u8::MAX as usize - 1 {
+            return Err(response!(Wrong("too many fields in one pattern"), self.source.file, self.source.snippet(&arm.pos)))
+          }
+
+          for (i, &(ref binding, binding_pos)) in bindings.iter().enumerate() {
+            let binding = self.declare_local(binding, binding_pos)?;
+
+            self.emit(Instruction::GetLocal(slot), binding_pos);
+            self.emit(Instruction::Field(i as u8 + 1), binding_pos);
+            self.emit(Instruction::SetLocal(binding), binding_pos);
+            self.emit(Instruction::Pop, binding_pos);
+          }
+
+          Some(to_next)
+        },
+
+        Pattern::Wildcard => None,
+      };
+
+      for statement in arm.body.iter() {
+        self.compile_statement(statement)?
+      }
+
+      // `_` is the last arm, though nothing should run after it either way
+      to_end.push(self.emit(Instruction::Jump(0), arm.pos));
+
+      if let Some(to_next) = to_next {
+        self.patch(to_next);
+        self.emit(Instruction::Pop, arm.pos);
+      }
+    }
+
+    for jump in to_end {
+      self.patch(jump)
+    }
+
+    Ok(())
+  }
+
+  // An `endelig` body is compiled twice, for when nothing was raised and for raising again after it
+  fn compile_try(&mut self, body: &[Statement], catch: &Option<Catch>, finally: &Option<(Vec<Statement>, Pos)>, pos: Pos) -> Result<(), ()> {
+    let to_finally = match *finally {
+      Some((ref body, finally_pos)) => {
+        self.current().handlers.push(Some(body.clone()));
+
+        Some(self.emit(Instruction::Finally(0), finally_pos))
+      },
+
+      None => None,
+    };
+
+    match *catch {
+      Some(ref catch) => {
+        self.current().handlers.push(None);
+
+        let to_catch = self.emit(Instruction::Try(0), pos);
+
+        for statement in body.iter() {
+          self.compile_statement(statement)?
+        }
+
+        self.current().handlers.pop();
+
+        self.emit(Instruction::EndTry, pos);
+
+        let to_end = self.emit(Instruction::Jump(0), pos);
+
+        // what was raised is on the stack
+        self.patch(to_catch);
+
+        if let Some(ref name) = catch.name {
+          let slot = self.declare_local(name, catch.pos)?;
+
+          self.emit(Instruction::SetLocal(slot), catch.pos);
+        }
+
+        self.emit(Instruction::Pop, catch.pos);
+
+        for statement in catch.body.iter() {
+          self.compile_statement(statement)?
+        }
+
+        self.patch(to_end)
+      },
+
+      None => for statement in body.iter() {
+        self.compile_statement(statement)?
+      },
+    }
+
+    if let (&Some((ref body, finally_pos)), Some(to_finally)) = (finally, to_finally) {
+      self.current().handlers.pop();
+
+      self.emit(Instruction::EndTry, finally_pos);
+
+      for statement in body.iter() {
+        self.compile_statement(statement)?
+      }
+
+      let to_end = self.emit(Instruction::Jump(0), finally_pos);
+
+      self.patch(to_finally);
+
+      for statement in body.iter() {
+        self.compile_statement(statement)?
+      }
+
+      self.emit(Instruction::Rethrow, finally_pos);
+
+      self.patch(to_end)
+    }
+
+    Ok(())
+  }
+
+  // Steps through a generator kept in a slot no name can refer to, `Next` leaves a value and whether there was one
+  fn compile_for(&mut self, name: &str, iterable: &Expression, body: &[Statement], pos: Pos) -> Result<(), ()> {
+    if let ExpressionNode::Range(Some(ref start), Some(ref end), inclusive) = iterable.node {
+      return self.compile_counting(name, start, end, inclusive, body, pos)
+    }
+
+    let slot = self.declare_local(&format!("<for {}>", pos.0), pos)?;
+
+    self.compile_expression(iterable)?;
+
+    self.emit(Instruction::Iterate, iterable.pos);
+    self.emit(Instruction::SetLocal(slot), pos);
+    self.emit(Instruction::Pop, pos);
+
+    let start = self.emit(Instruction::GetLocal(slot), pos);
+
+    self.emit(Instruction::Next, pos);
+
+    let to_done = self.emit(Instruction::JumpIfFalse(0), pos);
+    self.emit(Instruction::Pop, pos);
+
+    let binding = self.declare_local(name, pos)?;
+
+    self.emit(Instruction::SetLocal(binding), pos);
+    self.emit(Instruction::Pop, pos);
+
+    let breaks = self.compile_loop_body(body)?;
+
+    self.emit(Instruction::Jump(start), pos);
+
+    self.patch(to_done);
+    self.emit(Instruction::Pop, pos);
+    self.emit(Instruction::Pop, pos);
+
+    for jump in breaks {
+      self.patch(jump)
+    }
+
+    Ok(())
+  }
+
+  // `for i in a..b:` counts with `i` itself instead of making an array, the end is kept in a slot of its own
+  fn compile_counting(&mut self, name: &str, start: &Expression, end: &Expression, inclusive: bool, body: &[Statement], pos: Pos) -> Result<(), ()> {
+    let binding = self.declare_local(name, pos)?;
+    let limit   = self.declare_local(&format!("<for {}>", pos.0), pos)?;
+
+    self.compile_expression(start)?;
+
+    self.emit(Instruction::SetLocal(binding), pos);
+    self.emit(Instruction::Pop, pos);
+
+    self.compile_bound(end, inclusive)?;
+
+    self.emit(Instruction::SetLocal(limit), pos);
+    self.emit(Instruction::Pop, pos);
+
+    let top = self.emit(Instruction::GetLocal(binding), pos);
+
+    self.emit(Instruction::GetLocal(limit), pos);
+    self.emit(Instruction::Lt, pos);
+
+    let to_done = self.emit(Instruction::JumpIfFalse(0), pos);
+    self.emit(Instruction::Pop, pos);
+
+    let breaks = self.compile_loop_body(body)?;
+
+    self.emit(Instruction::GetLocal(binding), pos);
+    self.emit_constant(Value::Int(1), pos)?;
+    self.emit(Instruction::Add, pos);
+    self.emit(Instruction::SetLocal(binding), pos);
+    self.emit(Instruction::Pop, pos);
+    self.emit(Instruction::Jump(top), pos);
+
+    self.patch(to_done);
+    self.emit(Instruction::Pop, pos);
+
+    for jump in breaks {
+      self.patch(jump)
+    }
+
+    Ok(())
+  }
+
+  // `skip` goes on at what's compiled right after the body, the `break` jumps are left to patch past the end
+  fn compile_loop_body(&mut self, body: &[Statement]) -> Result<Vec<usize>, ()> {
+    let handlers = self.current().handlers.len();
+
+    self.current().loops.push(
+      Loop {
+        breaks: Vec::new(),
+        skips:  Vec::new(),
+        handlers,
+      }
+    );
+
+    for statement in body.iter() {
+      self.compile_statement(statement)?
+    }
+
+    let looping = self.current().loops.pop().unwrap();
+
+    for jump in looping.skips {
+      self.patch(jump)
+    }
+
+    Ok(looping.breaks)
+  }
+
+  // An end taken in is one past it left out
+  fn compile_bound(&mut self, end: &Expression, inclusive: bool) -> Result<(), ()> {
+    self.compile_expression(end)?;
+
+    if inclusive {
+      self.emit_constant(Value::Int(1), end.pos)?;
+      self.emit(Instruction::Add, end.pos);
+    }
+
+    Ok(())
+  }
+
+  // Ends the `prøv` blocks above the first `keep`, running their `endelig` bodies
+  fn leave_handlers(&mut self, keep: usize, pos: Pos) -> Result<(), ()> {
+    let handlers = self.current().handlers[keep ..].to_vec();
+
+    for handler in handlers.iter().rev() {
+      self.emit(Instruction::EndTry, pos);
+
+      if let Some(ref body) = *handler {
+        for statement in body.iter() {
+          self.compile_statement(statement)?
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // Ends the function being compiled, making a closure of it in the enclosing one
+  fn end_closure(&mut self, pos: Pos) -> Result<(), ()> {
+    let prototype = self.end_function();
+
+    let index = {
+      let chunk = &mut self.current().prototype.chunk;
+
+      chunk.functions.push(Rc::new(prototype));
+      chunk.functions.len() - 1
+    };
+
+    if index > u16::MAX as usize {
+      return Err(response!(Wrong("too many functions in one function"), self.source.file, self.source.snippet(&pos)))
+    }
+
+    self.emit(Instruction::Closure(index as u16), pos);
+
+    Ok(())
+  }
+
+
+
+  fn compile_expression(&mut self, expression: &Expression) -> Result<(), ()> {
+    use self::ExpressionNode::*;
+
+    let pos = expression.pos;
+
+    match expression.node {
+      Int(n) => {
+        // too big for an int, but still a number
+        let value = if n > i64::MAX as u64 { Value::Float(n as f64) } else { Value::Int(n as i64) };
+
+        self.emit_constant(value, pos)?
+      },
+
+      Float(n)   => self.emit_constant(Value::Float(n), pos)?,
+      Str(ref s) => self.emit_constant(Value::Str(s.as_str().into()), pos)?,
+
+      Bool(true)  => { self.emit(Instruction::True, pos); },
+      Bool(false) => { self.emit(Instruction::False, pos); },
+      Nil | Empty | EOF => { self.emit(Instruction::Nil, pos); },
+
+      Identifier(ref name) => {
+        let instruction = match self.resolve(name, pos)? {
+          Resolved::Local(slot)   => Instruction::GetLocal(slot),
+          Resolved::Upvalue(slot) => Instruction::GetUpvalue(slot),
+          Resolved::Global(name)  => Instruction::GetGlobal(name),
+        };
+
+        self.emit(instruction, pos);
+      },
+
+      Neg(ref operand) => {
+        self.compile_expression(operand)?;
+        self.emit(Instruction::Neg, pos);
+      },
+
+      Not(ref operand) => {
+        self.compile_expression(operand)?;
+        self.emit(Instruction::Not, pos);
+      },
+
+      Await(_) => return Err(response!(Wrong("`vent` can't be compiled to bytecode yet"), self.source.file, self.source.snippet(&pos))),
+
+      Yield(ref value) => {
+        if self.is_top_level() {
+          return Err(response!(Wrong("`gi` can only be used in functions"), self.source.file, self.source.snippet(&pos)))
+        }
+
+        self.compile_expression(value)?;
+        self.emit(Instruction::Yield, pos);
+      },
+
+      Binary(ref left, ref op, ref right) => self.compile_binary(left, op, right, pos)?,
+
+      Array(ref content) => self.compile_elements(content, 0, pos)?,
+
+      Table(ref content) => self.compile_entries(content, pos)?,
+
+      // put in place by `arrange_arguments` right after parsing
+      Named(..) => unreachable!(),
+
+      Spread(_) => return Err(response!(Wrong("`...` only spreads into arguments, arrays and tables"), self.source.file, self.source.snippet(&pos))),
+
+      Call(..) | Index(..) => self.compile_chain(expression)?,
+
+      // leaves the nil as what the whole chain is
+      Optional(ref value) => {
+        self.compile_link(value)?;
+
+        let to_end = self.emit(Instruction::JumpIfNil(0), pos);
+
+        self.current().chain.push(to_end)
+      },
+
+      Range(Some(ref start), Some(ref end), inclusive) => {
+        self.compile_expression(start)?;
+        self.compile_bound(end, inclusive)?;
+
+        self.emit(Instruction::Range, pos);
+      },
+
+      Range(..) => return Err(response!(Wrong("only slices can leave out the ends of a range"), self.source.file, self.source.snippet(&pos))),
+
+      // the fields go along as a table, matched to the params of the constructor when it's called
+      Construct(ref record, ref fields) => {
+        self.compile_expression(record)?;
+
+        for (key, value) in fields.iter() {
+          self.emit_constant(Value::Str(key.as_str().into()), value.pos)?;
+          self.compile_expression(value)?
+        }
+
+        let len = self.count(fields.len(), "fields in a record", pos)?;
+
+        self.emit(Instruction::Table(len), pos);
+        self.emit(Instruction::Construct, pos);
+      },
+    }
+
+    Ok(())
+  }
+
+  // A chain of indexes and calls, the `?.`s in it jump past the end of it
+  fn compile_chain(&mut self, chain: &Expression) -> Result<(), ()> {
+    let outer = std::mem::take(&mut self.current().chain);
+
+    self.compile_link(chain)?;
+
+    for jump in mem::replace(&mut self.current().chain, outer) {
+      self.patch(jump)
+    }
+
+    Ok(())
+  }
+
+  // A part of the chain compiling, it goes on through what's indexed or called
+  fn compile_link(&mut self, link: &Expression) -> Result<(), ()> {
+    match link.node {
+      ExpressionNode::Call(ref called, ref args)       => self.compile_call(called, args, link.pos),
+      ExpressionNode::Index(ref indexed, ref index, _) => self.compile_index(indexed, index, link.pos),
+      _                                                => self.compile_expression(link),
+    }
+  }
+
+  fn compile_call(&mut self, called: &Expression, args: &[Expression], pos: Pos) -> Result<(), ()> {
+    let mut argc = args.len();
+
+    // `a\b(c)` passes `a` along as the first argument
+    match called.node {
+      ExpressionNode::Index(ref indexed, ref index, true) => {
+        self.compile_link(indexed)?;
+        self.compile_expression(index)?;
+        self.emit(Instruction::Method, called.pos);
+
+        argc += 1
+      },
+
+      _ => self.compile_link(called)?,
+    }
+
+    // how many arguments there are is only known once they're spread
+    if args.iter().any(Self::is_spread) {
+      self.compile_elements(args, argc - args.len(), pos)?;
+      self.emit(Instruction::Apply, pos);
+    } else {
+      for arg in args.iter() {
+        self.compile_expression(arg)?
+      }
+
+      if argc > u8::MAX as usize {
+        return Err(response!(Wrong("can't pass more than 255 arguments"), self.source.file, self.source.snippet(&pos)))
+      }
+
+      self.emit(Instruction::Call(argc as u8), pos);
+    }
+
+    Ok(())
+  }
+
+  // missing ends are nil
+  fn compile_index(&mut self, indexed: &Expression, index: &Expression, pos: Pos) -> Result<(), ()> {
+    self.compile_link(indexed)?;
+
+    match index.node {
+      ExpressionNode::Range(ref start, ref end, inclusive) => {
+        match *start {
+          Some(ref start) => self.compile_expression(start)?,
+          None            => { self.emit(Instruction::Nil, index.pos); },
+        }
+
+        match *end {
+          Some(ref end) => self.compile_bound(end, inclusive)?,
+          None          => { self.emit(Instruction::Nil, index.pos); },
+        }
+
+        self.emit(Instruction::Slice, pos);
+      },
+
+      _ => {
+        self.compile_expression(index)?;
+        self.emit(Instruction::GetIndex, pos);
+      },
+    }
+
+    Ok(())
+  }
+
+  // An array of the `before` values on the stack and `content`, the elements up to a spread make it and it's added to from there on
+  fn compile_elements(&mut self, content: &[Expression], before: usize, pos: Pos) -> Result<(), ()> {
+    let plain = content.iter().take_while(|element| !Self::is_spread(element)).count();
+
+    for element in content[.. plain].iter() {
+      self.compile_expression(element)?
+    }
+
+    let len = self.count(before + plain, "elements in an array", pos)?;
+
+    self.emit(Instruction::Array(len), pos);
+
+    let mut left = &content[plain ..];
+
+    while let Some(element) = left.first() {
+      match element.node {
+        ExpressionNode::Spread(ref spread) => {
+          self.compile_expression(spread)?;
+
+          left = &left[1 ..]
+        },
+
+        _ => {
+          let plain = left.iter().take_while(|element| !Self::is_spread(element)).count();
+
+          self.compile_elements(&left[.. plain], 0, pos)?;
+
+          left = &left[plain ..]
+        },
+      }
+
+      self.emit(Instruction::Spread, pos);
+    }
+
+    Ok(())
+  }
+
+  // A table of `content`, the entries up to a spread make it and it's added to from there on
+  fn compile_entries(&mut self, content: &[(String, Expression)], pos: Pos) -> Result<(), ()> {
+    let plain = content.iter().take_while(|&(_, value)| !Self::is_spread(value)).count();
+
+    for (key, value) in content[.. plain].iter() {
+      self.emit_constant(Value::Str(key.as_str().into()), value.pos)?;
+      self.compile_expression(value)?
+    }
+
+    let len = self.count(plain, "entries in a table", pos)?;
+
+    self.emit(Instruction::Table(len), pos);
+
+    let mut left = &content[plain ..];
+
+    while let Some((_, value)) = left.first() {
+      match value.node {
+        ExpressionNode::Spread(ref spread) => {
+          self.compile_expression(spread)?;
+
+          left = &left[1 ..]
+        },
+
+        _ => {
+          let plain = left.iter().take_while(|&(_, value)| !Self::is_spread(value)).count();
+
+          self.compile_entries(&left[.. plain], pos)?;
+
+          left = &left[plain ..]
+        },
+      }
+
+      self.emit(Instruction::Spread, pos);
+    }
+
+    Ok(())
+  }
+
+  fn is_spread(expression: &Expression) -> bool {
+    matches!(expression.node, ExpressionNode::Spread(_))
+  }
+
+  fn compile_binary(&mut self, left: &Expression, op: &Operator, right: &Expression, pos: Pos) -> Result<(), ()> {
+    use self::Operator::*;
+
+    let instruction = match *op {
+      And => {
+        self.compile_expression(left)?;
+
+        let to_end = self.emit(Instruction::JumpIfFalse(0), pos);
+
+        self.emit(Instruction::Pop, pos);
+        self.compile_expression(right)?;
+
+        self.patch(to_end);
+
+        return Ok(())
+      },
+
+      Or => {
+        self.compile_expression(left)?;
+
+        let to_right = self.emit(Instruction::JumpIfFalse(0), pos);
+        let to_end   = self.emit(Instruction::Jump(0), pos);
+
+        self.patch(to_right);
+
+        self.emit(Instruction::Pop, pos);
+        self.compile_expression(right)?;
+
+        self.patch(to_end);
+
+        return Ok(())
+      },
+
+      Coalesce => {
+        self.compile_expression(left)?;
+
+        let to_right = self.emit(Instruction::JumpIfNil(0), pos);
+        let to_end   = self.emit(Instruction::Jump(0), pos);
+
+        self.patch(to_right);
+
+        self.emit(Instruction::Pop, pos);
+        self.compile_expression(right)?;
+
+        self.patch(to_end);
+
+        return Ok(())
+      },
+
+      // the function goes below its argument
+      PipeR | PipeL => {
+        let (function, value) = if *op == PipeR { (right, left) } else { (left, right) };
+
+        self.compile_expression(function)?;
+        self.compile_expression(value)?;
+
+        self.emit(Instruction::Call(1), pos);
+
+        return Ok(())
+      },
+
+      _ => Self::operation(op),
+    };
+
+    self.compile_expression(left)?;
+    self.compile_expression(right)?;
+
+    self.emit(instruction, pos);
+
+    Ok(())
+  }
+
+  // The instruction of an operator working on the two values on top of the stack
+  fn operation(op: &Operator) -> Instruction {
+    use self::Operator::*;
+
+    match *op {
+      Add    => Instruction::Add,
+      Sub    => Instruction::Sub,
+      Mul    => Instruction::Mul,
+      Div    => Instruction::Div,
+      Mod    => Instruction::Mod,
+      Pow    => Instruction::Pow,
+      Concat => Instruction::Concat,
+      Eq     => Instruction::Eq,
+      NEq    => Instruction::NEq,
+      Lt     => Instruction::Lt,
+      Gt     => Instruction::Gt,
+      LtEq   => Instruction::LtEq,
+      GtEq   => Instruction::GtEq,
+
+      // these jump or call instead
+      And | Or | Coalesce | PipeL | PipeR => unreachable!(),
+    }
+  }
+
+
+
+  fn begin_function(&mut self, name: &str, params: &[String]) {
+    let prototype = Prototype {
+      name:   name.to_string(),
+      params: params.to_vec(),
+      chunk:  Chunk {
+        source: Some(self.source.clone()),
+        ..Chunk::default()
+      },
+      ..Prototype::default()
+    };
+
+    self.functions.push(
+      FunctionState {
+        prototype,
+        locals:   params.to_vec(),
+        handlers: Vec::new(),
+        loops:    Vec::new(),
+        chain:    Vec::new(),
+      }
+    )
+  }
+
+  fn end_function(&mut self) -> Prototype {
+    let state = self.functions.pop().unwrap();
+
+    let mut prototype = state.prototype;
+
+    prototype.locals = state.locals;
+
+    prototype
+  }
+
+  fn current(&mut self) -> &mut FunctionState {
+    self.functions.last_mut().unwrap()
+  }
+
+  fn is_top_level(&self) -> bool {
+    self.functions.len() == 1
+  }
+
+
+
+  fn declare_local(&mut self, name: &str, pos: Pos) -> Result<u8, ()> {
+    let locals = &mut self.functions.last_mut().unwrap().locals;
+
+    let slot = match locals.iter().position(|local| local == name) {
+      Some(slot) => slot,
+      None       => {
+        locals.push(name.to_string());
+        locals.len() - 1
+      },
+    };
+
+    if slot > u8::MAX as usize {
+      return Err(response!(Wrong("too many variables in one function"), self.source.file, self.source.snippet(&pos)))
+    }
+
+    Ok(slot as u8)
+  }
+
+  fn resolve(&mut self, name: &str, pos: Pos) -> Result<Resolved, ()> {
+    let depth = self.functions.len() - 1;
+
+    if let Some(slot) = self.functions[depth].locals.iter().position(|local| local == name) {
+      return Ok(Resolved::Local(slot as u8))
+    }
+
+    if let Some(slot) = self.resolve_upvalue(depth, name, pos)? {
+      return Ok(Resolved::Upvalue(slot))
+    }
+
+    Ok(Resolved::Global(self.name(name, pos)?))
+  }
+
+  // Finds `name` in the functions enclosing the one at `depth`, capturing it through every function in between
+  fn resolve_upvalue(&mut self, depth: usize, name: &str, pos: Pos) -> Result<Option<u8>, ()> {
+    if depth == 0 {
+      return Ok(None)
+    }
+
+    let upvalue = match self.functions[depth - 1].locals.iter().position(|local| local == name) {
+      Some(slot) => (true, slot as u8),
+      None       => match self.resolve_upvalue(depth - 1, name, pos)? {
+        Some(slot) => (false, slot),
+        None       => return Ok(None),
+      },
+    };
+
+    let upvalues = &mut self.functions[depth].prototype.upvalues;
+
+    if let Some(slot) = upvalues.iter().position(|captured| *captured == upvalue) {
+      return Ok(Some(slot as u8))
+    }
+
+    upvalues.push(upvalue);
+
+    if upvalues.len() > u8::MAX as usize + 1 {
+      return Err(response!(Wrong("too many captured variables in one function"), self.source.file, self.source.snippet(&pos)))
+    }
+
+    Ok(Some((upvalues.len() - 1) as u8))
+  }
+
+
+
+  fn emit(&mut self, instruction: Instruction, pos: Pos) -> usize {
+    let line = self.source.location(pos.0).0;
+
+    self.current().prototype.chunk.write(instruction, line, pos)
+  }
+
+  // Points the jump at `index` to the next instruction
+  fn patch(&mut self, index: usize) {
+    let chunk  = &mut self.current().prototype.chunk;
+    let target = chunk.code.len();
+
+    chunk.code[index] = match chunk.code[index] {
+      Instruction::Jump(_)        => Instruction::Jump(target),
+      Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(target),
+      Instruction::JumpIfNil(_)   => Instruction::JumpIfNil(target),
+      Instruction::Try(_)         => Instruction::Try(target),
+      Instruction::Finally(_)     => Instruction::Finally(target),
+      instruction                 => instruction,
+    }
+  }
+
+  fn emit_constant(&mut self, value: Value, pos: Pos) -> Result<(), ()> {
+    let index = self.constant(value, pos)?;
+
+    self.emit(Instruction::Constant(index), pos);
+
+    Ok(())
+  }
+
+  fn constant(&mut self, value: Value, pos: Pos) -> Result<u16, ()> {
+    let constants = &mut self.functions.last_mut().unwrap().prototype.chunk.constants;
+
+    // names and strings are shared, numbers would mix up `1` and `1.0`
+    let existing = match value {
+      Value::Str(ref s) => constants.iter().position(|constant| match *constant {
+        Value::Str(ref other) => s == other,
+        _                     => false,
+      }),
+
+      _ => None,
+    };
+
+    let index = match existing {
+      Some(index) => index,
+      None        => {
+        constants.push(value);
+        constants.len() - 1
+      },
+    };
+
+    if index > u16::MAX as usize {
+      return Err(response!(Wrong("too many constants in one function"), self.source.file, self.source.snippet(&pos)))
+    }
+
+    Ok(index as u16)
+  }
+
+  fn name(&mut self, name: &str, pos: Pos) -> Result<u16, ()> {
+    self.constant(Value::Str(name.into()), pos)
+  }
+
+  fn count(&self, len: usize, what: &str, pos: Pos) -> Result<u16, ()> {
+    if len > u16::MAX as usize {
+      return Err(response!(Wrong(format!("too many {}", what)), self.source.file, self.source.snippet(&pos)))
+    }
+
+    Ok(len as u16)
+  }
+}
+
+enum Resolved {
+  Local(u8),
+  Upvalue(u8),
+  Global(u16),
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(code: &str) -> crate::Output<Value> {
+    crate::run_str("<test>", code, &mut Globals::new(), Limits::default())
+  }
+
+  fn message(code: &str) -> String {
+    run(code).diagnostics.first().map_or(String::new(), |diagnostic| diagnostic.message.clone())
+  }
+
+  #[test]
+  fn runs_the_first_matching_arm() {
+    let code = "enum Shape = { Circle(r: int), Empty }\nøl result = \"none\"\nmatch Shape.Circle(2):\n  Empty: result = \"empty\"\n  Circle(r): result = \"circle \" ++ r\n  _: result = \"other\"\nresult\n";
+
+    assert_eq!(run(code).result.map(|value| value.to_string()), Some("circle 2".to_string()))
+  }
+
+  #[test]
+  fn leaves_the_match_after_the_wildcard() {
+    let code   = "enum Shape = { Circle(r: int), Empty }\nøl result = \"none\"\nmatch Shape.Empty:\n  _: result = \"other\"\nmatch Shape.Empty:\n  Empty: result = \"empty\"\nresult\n";
+    let source = Source::from("<test>", code.to_string());
+    let tokens = Lexer::default(source.content.chars().collect(), &source).collect::<Result<Vec<Token>, ()>>().unwrap();
+
+    let mut ast = Parser::new(tokens, &source).parse().unwrap();
+
+    // the parser won't have arms after `_`, so the second match lends it one
+    let arm = match ast.remove(3).node {
+      StatementNode::Match(_, ref arms) => arms[0].clone(),
+      ref node                          => panic!("expected a match, found {:?}", node),
+    };
+
+    if let StatementNode::Match(_, ref mut arms) = ast[2].node {
+      arms.push(arm)
+    }
+
+    let prototype = CodeGen::new(&source).compile(&ast).unwrap();
+    let result    = Vm::new(&mut Globals::new()).run(prototype).unwrap();
+
+    assert_eq!(result.to_string(), "other")
+  }
+
+  #[test]
+  fn refuses_more_params_than_slots() {
+    let params = (0 .. 256).map(|i| format!("p{}", i)).collect::<Vec<String>>().join(", ");
+
+    assert_eq!(message(&format!("øl f({}) =\n  øl p0\n", params)), "too many params in one function");
+    assert!(run(&format!("øl f({}) =\n  øl p0\n", params.rsplit_once(", ").unwrap().0)).is_ok())
+  }
+
+  #[test]
+  fn refuses_more_locals_than_slots() {
+    let locals = (0 .. 257).map(|i| format!("  øl x{} = {}\n", i, i)).collect::<String>();
+
+    assert_eq!(message(&format!("øl f() =\n{}  øl x0\n", locals)), "too many variables in one function")
+  }
+
+  #[test]
+  fn pushes_nil() {
+    assert_eq!(run("øl t = {}\n[t.x == nil, nil]\n").result.map(|value| value.repr()), Some("[true, nil]".to_string()))
+  }
+
+  #[test]
+  fn catches_what_is_thrown() {
+    let code = "øl f() =\n  kast \"out of cheese\"\nøl result = \"none\"\nprøv:\n  f()\n  result = \"ran on\"\nfang e:\n  result = e\nresult\n";
+
+    assert_eq!(run(code).result.map(|value| value.to_string()), Some("out of cheese".to_string()))
+  }
+
+  #[test]
+  fn runs_finally_either_way() {
+    let code = "øl result = \"ran\"\nprøv:\n  prøv:\n    kast 1\n  endelig:\n    result = result ++ \" inner \"\nfang e:\n  result = result ++ e\nendelig:\n  result = result ++ \" outer\"\nresult\n";
+
+    assert_eq!(run(code).result.map(|value| value.to_string()), Some("ran inner 1 outer".to_string()))
+  }
+
+  #[test]
+  fn reports_uncaught_errors_where_they_were_thrown() {
+    let code   = "øl f() =\n  kast \"out of cheese\"\nf()\n";
+    let output = run(code);
+
+    let diagnostic = output.diagnostics.first().expect("it should be reported");
+
+    assert_eq!(diagnostic.message, "uncaught \"out of cheese\"");
+    assert!(diagnostic.pos.is_some_and(|pos| code[pos.0 ..].starts_with("kast")))
+  }
+
+  #[test]
+  fn resumes_generators_where_they_yielded() {
+    let code = "øl count(n) =\n  for i in 0..n:\n    gi i * 10\nøl result = \"got\"\nfor x in count(3):\n  result = result ++ \",\" ++ x\nresult\n";
+
+    assert_eq!(run(code).result.map(|value| value.to_string()), Some("got,0,10,20".to_string()))
+  }
+
+  #[test]
+  fn runs_generators_only_as_far_as_they_are_looped() {
+    let code = "øl steps = 0\nøl many() =\n  for i in 0..1000000:\n    steps = steps + 1\n    gi i\nfor x in many():\n  øl x == 2:\n    break\nsteps\n";
+
+    assert_eq!(run(code).result.map(|value| value.to_string()), Some("3".to_string()))
+  }
+
+  #[test]
+  fn counts_over_ranges() {
+    let code = "øl sum = 0\nfor i in 1...4:\n  sum = sum + i\nfor i in 0..2:\n  sum = sum + i * 100\nsum\n";
+
+    assert_eq!(run(code).result.map(|value| value.to_string()), Some("110".to_string()))
+  }
+
+  #[test]
+  fn slices_arrays_and_strings() {
+    let code = "øl xs = [1, 2, 3, 4]\n[0..3, 1...2, xs[1..3], xs[..2], xs[2..], xs[1...2], \"skål\"[..3]]\n";
+
+    assert_eq!(run(code).result.map(|value| value.repr()), Some("[[0, 1, 2], [1, 2], [2, 3], [1, 2], [3, 4], [2, 3], \"skå\"]".to_string()))
+  }
+
+  #[test]
+  fn spreads_and_gathers() {
+    let code = "øl log(level, ...msgs) =\n  øl [level, msgs]\nøl xs = [2, 3]\nøl base = { a: 1 }\n[log(\"info\", ...xs, 4), log(\"none\"), [0, ...xs, ...xs], { ...base, b: 2 }]\n";
+
+    assert_eq!(run(code).result.map(|value| value.repr()), Some("[[\"info\", [2, 3, 4]], [\"none\", []], [0, 2, 3, 2, 3], { a: 1, b: 2 }]".to_string()))
+  }
+
+  #[test]
+  fn stops_optional_chains_at_nil() {
+    let code = "øl player = { inventory: { beer: 2 }, tools: [1, 2] }\n[player?.inventory?.beer, player.bag?.beer, player.bag?.beer.count, player.tools?.[1], player.bag?.[0], player.greet?.(1)]\n";
+
+    assert_eq!(run(code).result.map(|value| value.repr()), Some("[2, nil, nil, 2, nil, nil]".to_string()))
+  }
+
+  #[test]
+  fn coalesces_only_nil() {
+    let code = "øl player = { inventory: { beer: 0 } }\n[player.bag ?? \"none\", player.inventory.beer ?? 1, false ?? true, nil ?? nil ?? 3]\n";
+
+    assert_eq!(run(code).result.map(|value| value.repr()), Some("[\"none\", 0, false, 3]".to_string()))
+  }
+
+  #[test]
+  fn assigns_by_every_operator() {
+    let code = "øl n = 2\nn += 4\nn -= 1\nn *= 3\nn /= 5\nn %= 2\nøl p = 2\np ^= 3\nøl s = \"skå\"\ns ++= \"l\"\nøl xs = [1]\nxs ++= [2]\n[n, p, s, xs]\n";
+
+    assert_eq!(run(code).result.map(|value| value.repr()), Some("[1, 8, \"skål\", [1, 2]]".to_string()))
+  }
+
+  #[test]
+  fn evaluates_compound_indexes_once() {
+    let code = "øl calls = 0\nøl t = { n: [1] }\nøl key() =\n  calls = calls + 1\n  øl \"n\"\nt[key()] ++= [2]\n[t.n, calls]\n";
+
+    assert_eq!(run(code).result.map(|value| value.repr()), Some("[[1, 2], 1]".to_string()))
+  }
+
+  #[test]
+  fn builds_records_with_their_defaults() {
+    let code = "record Player = { name: str, score: int = 10 }\nøl p = Player { name: \"a\" }\nøl q = Player { score: 3, name: \"b\" }\nøl r = Player(\"c\")\n[p.name, p.score, q.name, q.score, r.score]\n";
+
+    assert_eq!(run(code).result.map(|value| value.repr()), Some("[\"a\", 10, \"b\", 3, 10]".to_string()))
+  }
+}
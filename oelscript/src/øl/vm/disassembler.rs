@@ -0,0 +1,69 @@
+use super::*;
+
+// Lists the instructions of a function and of every function nested in it
+pub fn disassemble(prototype: &Prototype) -> String {
+  let mut result = format!("== {}({}) ==\n", prototype.name, prototype.params.join(", "));
+
+  let chunk = &prototype.chunk;
+
+  for (ip, instruction) in chunk.code.iter().enumerate() {
+    let line = if ip > 0 && chunk.lines[ip] == chunk.lines[ip - 1] {
+      "   |".to_string()
+    } else {
+      format!("{:4}", chunk.lines[ip])
+    };
+
+    result.push_str(&format!("{:04} {} {}\n", ip, line, describe(instruction, prototype)))
+  }
+
+  for function in chunk.functions.iter() {
+    result.push('\n');
+    result.push_str(&disassemble(function))
+  }
+
+  result
+}
+
+fn describe(instruction: &Instruction, prototype: &Prototype) -> String {
+  use self::Instruction::*;
+
+  let chunk = &prototype.chunk;
+
+  let constant = |name: &str, index: u16| format!("{:<14} {:4} {}", name, index, chunk.constants[index as usize].repr());
+  let local    = |name: &str, slot: u8| format!("{:<14} {:4} {}", name, slot, prototype.locals[slot as usize]);
+  let operand  = |name: &str, operand: usize| format!("{:<14} {:4}", name, operand);
+
+  match *instruction {
+    Constant(index)     => constant("Constant", index),
+    GetGlobal(index)    => constant("GetGlobal", index),
+    SetGlobal(index)    => constant("SetGlobal", index),
+    DefineGlobal(index) => constant("DefineGlobal", index),
+
+    GetLocal(slot) => local("GetLocal", slot),
+    SetLocal(slot) => local("SetLocal", slot),
+
+    GetUpvalue(slot)    => operand("GetUpvalue", slot as usize),
+    SetUpvalue(slot)    => operand("SetUpvalue", slot as usize),
+    Array(len)          => operand("Array", len as usize),
+    Table(len)          => operand("Table", len as usize),
+    Jump(target)        => operand("Jump", target),
+    JumpIfFalse(target) => operand("JumpIfFalse", target),
+    JumpIfNil(target)   => operand("JumpIfNil", target),
+    Try(target)         => operand("Try", target),
+    Finally(target)     => operand("Finally", target),
+    Call(argc)          => operand("Call", argc as usize),
+    Field(n)            => operand("Field", n as usize),
+
+    Closure(index) => {
+      let function = &chunk.functions[index as usize];
+
+      let upvalues = function.upvalues.iter()
+        .map(|&(is_local, slot)| format!("{} {}", if is_local { "local" } else { "upvalue" }, slot))
+        .collect::<Vec<String>>();
+
+      format!("{:<14} {:4} <{}> {}", "Closure", index, function.name, upvalues.join(", "))
+    },
+
+    _ => format!("{:?}", instruction),
+  }
+}
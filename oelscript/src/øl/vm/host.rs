@@ -0,0 +1,350 @@
+use super::*;
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub type NativeFunction = dyn Fn(&mut Vm, &[Value]) -> Result<Value, RuntimeError>;
+
+// A function of the host, failing with a message the VM points at the call
+pub struct Native {
+  pub name:     String,
+  pub function: Box<NativeFunction>,
+}
+
+// The global environment, shared by every script run against it and holding the prelude
+pub struct Globals {
+  values: HashMap<String, Value>,
+}
+
+impl Globals {
+  pub fn new() -> Self {
+    let mut globals = Globals {
+      values: HashMap::new(),
+    };
+
+    prelude::install(&mut globals);
+
+    globals
+  }
+
+  pub fn get(&self, name: &str) -> Option<&Value> {
+    self.values.get(name)
+  }
+
+  pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+    self.values.get_mut(name)
+  }
+
+  pub fn set<T: IntoValue>(&mut self, name: &str, value: T) {
+    self.values.insert(name.to_string(), value.into_value());
+  }
+
+  // Registers a Rust function taking and returning convertible values
+  //
+  //   globals.register("clamp", |x: f64, low: f64, high: f64| x.max(low).min(high))
+  pub fn register<Args, F: Host<Args> + 'static>(&mut self, name: &str, function: F) {
+    let native_name = name.to_string();
+
+    self.native(name, move |_, args| function.call(&native_name, args))
+  }
+
+  // Registers a function working on raw values, with the VM at hand for calling back into scripts
+  pub fn native<F>(&mut self, name: &str, function: F)
+  where
+    F: Fn(&mut Vm, &[Value]) -> Result<Value, RuntimeError> + 'static
+  {
+    let native = Native {
+      name:     name.to_string(),
+      function: Box::new(function),
+    };
+
+    self.set(name, Value::Native(Rc::new(native)))
+  }
+}
+
+impl Default for Globals {
+  fn default() -> Self {
+    Globals::new()
+  }
+}
+
+
+
+// Converts a script value into a Rust one, the error tells what was expected instead
+pub trait FromValue: Sized {
+  fn from_value(value: &Value) -> Result<Self, String>;
+}
+
+pub trait IntoValue {
+  fn into_value(self) -> Value;
+}
+
+fn expected<T>(what: &str, value: &Value) -> Result<T, String> {
+  Err(format!("expected {}, found {}", what, value.type_name()))
+}
+
+impl FromValue for Value {
+  fn from_value(value: &Value) -> Result<Self, String> {
+    Ok(value.clone())
+  }
+}
+
+impl FromValue for bool {
+  fn from_value(value: &Value) -> Result<Self, String> {
+    match *value {
+      Value::Bool(b) => Ok(b),
+      _              => expected("bool", value),
+    }
+  }
+}
+
+impl FromValue for i64 {
+  fn from_value(value: &Value) -> Result<Self, String> {
+    match *value {
+      Value::Int(n)                          => Ok(n),
+      Value::Float(n) if n.fract() == 0.0    => Ok(n as i64),
+      _                                      => expected("int", value),
+    }
+  }
+}
+
+impl FromValue for f64 {
+  fn from_value(value: &Value) -> Result<Self, String> {
+    match *value {
+      Value::Int(n)   => Ok(n as f64),
+      Value::Float(n) => Ok(n),
+      _               => expected("float", value),
+    }
+  }
+}
+
+impl FromValue for String {
+  fn from_value(value: &Value) -> Result<Self, String> {
+    match *value {
+      Value::Str(ref s) => Ok(s.to_string()),
+      _                 => expected("string", value),
+    }
+  }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+  fn from_value(value: &Value) -> Result<Self, String> {
+    match *value {
+      Value::Array(ref content) => content.borrow().iter().map(T::from_value).collect(),
+      _                         => expected("array", value),
+    }
+  }
+}
+
+// nil is None, so trailing `Option` parameters can be left out
+impl<T: FromValue> FromValue for Option<T> {
+  fn from_value(value: &Value) -> Result<Self, String> {
+    match *value {
+      Value::Nil => Ok(None),
+      _          => T::from_value(value).map(Some),
+    }
+  }
+}
+
+impl IntoValue for Value {
+  fn into_value(self) -> Value {
+    self
+  }
+}
+
+impl IntoValue for () {
+  fn into_value(self) -> Value {
+    Value::Nil
+  }
+}
+
+impl IntoValue for bool {
+  fn into_value(self) -> Value {
+    Value::Bool(self)
+  }
+}
+
+impl IntoValue for i64 {
+  fn into_value(self) -> Value {
+    Value::Int(self)
+  }
+}
+
+impl IntoValue for f64 {
+  fn into_value(self) -> Value {
+    Value::Float(self)
+  }
+}
+
+impl IntoValue for String {
+  fn into_value(self) -> Value {
+    Value::Str(self.into())
+  }
+}
+
+impl IntoValue for &str {
+  fn into_value(self) -> Value {
+    Value::Str(self.into())
+  }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+  fn into_value(self) -> Value {
+    Value::Array(Rc::new(RefCell::new(self.into_iter().map(IntoValue::into_value).collect())))
+  }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+  fn into_value(self) -> Value {
+    self.map_or(Value::Nil, IntoValue::into_value)
+  }
+}
+
+
+
+// What a registered function may return, a plain value or a `Result` failing the call
+pub trait IntoResult {
+  fn into_result(self) -> Result<Value, RuntimeError>;
+}
+
+impl<T: IntoValue> IntoResult for T {
+  fn into_result(self) -> Result<Value, RuntimeError> {
+    Ok(self.into_value())
+  }
+}
+
+impl<T: IntoValue, E: Into<RuntimeError>> IntoResult for Result<T, E> {
+  fn into_result(self) -> Result<Value, RuntimeError> {
+    self.map(IntoValue::into_value).map_err(Into::into)
+  }
+}
+
+// Rust functions that can be registered, `Args` being the tuple of their parameter types
+pub trait Host<Args> {
+  fn call(&self, name: &str, args: &[Value]) -> Result<Value, RuntimeError>;
+}
+
+macro_rules! host {
+  ( $( $arg:ident ),* ) => {
+    impl<F, R, $( $arg: FromValue ),*> Host<( $( $arg, )* )> for F
+    where
+      F: Fn( $( $arg ),* ) -> R,
+      R: IntoResult,
+    {
+      #[allow(non_snake_case, unused_mut, unused_variables)]
+      fn call(&self, name: &str, args: &[Value]) -> Result<Value, RuntimeError> {
+        let arity = 0 $( + { stringify!($arg); 1 } )*;
+
+        if args.len() > arity {
+          return Err(format!("`{}` takes {} arguments, got {}", name, arity, args.len()).into())
+        }
+
+        let mut position = 0;
+
+        $(
+          position += 1;
+
+          let $arg = match $arg::from_value(args.get(position - 1).unwrap_or(&Value::Nil)) {
+            Ok(value)    => value,
+            Err(message) => return Err(format!("argument {} of `{}`: {}", position, name, message).into()),
+          };
+        )*
+
+        self( $( $arg ),* ).into_result()
+      }
+    }
+  };
+}
+
+host!();
+host!(A);
+host!(A, B);
+host!(A, B, C);
+host!(A, B, C, D);
+host!(A, B, C, D, E);
+host!(A, B, C, D, E, G);
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(code: &str, globals: &mut Globals) -> crate::Output<Value> {
+    crate::run_str("<test>", code, globals, Limits::default())
+  }
+
+  fn message(code: &str, globals: &mut Globals) -> String {
+    run(code, globals).diagnostics.first().map_or(String::new(), |diagnostic| diagnostic.message.clone())
+  }
+
+  #[test]
+  fn calls_registered_functions() {
+    let mut globals = Globals::new();
+
+    globals.register("clamp", |x: f64, low: f64, high: f64| x.max(low).min(high));
+    globals.register("shout", |s: String, times: Option<i64>| s.to_uppercase().repeat(times.unwrap_or(1) as usize));
+    globals.register("halve", |n: i64| if n % 2 == 0 { Ok(n / 2) } else { Err(format!("{} is odd", n)) });
+
+    assert_eq!(run("[clamp(5, 0, 3), shout(\"skål\"), shout(\"ø\", 2), halve(4)]\n", &mut globals).result.map(|value| value.repr()), Some("[3, \"SKÅL\", \"ØØ\", 2]".to_string()));
+    assert_eq!(message("halve(3)\n", &mut globals), "3 is odd")
+  }
+
+  #[test]
+  fn reports_arguments_that_dont_convert() {
+    let mut globals = Globals::new();
+
+    globals.register("sum", |xs: Vec<i64>| xs.iter().sum::<i64>());
+
+    assert_eq!(message("sum([1, \"2\"])\n", &mut globals), "argument 1 of `sum`: expected int, found string");
+    assert_eq!(message("sum(1)\n", &mut globals), "argument 1 of `sum`: expected array, found int");
+    assert_eq!(message("sum()\n", &mut globals), "argument 1 of `sum`: expected array, found nil");
+    assert_eq!(message("sum([1], [2])\n", &mut globals), "`sum` takes 1 arguments, got 2")
+  }
+
+  #[test]
+  fn converts_values_both_ways() {
+    assert_eq!(i64::from_value(&Value::Float(2.0)), Ok(2));
+    assert_eq!(i64::from_value(&Value::Float(2.5)), Err("expected int, found float".to_string()));
+    assert_eq!(f64::from_value(&Value::Int(2)), Ok(2.0));
+    assert_eq!(bool::from_value(&Value::Nil), Err("expected bool, found nil".to_string()));
+    assert_eq!(Option::<String>::from_value(&Value::Nil), Ok(None));
+
+    assert_eq!(vec![Some("ø"), None].into_value().repr(), "[\"ø\", nil]");
+    assert_eq!(().into_value(), Value::Nil)
+  }
+
+  #[test]
+  fn calls_back_into_scripts_from_natives() {
+    let mut globals = Globals::new();
+
+    globals.native("twice", |vm, args| {
+      let once = vm.call(args[0].clone(), vec![args[1].clone()])?;
+
+      vm.call(args[0].clone(), vec![once])
+    });
+
+    assert_eq!(run("øl inc(n) =\n  øl n + 1\ntwice(inc, 1)\n", &mut globals).result, Some(Value::Int(3)))
+  }
+
+  #[test]
+  fn calls_script_functions_from_the_host() {
+    let mut globals = Globals::new();
+
+    run("øl double(n) =\n  øl n * 2\nøl fail(n) =\n  øl n + nil\n", &mut globals);
+
+    let double = globals.get("double").cloned().unwrap();
+    let fail   = globals.get("fail").cloned().unwrap();
+
+    let mut vm = Vm::new(&mut globals);
+
+    assert_eq!(vm.call(double, vec![Value::Int(21)]), Ok(Value::Int(42)));
+
+    // the error points into the script the function came from
+    let error = vm.call(fail, vec![Value::Int(1)]).unwrap_err();
+
+    assert_eq!(error.source.map(|source| source.file.0.clone()), Some("<test>".to_string()));
+    assert_eq!(error.pos.map(|pos| &"øl double(n) =\n  øl n * 2\nøl fail(n) =\n  øl n + nil\n"[pos.0 .. pos.1]), Some("n + nil"))
+  }
+}
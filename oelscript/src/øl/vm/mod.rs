@@ -0,0 +1,19 @@
+pub mod value;
+pub mod chunk;
+pub mod codegen;
+#[allow(clippy::module_inception)]
+pub mod vm;
+pub mod host;
+pub mod disassembler;
+
+use super::parser::*;
+use super::source::*;
+use super::lexer::*;
+use super::prelude;
+
+pub use self::value::*;
+pub use self::chunk::*;
+pub use self::codegen::*;
+pub use self::vm::*;
+pub use self::host::*;
+pub use self::disassembler::*;
@@ -0,0 +1,261 @@
+use super::*;
+
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+// How deep arrays, tables and records are shown before the rest is left out
+const SHOW_DEPTH: usize = 64;
+
+#[derive(Clone)]
+pub enum Value {
+  Nil,
+  Bool(bool),
+  Int(i64),
+  Float(f64),
+  Str(Rc<str>),
+  Array(Rc<RefCell<Vec<Value>>>),
+  Table(Rc<RefCell<Vec<(String, Value)>>>), // keeps insertion order like a JS object
+  Record(Rc<Instance>),
+  Function(Rc<Closure>),
+  Native(Rc<Native>),
+  Generator(Rc<RefCell<Generator>>),
+}
+
+pub struct Closure {
+  pub prototype: Rc<Prototype>,
+  pub upvalues:  Vec<Rc<RefCell<Upvalue>>>,
+}
+
+// A value of a record type, its fields are fixed by the constructor that made it
+pub struct Instance {
+  pub record: Rc<Prototype>,
+  pub fields: RefCell<Vec<Value>>, // in the order of the constructor's params
+}
+
+impl Instance {
+  pub fn name(&self) -> &str {
+    &self.record.name
+  }
+
+  pub fn field_names(&self) -> &[String] {
+    &self.record.params
+  }
+
+  pub fn field(&self, name: &str) -> Option<usize> {
+    self.field_names().iter().position(|param| param == name)
+  }
+}
+
+// A captured variable, living on the stack until the function owning it returns
+#[derive(Debug)]
+pub enum Upvalue {
+  Open(usize),
+  Closed(Value),
+}
+
+impl Value {
+  pub fn type_name(&self) -> &'static str {
+    use self::Value::*;
+
+    match *self {
+      Nil          => "nil",
+      Bool(_)      => "bool",
+      Int(_)       => "int",
+      Float(_)     => "float",
+      Str(_)       => "string",
+      Array(_)     => "array",
+      // records and generators are plain objects in JS
+      Table(_)     |
+      Record(_)    |
+      Generator(_) => "table",
+      Function(_)  |
+      Native(_)    => "function",
+    }
+  }
+
+  // Same rules as JavaScript
+  pub fn truthy(&self) -> bool {
+    use self::Value::*;
+
+    match *self {
+      Nil        => false,
+      Bool(b)    => b,
+      Int(n)     => n != 0,
+      Float(n)   => n != 0.0 && !n.is_nan(),
+      Str(ref s) => !s.is_empty(),
+      _          => true,
+    }
+  }
+
+  // How the value is written in source, strings get their quotes
+  pub fn repr(&self) -> String {
+    let mut repr = String::new();
+
+    self.show(&mut repr, true, &mut Vec::new()).unwrap();
+
+    repr
+  }
+
+  // Bytes it holds on to besides itself, counted the way `Vm::allocate` is charged
+  pub fn size(&self) -> usize {
+    match *self {
+      Value::Str(ref s)         => s.len(),
+      Value::Array(ref content) => content.borrow().len() * mem::size_of::<Value>(),
+      Value::Table(ref content) => content.borrow().len() * mem::size_of::<(String, Value)>(),
+      _                         => 0,
+    }
+  }
+
+  // `seen` are the arrays, tables and records being shown around this one, one of them
+  // showing up again inside itself is `[...]` instead of going around forever
+  fn show(&self, f: &mut dyn fmt::Write, quoted: bool, seen: &mut Vec<usize>) -> fmt::Result {
+    use self::Value::*;
+
+    let address = match *self {
+      Array(ref content)   => Rc::as_ptr(content) as *const () as usize,
+      Table(ref content)   => Rc::as_ptr(content) as *const () as usize,
+      Record(ref instance) => Rc::as_ptr(instance) as *const () as usize,
+
+      Str(ref s) if quoted => return write!(f, "{:?}", s),
+      _                    => return write!(f, "{}", self),
+    };
+
+    // nesting too deep to print is as good as a cycle
+    if seen.contains(&address) || seen.len() == SHOW_DEPTH {
+      return write!(f, "[...]")
+    }
+
+    seen.push(address);
+
+    match *self {
+      Array(ref content) => {
+        write!(f, "[")?;
+
+        for (i, element) in content.borrow().iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?
+          }
+
+          element.show(f, true, seen)?
+        }
+
+        write!(f, "]")?
+      },
+
+      Table(ref content) => {
+        write!(f, "{{ ")?;
+
+        for (i, (key, value)) in content.borrow().iter().enumerate() {
+          write!(f, "{}{}: ", if i > 0 { ", " } else { "" }, key)?;
+
+          value.show(f, true, seen)?
+        }
+
+        write!(f, " }}")?
+      },
+
+      Record(ref instance) => {
+        write!(f, "{} {{ ", instance.name())?;
+
+        for (i, (key, value)) in instance.field_names().iter().zip(instance.fields.borrow().iter()).enumerate() {
+          write!(f, "{}{}: ", if i > 0 { ", " } else { "" }, key)?;
+
+          value.show(f, true, seen)?
+        }
+
+        write!(f, " }}")?
+      },
+
+      _ => unreachable!(),
+    }
+
+    seen.pop();
+
+    Ok(())
+  }
+}
+
+impl PartialEq for Value {
+  fn eq(&self, other: &Value) -> bool {
+    use self::Value::*;
+
+    match (self, other) {
+      (Nil,          Nil)          => true,
+      (Bool(a),      Bool(b))      => a == b,
+      (Int(a),       Int(b))       => a == b,
+      (Float(a),     Float(b))     => a == b,
+      (Int(a),       Float(b))     => *a as f64 == *b,
+      (Float(a),     Int(b))       => *a == *b as f64,
+      (Str(a),       Str(b))       => a == b,
+      (Array(a),     Array(b))     => Rc::ptr_eq(a, b),
+      (Table(a),     Table(b))     => Rc::ptr_eq(a, b),
+      (Record(a),    Record(b))    => Rc::ptr_eq(a, b),
+      (Function(a),  Function(b))  => Rc::ptr_eq(a, b),
+      (Native(a),    Native(b))    => Rc::ptr_eq(a, b),
+      (Generator(a), Generator(b)) => Rc::ptr_eq(a, b),
+      _ => false,
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    use self::Value::*;
+
+    match *self {
+      Nil        => write!(f, "nil"),
+      Bool(b)    => write!(f, "{}", b),
+      Int(n)     => write!(f, "{}", n),
+      Float(n)   => write!(f, "{}", n),
+      Str(ref s) => write!(f, "{}", s),
+
+      Array(_) | Table(_) | Record(_) => self.show(f, false, &mut Vec::new()),
+
+      Function(ref closure) => write!(f, "<øl {}({})>", closure.prototype.name, closure.prototype.params.join(", ")),
+      Native(ref native)    => write!(f, "<native {}>", native.name),
+      Generator(_)         => write!(f, "<generator>"),
+    }
+  }
+}
+
+impl fmt::Debug for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.repr())
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn repr(code: &str) -> String {
+    crate::run_str("<test>", code, &mut Globals::new(), Limits::default()).result.expect("it should run").repr()
+  }
+
+  #[test]
+  fn shows_arrays_in_themselves() {
+    assert_eq!(repr("øl a = [1]\na[1] = a\na\n"), "[1, [...]]")
+  }
+
+  #[test]
+  fn shows_tables_in_themselves() {
+    assert_eq!(repr("øl t = { x: \"x\" }\nt.me = [t]\nt\n"), "{ x: \"x\", me: [[...]] }")
+  }
+
+  #[test]
+  fn shows_shared_values_in_full() {
+    assert_eq!(repr("øl a = [1]\n[a, { a: a }]\n"), "[[1], { a: [1] }]")
+  }
+
+  #[test]
+  fn leaves_out_deep_nesting() {
+    let repr = repr("øl a = []\nfor i in 0..100:\n  a = [a]\na\n");
+
+    assert_eq!(repr.matches('[').count(), SHOW_DEPTH + 1);
+    assert!(repr.contains("[...]"))
+  }
+}
@@ -0,0 +1,1250 @@
+use super::*;
+use super::super::error::Response::Wrong;
+
+use std::mem;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::time::{ Duration, Instant };
+
+pub const FRAMES_MAX: usize = 256;
+
+// Bounds of a run for untrusted scripts, None leaves a resource unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+  pub fuel:    Option<u64>,      // instructions run
+  pub timeout: Option<Duration>, // wall-clock time
+  pub memory:  Option<usize>,    // bytes of the arrays, tables and strings made, freed ones included
+}
+
+// Why running stopped, reported once it's back at the outermost call
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+  pub message: String,
+  pub pos:     Option<Pos>,   // None until the VM knows which instruction failed
+  pub source:  Option<Rc<Source>>, // what `pos` is in, the source of the function that failed
+  pub calls:   Vec<Pos>,      // call sites leading there in that source, innermost first
+  pub thrown:  Option<Value>, // what `kast` raised, caught as is instead of as the message
+  pub fatal:   bool,          // running out of a limit, which `fang` can't catch
+}
+
+impl RuntimeError {
+  pub fn fatal(message: String) -> Self {
+    RuntimeError {
+      fatal: true,
+      ..RuntimeError::from(message)
+    }
+  }
+}
+
+impl From<String> for RuntimeError {
+  fn from(message: String) -> Self {
+    RuntimeError {
+      message,
+      pos:    None,
+      source: None,
+      calls:  Vec::new(),
+      thrown: None,
+      fatal:  false,
+    }
+  }
+}
+
+impl<'a> From<&'a str> for RuntimeError {
+  fn from(message: &'a str) -> Self {
+    RuntimeError::from(message.to_string())
+  }
+}
+
+struct Frame {
+  closure:   Rc<Closure>,
+  ip:        usize,
+  base:      usize, // stack index of the first parameter, the called function sits right below it
+  generator: Option<Rc<RefCell<Generator>>>, // the one it's running the function of
+}
+
+// What a `for` loop steps through
+pub enum Generator {
+  Elements(Rc<RefCell<Vec<Value>>>, usize), // an array and the index of its next element
+  Suspended(Suspended),
+  Running,
+  Done,
+}
+
+// The frame of a generator function while it waits to be resumed
+pub struct Suspended {
+  closure:  Rc<Closure>,
+  ip:       usize,
+  stack:    Vec<Value>,   // from its first parameter up
+  handlers: Vec<Handler>, // the `prøv` blocks it's in, relative to its frame
+  captured: Vec<(usize, Rc<RefCell<Upvalue>>)>, // its variables closures hold on to, by slot
+}
+
+// Where to go when what runs after a `Try` or `Finally` raises
+struct Handler {
+  frames:  usize, // how many there were, the one it's in being the last
+  stack:   usize,
+  target:  usize,
+  finally: bool,
+  pending: usize,
+}
+
+// Runs bytecode against a set of globals that outlives it
+pub struct Vm<'v> {
+  pub globals:   &'v mut Globals,
+  stack:         Vec<Value>,
+  frames:        Vec<Frame>,
+  open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+  handlers:      Vec<Handler>,
+  pending:       Vec<(RuntimeError, usize)>, // errors `endelig` bodies are running for, and where they were raised
+
+  limits:    Limits,
+  deadline:  Option<Instant>,
+  steps:     u64,
+  allocated: usize,
+}
+
+impl<'v> Vm<'v> {
+  pub fn new(globals: &'v mut Globals) -> Self {
+    Vm {
+      globals,
+      stack:         Vec::new(),
+      frames:        Vec::new(),
+      open_upvalues: Vec::new(),
+      handlers:      Vec::new(),
+      pending:       Vec::new(),
+
+      limits:    Limits::default(),
+      deadline:  None,
+      steps:     0,
+      allocated: 0,
+    }
+  }
+
+  // The timeout starts counting from here
+  pub fn with_limits(mut self, limits: Limits) -> Self {
+    self.deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+    self.limits   = limits;
+
+    self
+  }
+
+  // Runs a compiled script, reporting what made it fail
+  pub fn run(&mut self, prototype: Rc<Prototype>) -> Result<Value, ()> {
+    let closure = Closure {
+      prototype,
+      upvalues: Vec::new(),
+    };
+
+    self.call(Value::Function(Rc::new(closure)), Vec::new()).map_err(|error| self.report(&error))
+  }
+
+  // Calls an ØlScript or native function, also from within a native one
+  pub fn call(&mut self, function: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let depth  = self.frames.len();
+    let bottom = self.stack.len();
+    let argc   = args.len();
+
+    self.stack.push(function);
+    self.stack.extend(args);
+
+    // calls of natives are done right away
+    let result = match self.call_value(argc) {
+      Ok(true)   => self.execute(depth),
+      Ok(false)  => Ok(()),
+      Err(error) => Err(error),
+    };
+
+    match result {
+      Ok(()) => Ok(self.stack.pop().unwrap()),
+
+      Err(error) => {
+        self.stack.truncate(bottom);
+
+        Err(error)
+      },
+    }
+  }
+
+  // Points at where in its own source the function that failed was, a native called right from the host has none
+  pub fn report(&self, error: &RuntimeError) {
+    let source = match error.source {
+      Some(ref source) => source,
+      None             => return response!(Wrong(&error.message), FilePath(String::from("<host>"))),
+    };
+
+    let mut snippet = source.snippet(&error.pos.unwrap_or_default());
+
+    for call in error.calls.iter() {
+      snippet = snippet.label(call, "called from here")
+    }
+
+    response!(Wrong(&error.message), source.file, snippet)
+  }
+
+
+
+  // Runs until the frame at `depth` returns
+  fn execute(&mut self, depth: usize) -> Result<(), RuntimeError> {
+    loop {
+      match self.step(depth) {
+        Ok(true)   => return Ok(()),
+        Ok(false)  => (),
+        Err(error) => if let Err(error) = self.handle(error, depth) {
+          return Err(self.unwind(error, depth))
+        },
+      }
+    }
+  }
+
+  // Goes on at the innermost handler of a frame above `depth`, giving the error back when there's none
+  fn handle(&mut self, error: RuntimeError, depth: usize) -> Result<(), RuntimeError> {
+    if error.fatal || self.handlers.last().is_none_or(|handler| handler.frames <= depth) {
+      return Err(error)
+    }
+
+    let handler   = self.handlers.pop().unwrap();
+    let mut error = self.unwind(error, handler.frames);
+
+    let ip = self.frames.last().unwrap().ip;
+
+    if error.pos.is_none() {
+      let chunk = &self.frames.last().unwrap().closure.prototype.chunk;
+
+      error.pos    = Some(chunk.spans[ip - 1]);
+      error.source = chunk.source.clone()
+    }
+
+    self.close_upvalues(handler.stack);
+    self.stack.truncate(handler.stack);
+    self.pending.truncate(handler.pending);
+
+    // an `endelig` body runs and raises the error again, a `fang` body gets what was raised
+    if handler.finally {
+      self.pending.push((error, ip))
+    } else {
+      let caught = match error.thrown {
+        Some(value) => value,
+        None        => Value::Str(error.message.as_str().into()),
+      };
+
+      self.stack.push(caught)
+    }
+
+    self.frames.last_mut().unwrap().ip = handler.target;
+
+    Ok(())
+  }
+
+  // Drops the frames above `depth`, pointing the error at where they were
+  fn unwind(&mut self, mut error: RuntimeError, depth: usize) -> RuntimeError {
+    while self.frames.len() > depth {
+      let frame = self.frames.pop().unwrap();
+      let chunk = &frame.closure.prototype.chunk;
+      let pos   = chunk.spans[frame.ip - 1];
+
+      // call sites in another source can't be pointed at in the snippet of this one
+      let same_source = match (&error.source, &chunk.source) {
+        (Some(ref a), Some(ref b)) => Rc::ptr_eq(a, b),
+        (a, b)                     => a.is_none() && b.is_none(),
+      };
+
+      // like in JS, a generator that raised is done
+      if let Some(ref generator) = frame.generator {
+        *generator.borrow_mut() = Generator::Done
+      }
+
+      match error.pos {
+        None => {
+          error.pos    = Some(pos);
+          error.source = chunk.source.clone()
+        },
+
+        // recursion would repeat the same call sites
+        Some(at) if same_source && at != pos && !error.calls.contains(&pos) => error.calls.push(pos),
+        _                                                                   => (),
+      }
+
+      self.close_upvalues(frame.base)
+    }
+
+    self.drop_handlers();
+
+    error
+  }
+
+  // Runs one instruction, true once the frame at `depth` has returned
+  fn step(&mut self, depth: usize) -> Result<bool, RuntimeError> {
+    use self::Instruction::*;
+
+    self.steps += 1;
+
+    if let Some(fuel) = self.limits.fuel {
+      if self.steps > fuel {
+        return Err(RuntimeError::fatal(format!("ran out of fuel after {} instructions", fuel)))
+      }
+    }
+
+    // the clock is only read every so often
+    if self.steps.is_multiple_of(1024) {
+      self.check_deadline()?
+    }
+
+    let (instruction, base) = {
+      let frame = self.frames.last_mut().unwrap();
+
+      frame.ip += 1;
+
+      (frame.closure.prototype.chunk.code[frame.ip - 1], frame.base)
+    };
+
+    match instruction {
+      Constant(index) => {
+        let value = self.constant(index);
+        self.stack.push(value)
+      },
+
+      Nil   => self.stack.push(Value::Nil),
+      True  => self.stack.push(Value::Bool(true)),
+      False => self.stack.push(Value::Bool(false)),
+
+      Pop => {
+        self.stack.pop();
+      },
+
+      Dup2 => {
+        let a = self.peek(1).clone();
+        let b = self.peek(0).clone();
+
+        self.stack.push(a);
+        self.stack.push(b)
+      },
+
+      GetLocal(slot) => {
+        let value = self.stack[base + slot as usize].clone();
+        self.stack.push(value)
+      },
+
+      SetLocal(slot) => self.stack[base + slot as usize] = self.peek(0).clone(),
+
+      GetUpvalue(slot) => {
+        let upvalue = self.frames.last().unwrap().closure.upvalues[slot as usize].clone();
+
+        let value = match *upvalue.borrow() {
+          Upvalue::Open(index)      => self.stack[index].clone(),
+          Upvalue::Closed(ref value) => value.clone(),
+        };
+
+        self.stack.push(value)
+      },
+
+      SetUpvalue(slot) => {
+        let upvalue = self.frames.last().unwrap().closure.upvalues[slot as usize].clone();
+        let value   = self.peek(0).clone();
+
+        let mut upvalue = upvalue.borrow_mut();
+
+        match *upvalue {
+          Upvalue::Open(index) => self.stack[index] = value,
+          Upvalue::Closed(_)   => *upvalue = Upvalue::Closed(value),
+        }
+      },
+
+      GetGlobal(name) => {
+        let name = self.constant(name).to_string();
+
+        match self.globals.get(&name).cloned() {
+          Some(value) => self.stack.push(value),
+          None        => return Err(format!("no such value `{}`", name).into()),
+        }
+      },
+
+      SetGlobal(name) => {
+        let name  = self.constant(name).to_string();
+        let value = self.peek(0).clone();
+
+        match self.globals.get_mut(&name) {
+          Some(slot) => *slot = value,
+          None       => return Err(format!("can't assign to undeclared `{}`", name).into()),
+        }
+      },
+
+      DefineGlobal(name) => {
+        let name  = self.constant(name).to_string();
+        let value = self.stack.pop().unwrap();
+
+        self.globals.set(&name, value);
+      },
+
+      Array(len) => {
+        self.allocate(len as usize * mem::size_of::<Value>())?;
+
+        let content = self.stack.split_off(self.stack.len() - len as usize);
+
+        self.stack.push(Value::Array(Rc::new(RefCell::new(content))))
+      },
+
+      Table(len) => {
+        self.allocate(len as usize * mem::size_of::<(String, Value)>())?;
+
+        let flat = self.stack.split_off(self.stack.len() - 2 * len as usize);
+
+        let mut content: Vec<(String, Value)> = Vec::new();
+
+        for pair in flat.chunks(2) {
+          let key = pair[0].to_string();
+
+          match content.iter().position(|(name, _)| *name == key) {
+            Some(i) => content[i].1 = pair[1].clone(),
+            None    => content.push((key, pair[1].clone())),
+          }
+        }
+
+        self.stack.push(Value::Table(Rc::new(RefCell::new(content))))
+      },
+
+      Spread => {
+        let value  = self.stack.pop().unwrap();
+        let target = self.peek(0).clone();
+
+        match target {
+          Value::Array(ref content) => {
+            let elements = match value {
+              Value::Array(ref elements) => elements.borrow().clone(),
+              Value::Str(ref s)          => s.chars().map(|c| Value::Str(c.to_string().into())).collect(),
+              ref value                  => return Err(format!("can't spread {} into an array", value.type_name()).into()),
+            };
+
+            self.allocate(elements.len() * mem::size_of::<Value>())?;
+
+            content.borrow_mut().extend(elements)
+          },
+
+          Value::Table(ref content) => {
+            let entries = match value {
+              Value::Table(ref entries)   => entries.borrow().clone(),
+              Value::Record(ref instance) => instance.field_names().iter().cloned().zip(instance.fields.borrow().iter().cloned()).collect(),
+              Value::Nil                  => Vec::new(),
+              ref value                   => return Err(format!("can't spread {} into a table", value.type_name()).into()),
+            };
+
+            self.allocate(entries.len() * mem::size_of::<(String, Value)>())?;
+
+            let mut content = content.borrow_mut();
+
+            for (key, value) in entries {
+              match content.iter().position(|(name, _)| *name == key) {
+                Some(i) => content[i].1 = value,
+                None    => content.push((key, value)),
+              }
+            }
+          },
+
+          _ => unreachable!(),
+        }
+      },
+
+      GetIndex => {
+        let index   = self.stack.pop().unwrap();
+        let indexed = self.stack.pop().unwrap();
+
+        match Self::index(&indexed, &index) {
+          Ok(value)    => self.stack.push(value),
+          Err(message) => return Err(message.into()),
+        }
+      },
+
+      SetIndex => {
+        let value   = self.stack.pop().unwrap();
+        let index   = self.stack.pop().unwrap();
+        let indexed = self.stack.pop().unwrap();
+
+        if let (Value::Array(content), &Value::Int(i)) = (&indexed, &index) {
+          let len = content.borrow().len() as i64;
+
+          if i >= len {
+            self.allocate((i - len + 1) as usize * mem::size_of::<Value>())?
+          }
+        }
+
+        if let Err(message) = Self::set_index(&indexed, index, value.clone()) {
+          return Err(message.into())
+        }
+
+        self.stack.push(value)
+      },
+
+      Method => {
+        let index    = self.stack.pop().unwrap();
+        let receiver = self.stack.pop().unwrap();
+
+        match Self::index(&receiver, &index) {
+          Ok(function) => {
+            self.stack.push(function);
+            self.stack.push(receiver)
+          },
+
+          Err(message) => return Err(message.into()),
+        }
+      },
+
+      Instance => {
+        let record = self.frames.last().unwrap().closure.prototype.clone();
+        let len    = record.params.len();
+
+        self.allocate(len * mem::size_of::<Value>())?;
+
+        let fields = self.stack[base .. base + len].to_vec();
+
+        self.stack.push(Value::Record(Rc::new(super::Instance { record, fields: RefCell::new(fields) })))
+      },
+
+      Construct => {
+        let named = match self.stack.pop().unwrap() {
+          Value::Table(content) => content.borrow().clone(),
+          _                     => unreachable!(),
+        };
+
+        let record = match *self.peek(0) {
+          Value::Function(ref closure) if closure.prototype.record => closure.prototype.clone(),
+          Value::Function(ref closure)                             => return Err(format!("`{}` isn't a record", closure.prototype.name).into()),
+          ref value                                                => return Err(format!("can't construct {}", value.type_name()).into()),
+        };
+
+        let mut args = vec!(Value::Nil; record.params.len());
+
+        for (key, value) in named {
+          match record.params.iter().position(|param| *param == key) {
+            Some(i) => args[i] = value,
+            None    => return Err(format!("`{}` has no field `{}`", record.name, key).into()),
+          }
+        }
+
+        self.stack.extend(args);
+
+        self.call_value(record.params.len())?;
+      },
+
+      Field(n) => match self.stack.pop().unwrap() {
+        Value::Table(content) => {
+          let value = content.borrow().get(n as usize).map_or(Value::Nil, |(_, value)| value.clone());
+
+          self.stack.push(value)
+        },
+
+        value => return Err(format!("can't take the fields of {}", value.type_name()).into()),
+      },
+
+      Range => {
+        let end   = self.stack.pop().unwrap();
+        let start = self.stack.pop().unwrap();
+
+        let (from, to) = match (&start, &end) {
+          (&Value::Int(a),   &Value::Int(b))   => (a as f64, b as f64),
+          (&Value::Int(a),   &Value::Float(b)) => (a as f64, b),
+          (&Value::Float(a), &Value::Int(b))   => (a, b as f64),
+          (&Value::Float(a), &Value::Float(b)) => (a, b),
+          _                                    => return Err(format!("can't make a range of {} and {}", start.type_name(), end.type_name()).into()),
+        };
+
+        let len = (to - from).ceil().max(0.0);
+
+        if len.is_nan() || len > u32::MAX as f64 {
+          return Err(format!("a range from {} to {} is too long", from, to).into())
+        }
+
+        let len = len as usize;
+
+        self.allocate(len * mem::size_of::<Value>())?;
+
+        // ints stay ints, anything else counts in floats
+        let content = match (start, end) {
+          (Value::Int(a), Value::Int(_)) => (0 .. len as i64).map(|i| Value::Int(a + i)).collect(),
+          _                              => (0 .. len).map(|i| Value::Float(from + i as f64)).collect(),
+        };
+
+        self.stack.push(Value::Array(Rc::new(RefCell::new(content))))
+      },
+
+      Slice => {
+        let end     = self.stack.pop().unwrap();
+        let start   = self.stack.pop().unwrap();
+        let indexed = self.stack.pop().unwrap();
+
+        let value = match indexed {
+          Value::Array(ref content) => {
+            let content = content.borrow();
+            let (a, b)  = Self::bounds(&start, &end, content.len())?;
+
+            self.allocate((b - a) * mem::size_of::<Value>())?;
+
+            Value::Array(Rc::new(RefCell::new(content[a .. b].to_vec())))
+          },
+
+          Value::Str(ref content) => {
+            let (a, b) = Self::bounds(&start, &end, content.chars().count())?;
+
+            self.allocate(b - a)?;
+
+            Value::Str(content.chars().skip(a).take(b - a).collect::<String>().into())
+          },
+
+          ref value => return Err(format!("can't slice {}", value.type_name()).into()),
+        };
+
+        self.stack.push(value)
+      },
+
+      Neg => match self.stack.pop().unwrap() {
+        Value::Int(n)   => self.stack.push(n.checked_neg().map_or(Value::Float(-(n as f64)), Value::Int)),
+        Value::Float(n) => self.stack.push(Value::Float(-n)),
+        value           => return Err(format!("can't negate {}", value.type_name()).into()),
+      },
+
+      Not => {
+        let value = self.stack.pop().unwrap();
+        self.stack.push(Value::Bool(!value.truthy()))
+      },
+
+      Add | Sub | Mul | Div | Mod | Pow | Concat | Eq | NEq | Lt | Gt | LtEq | GtEq => {
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        let value = Self::binary(instruction, a, b)?;
+
+        match value {
+          Value::Str(ref s)         if instruction == Concat => self.allocate(s.len())?,
+          Value::Array(ref content) if instruction == Concat => self.allocate(content.borrow().len() * mem::size_of::<Value>())?,
+          _                                                  => (),
+        }
+
+        self.stack.push(value)
+      },
+
+      Jump(target) => self.frames.last_mut().unwrap().ip = target,
+
+      JumpIfFalse(target) => if !self.peek(0).truthy() {
+        self.frames.last_mut().unwrap().ip = target
+      },
+
+      JumpIfNil(target) => if let Value::Nil = *self.peek(0) {
+        self.frames.last_mut().unwrap().ip = target
+      },
+
+      Call(argc) => {
+        self.call_value(argc as usize)?;
+      },
+
+      Apply => {
+        let args = match self.stack.pop().unwrap() {
+          Value::Array(content) => content.borrow().clone(),
+          _                     => unreachable!(),
+        };
+
+        let argc = args.len();
+
+        self.stack.extend(args);
+        self.call_value(argc)?;
+      },
+
+      Closure(index) => {
+        let closure = {
+          let enclosing = self.frames.last().unwrap().closure.clone();
+          let prototype = enclosing.prototype.chunk.functions[index as usize].clone();
+
+          let mut upvalues = Vec::new();
+
+          for &(is_local, slot) in prototype.upvalues.iter() {
+            upvalues.push(
+              if is_local {
+                self.capture(base + slot as usize)
+              } else {
+                enclosing.upvalues[slot as usize].clone()
+              }
+            )
+          }
+
+          super::Closure {
+            prototype,
+            upvalues,
+          }
+        };
+
+        self.stack.push(Value::Function(Rc::new(closure)))
+      },
+
+      Try(target) | Finally(target) => self.handlers.push(
+        Handler {
+          frames:  self.frames.len(),
+          stack:   self.stack.len(),
+          target,
+          finally: instruction == Finally(target),
+          pending: self.pending.len(),
+        }
+      ),
+
+      EndTry => {
+        self.handlers.pop();
+      },
+
+      Throw => {
+        let value = self.stack.pop().unwrap();
+
+        return Err(
+          RuntimeError {
+            thrown: Some(value.clone()),
+            ..RuntimeError::from(format!("uncaught {}", value.repr()))
+          }
+        )
+      },
+
+      // back to where the error was raised, so it's reported from there
+      Rethrow => {
+        let (error, ip) = self.pending.pop().unwrap();
+
+        self.frames.last_mut().unwrap().ip = ip;
+
+        return Err(error)
+      },
+
+      Iterate => {
+        let generator = match self.stack.pop().unwrap() {
+          Value::Generator(generator) => Value::Generator(generator),
+          Value::Array(content)       => Self::elements(content),
+
+          Value::Str(content) => {
+            let chars = content.chars().map(|c| Value::Str(c.to_string().into())).collect();
+
+            Self::elements(Rc::new(RefCell::new(chars)))
+          },
+
+          value => return Err(format!("can't loop over {}", value.type_name()).into()),
+        };
+
+        self.stack.push(generator)
+      },
+
+      Next => {
+        let generator = match self.stack.pop().unwrap() {
+          Value::Generator(generator) => generator,
+          _                           => unreachable!(),
+        };
+
+        let state = mem::replace(&mut *generator.borrow_mut(), Generator::Running);
+
+        match state {
+          Generator::Elements(content, i) => {
+            let element = content.borrow().get(i).cloned();
+
+            match element {
+              Some(element) => {
+                *generator.borrow_mut() = Generator::Elements(content, i + 1);
+
+                self.stack.push(element);
+                self.stack.push(Value::Bool(true))
+              },
+
+              None => {
+                *generator.borrow_mut() = Generator::Done;
+
+                self.stack.push(Value::Nil);
+                self.stack.push(Value::Bool(false))
+              },
+            }
+          },
+
+          // the frame goes back on top, it pushes the next value when it yields or returns
+          Generator::Suspended(suspended) => {
+            if self.frames.len() == FRAMES_MAX {
+              let message = format!("stack overflow resuming `{}`", suspended.closure.prototype.name);
+
+              *generator.borrow_mut() = Generator::Suspended(suspended);
+
+              return Err(message.into())
+            }
+
+            self.stack.push(Value::Generator(generator.clone()));
+
+            let base = self.stack.len();
+
+            self.stack.extend(suspended.stack);
+
+            self.frames.push(
+              Frame {
+                closure:   suspended.closure,
+                ip:        suspended.ip,
+                base,
+                generator: Some(generator),
+              }
+            );
+
+            for mut handler in suspended.handlers {
+              handler.frames  = self.frames.len();
+              handler.stack  += base;
+              handler.pending = self.pending.len();
+
+              self.handlers.push(handler)
+            }
+
+            // closures may have changed them in the meantime
+            for (slot, upvalue) in suspended.captured {
+              let value = mem::replace(&mut *upvalue.borrow_mut(), Upvalue::Open(base + slot));
+
+              if let Upvalue::Closed(value) = value {
+                self.stack[base + slot] = value
+              }
+
+              self.open_upvalues.push(upvalue)
+            }
+          },
+
+          Generator::Running => return Err("can't loop over a generator from within itself".into()),
+
+          Generator::Done => {
+            *generator.borrow_mut() = Generator::Done;
+
+            self.stack.push(Value::Nil);
+            self.stack.push(Value::Bool(false))
+          },
+        }
+      },
+
+      // the frame is set aside, taking along the `prøv` blocks it's in
+      Yield => {
+        let value = self.stack.pop().unwrap();
+        let frame = self.frames.pop().unwrap();
+
+        let mut handlers = Vec::new();
+
+        while self.handlers.last().is_some_and(|handler| handler.frames > self.frames.len()) {
+          let mut handler = self.handlers.pop().unwrap();
+
+          handler.stack -= base;
+          handlers.insert(0, handler)
+        }
+
+        // variables captured from it can't stay on a stack it's leaving, they're opened again when it's resumed
+        let captured = self.open_upvalues.iter().filter_map(|upvalue| match *upvalue.borrow() {
+          Upvalue::Open(index) if index >= base => Some((index - base, upvalue.clone())),
+          _                                     => None,
+        }).collect();
+
+        self.close_upvalues(base);
+
+        let mut stack = self.stack.split_off(base);
+
+        stack.push(Value::Nil);
+
+        self.stack.pop();
+
+        *frame.generator.unwrap().borrow_mut() = Generator::Suspended(
+          Suspended {
+            closure: frame.closure,
+            ip:      frame.ip,
+            stack,
+            handlers,
+            captured,
+          }
+        );
+
+        self.stack.push(value);
+        self.stack.push(Value::Bool(true))
+      },
+
+      Return => {
+        let result = self.stack.pop().unwrap();
+        let frame  = self.frames.pop().unwrap();
+
+        self.close_upvalues(base);
+        self.drop_handlers();
+
+        self.stack.truncate(base - 1);
+
+        // what a generator function returns is left out, like in a JS `for` loop
+        match frame.generator {
+          Some(generator) => {
+            *generator.borrow_mut() = Generator::Done;
+
+            self.stack.push(Value::Nil);
+            self.stack.push(Value::Bool(false))
+          },
+
+          None => self.stack.push(result),
+        }
+
+        return Ok(self.frames.len() == depth)
+      },
+    }
+
+    Ok(false)
+  }
+
+  // Calls the function below `argc` arguments on the stack, true when a new frame has to be run
+  fn call_value(&mut self, argc: usize) -> Result<bool, RuntimeError> {
+    let base = self.stack.len() - argc;
+
+    match self.stack[base - 1].clone() {
+      Value::Function(closure) => {
+        if self.frames.len() == FRAMES_MAX {
+          return Err(format!("stack overflow calling `{}`", closure.prototype.name).into())
+        }
+
+        // missing arguments are nil and extra ones are dropped, like in JS
+        if closure.prototype.rest {
+          let fixed = closure.prototype.params.len() - 1;
+          let rest  = self.stack.split_off(base + fixed.min(argc));
+
+          self.allocate(rest.len() * mem::size_of::<Value>())?;
+
+          self.stack.resize(base + fixed, Value::Nil);
+          self.stack.push(Value::Array(Rc::new(RefCell::new(rest))))
+        } else {
+          self.stack.truncate(base + closure.prototype.params.len().min(argc));
+        }
+
+        self.stack.resize(base + closure.prototype.locals.len(), Value::Nil);
+
+        // the body of a generator function runs as the generator is stepped through
+        if closure.prototype.generator {
+          self.allocate(closure.prototype.locals.len() * mem::size_of::<Value>())?;
+
+          let stack = self.stack.split_off(base);
+
+          self.stack.pop();
+
+          let suspended = Suspended {
+            closure,
+            ip:       0,
+            stack,
+            handlers: Vec::new(),
+            captured: Vec::new(),
+          };
+
+          self.stack.push(Value::Generator(Rc::new(RefCell::new(Generator::Suspended(suspended)))));
+
+          return Ok(false)
+        }
+
+        self.frames.push(
+          Frame {
+            closure,
+            ip:        0,
+            base,
+            generator: None,
+          }
+        );
+
+        Ok(true)
+      },
+
+      Value::Native(native) => {
+        let args = self.stack.split_off(base);
+
+        self.stack.pop();
+
+        let value = (native.function)(self, &args)?;
+
+        // what a native made is charged as if the script had made it
+        self.allocate(value.size())?;
+        self.check_deadline()?;
+
+        self.stack.push(value);
+
+        Ok(false)
+      },
+
+      value => Err(format!("can't call {}", value.type_name()).into()),
+    }
+  }
+
+
+
+  fn elements(content: Rc<RefCell<Vec<Value>>>) -> Value {
+    Value::Generator(Rc::new(RefCell::new(Generator::Elements(content, 0))))
+  }
+
+  // Handlers of frames that are gone, left by returning from within a `prøv`
+  fn drop_handlers(&mut self) {
+    while self.handlers.last().is_some_and(|handler| handler.frames > self.frames.len()) {
+      self.handlers.pop();
+    }
+  }
+
+  fn capture(&mut self, index: usize) -> Rc<RefCell<Upvalue>> {
+    for upvalue in self.open_upvalues.iter() {
+      if let Upvalue::Open(open) = *upvalue.borrow() {
+        if open == index {
+          return upvalue.clone()
+        }
+      }
+    }
+
+    let upvalue = Rc::new(RefCell::new(Upvalue::Open(index)));
+
+    self.open_upvalues.push(upvalue.clone());
+
+    upvalue
+  }
+
+  // Moves variables captured from stack slots at or above `base` into their upvalues
+  fn close_upvalues(&mut self, base: usize) {
+    let stack = &self.stack;
+
+    self.open_upvalues.retain(|upvalue| {
+      let index = match *upvalue.borrow() {
+        Upvalue::Open(index) => index,
+        Upvalue::Closed(_)   => return false,
+      };
+
+      if index < base {
+        return true
+      }
+
+      *upvalue.borrow_mut() = Upvalue::Closed(stack[index].clone());
+
+      false
+    })
+  }
+
+
+
+  // Charges what was made against the memory limit, natives making more than they return charge it too
+  pub fn allocate(&mut self, bytes: usize) -> Result<(), RuntimeError> {
+    self.allocated = self.allocated.saturating_add(bytes);
+
+    match self.limits.memory {
+      Some(memory) if self.allocated > memory => Err(RuntimeError::fatal(format!("ran out of memory, the limit is {} bytes", memory))),
+      _                                       => Ok(()),
+    }
+  }
+
+  // Fails once the timeout has passed, natives running for long check it as they go
+  pub fn check_deadline(&self) -> Result<(), RuntimeError> {
+    match self.deadline {
+      Some(deadline) if Instant::now() > deadline => Err(RuntimeError::fatal(format!("timed out after {:?}", self.limits.timeout.unwrap()))),
+      _                                           => Ok(()),
+    }
+  }
+
+  fn constant(&self, index: u16) -> Value {
+    self.frames.last().unwrap().closure.prototype.chunk.constants[index as usize].clone()
+  }
+
+  fn peek(&self, distance: usize) -> &Value {
+    &self.stack[self.stack.len() - 1 - distance]
+  }
+
+  fn index(indexed: &Value, index: &Value) -> Result<Value, String> {
+    let value = match (indexed, index) {
+      (Value::Array(content), &Value::Int(i)) => {
+        if i < 0 { None } else { content.borrow().get(i as usize).cloned() }
+      },
+
+      (Value::Str(content), &Value::Int(i)) => {
+        if i < 0 { None } else { content.chars().nth(i as usize).map(|c| Value::Str(c.to_string().into())) }
+      },
+
+      (Value::Table(content), Value::Str(key)) => {
+        content.borrow().iter().find(|&(name, _)| **name == **key).map(|(_, value)| value.clone())
+      },
+
+      (Value::Record(instance), Value::Str(key)) => match instance.field(key) {
+        Some(i) => Some(instance.fields.borrow()[i].clone()),
+        None    => return Err(format!("`{}` has no field `{}`", instance.name(), key)),
+      },
+
+      (Value::Array(content), Value::Str(key)) if &**key == "length" => Some(Value::Int(content.borrow().len() as i64)),
+      (Value::Str(content),   Value::Str(key)) if &**key == "length" => Some(Value::Int(content.chars().count() as i64)),
+
+      _ => return Err(format!("can't index {} with {}", indexed.type_name(), index.type_name())),
+    };
+
+    Ok(value.unwrap_or(Value::Nil))
+  }
+
+  // Where a slice starts and ends like JS `slice`, counting from the end when negative and never past it
+  fn bounds(start: &Value, end: &Value, len: usize) -> Result<(usize, usize), String> {
+    let bound = |value: &Value, missing: usize| {
+      let i = match *value {
+        Value::Nil      => return Ok(missing),
+        Value::Int(i)   => i,
+        Value::Float(n) => n as i64,
+        ref value       => return Err(format!("can't slice with {}", value.type_name())),
+      };
+
+      Ok(if i < 0 { (len as i64 + i).max(0) as usize } else { (i as usize).min(len) })
+    };
+
+    let start = bound(start, 0)?;
+    let end   = bound(end, len)?;
+
+    Ok((start, end.max(start)))
+  }
+
+  fn set_index(indexed: &Value, index: Value, value: Value) -> Result<(), String> {
+    match (indexed, index) {
+      (Value::Array(content), Value::Int(i)) => {
+        if i < 0 {
+          return Err(format!("can't assign to negative index {}", i))
+        }
+
+        let mut content = content.borrow_mut();
+        let i           = i as usize;
+
+        // like JS, writing past the end grows the array
+        if content.len() <= i {
+          content.resize(i + 1, Value::Nil)
+        }
+
+        content[i] = value
+      },
+
+      (Value::Table(content), Value::Str(ref key)) => {
+        let mut content = content.borrow_mut();
+
+        match content.iter().position(|(name, _)| **name == **key) {
+          Some(i) => content[i].1 = value,
+          None    => content.push((key.to_string(), value)),
+        }
+      },
+
+      (Value::Record(instance), Value::Str(ref key)) => match instance.field(key) {
+        Some(i) => instance.fields.borrow_mut()[i] = value,
+        None    => return Err(format!("`{}` has no field `{}`", instance.name(), key)),
+      },
+
+      (indexed, index) => return Err(format!("can't assign to {} index of {}", index.type_name(), indexed.type_name())),
+    }
+
+    Ok(())
+  }
+
+  fn binary(instruction: Instruction, a: Value, b: Value) -> Result<Value, String> {
+    use self::Instruction::*;
+    use self::Value::{ Int, Float, Str, Bool };
+
+    let value = match (instruction, &a, &b) {
+      (Eq,  _, _) => Bool(a == b),
+      (NEq, _, _) => Bool(a != b),
+
+      (Add, &Int(a), &Int(b)) => a.checked_add(b).map_or(Float(a as f64 + b as f64), Int),
+      (Sub, &Int(a), &Int(b)) => a.checked_sub(b).map_or(Float(a as f64 - b as f64), Int),
+      (Mul, &Int(a), &Int(b)) => a.checked_mul(b).map_or(Float(a as f64 * b as f64), Int),
+      (Mod, &Int(a), &Int(b)) if b != 0 => Int(a % b),
+      (Pow, &Int(a), &Int(b)) if b >= 0 && b <= u32::MAX as i64 => a.checked_pow(b as u32).map_or(Float((a as f64).powf(b as f64)), Int),
+
+      (Lt,   Str(a), Str(b)) => Bool(a < b),
+      (Gt,   Str(a), Str(b)) => Bool(a > b),
+      (LtEq, Str(a), Str(b)) => Bool(a <= b),
+      (GtEq, Str(a), Str(b)) => Bool(a >= b),
+
+      (Concat, Value::Array(a), Value::Array(b)) => {
+        let mut content = a.borrow().clone();
+
+        content.extend(b.borrow().iter().cloned());
+
+        Value::Array(Rc::new(RefCell::new(content)))
+      },
+
+      (Concat, &Str(_), _) | (Concat, _, &Str(_)) => Str(format!("{}{}", a, b).into()),
+
+      _ => {
+        let value = match (Self::number(&a), Self::number(&b)) {
+          (Some(x), Some(y)) => match instruction {
+            Add  => Some(Float(x + y)),
+            Sub  => Some(Float(x - y)),
+            Mul  => Some(Float(x * y)),
+            Div  => Some(Float(x / y)),
+            Mod  => Some(Float(x % y)),
+            Pow  => Some(Float(x.powf(y))),
+            Lt   => Some(Bool(x < y)),
+            Gt   => Some(Bool(x > y)),
+            LtEq => Some(Bool(x <= y)),
+            GtEq => Some(Bool(x >= y)),
+            _    => None,
+          },
+
+          _ => None,
+        };
+
+        match value {
+          Some(value) => value,
+          None        => return Err(format!("can't use `{}` on {} and {}", Self::operator(instruction), a.type_name(), b.type_name())),
+        }
+      },
+    };
+
+    Ok(value)
+  }
+
+  fn number(value: &Value) -> Option<f64> {
+    match *value {
+      Value::Int(n)   => Some(n as f64),
+      Value::Float(n) => Some(n),
+      _               => None,
+    }
+  }
+
+  fn operator(instruction: Instruction) -> &'static str {
+    use self::Instruction::*;
+
+    match instruction {
+      Add    => "+",
+      Sub    => "-",
+      Mul    => "*",
+      Div    => "/",
+      Mod    => "%",
+      Pow    => "^",
+      Concat => "++",
+      Lt     => "<",
+      Gt     => ">",
+      LtEq   => "<=",
+      GtEq   => ">=",
+      _      => "?",
+    }
+  }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::thread;
+
+  fn message(code: &str, globals: &mut Globals, limits: Limits) -> String {
+    let output = crate::run_str("<test>", code, globals, limits);
+
+    output.diagnostics.first().map_or(String::new(), |diagnostic| diagnostic.message.clone())
+  }
+
+  #[test]
+  fn charges_what_natives_make() {
+    let mut globals = Globals::new();
+
+    globals.native("big", |_, _| Ok(Value::Str("ø".repeat(1000).into())));
+
+    let limits = Limits {
+      memory: Some(1000),
+      ..Limits::default()
+    };
+
+    assert_eq!(message("big()\n", &mut globals, limits), "ran out of memory, the limit is 1000 bytes")
+  }
+
+  #[test]
+  fn times_out_within_natives() {
+    let mut globals = Globals::new();
+
+    globals.native("spin", |vm, _| loop { vm.check_deadline()? });
+    globals.native("nap",  |_, _| { thread::sleep(Duration::from_millis(20)); Ok(Value::Nil) });
+
+    let limits = Limits {
+      timeout: Some(Duration::from_millis(5)),
+      ..Limits::default()
+    };
+
+    assert_eq!(message("spin()\n", &mut globals, limits), "timed out after 5ms");
+    assert_eq!(message("nap()\n", &mut globals, limits), "timed out after 5ms")
+  }
+}